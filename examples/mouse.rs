@@ -23,8 +23,11 @@ fn main() {
             Event::Mouse(me) => {
                 match me {
                     MouseEvent::Press(_, a, b) |
+                    MouseEvent::DoubleClick(_, a, b) |
+                    MouseEvent::TripleClick(_, a, b) |
                     MouseEvent::Release(a, b) |
-                    MouseEvent::Hold(a, b) => {
+                    MouseEvent::Hold(a, b) |
+                    MouseEvent::Move(a, b) => {
                         write!(stdout, "{}", cursor::Goto(a, b)).unwrap();
                     }
                 }
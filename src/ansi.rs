@@ -0,0 +1,653 @@
+//! Handling text written with escape sequences, such as styled output.
+//!
+//! # Example
+//!
+//! ```rust
+//! use termion::{ansi, color};
+//!
+//! let text = format!("{}error{}: not found", color::Fg(color::Red), color::Fg(color::Reset));
+//! assert_eq!(ansi::strip(&text), "error: not found");
+//! assert_eq!(ansi::width(&text), 16);
+//! ```
+
+use std::cmp;
+use std::fmt::Write as FmtWrite;
+use std::io::{self, Write};
+use std::mem;
+
+use color::{AnsiValue, AnyColor, Rgb};
+use style::{Attributes, Style};
+
+/// Where a `Machine` is within the output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum State {
+    /// Not within a sequence.
+    Ground,
+    /// After an ESC byte.
+    Escape,
+    /// Within an escape sequence with intermediate bytes, such as `ESC ( B`.
+    EscapeIntermediate,
+    /// Within a CSI sequence.
+    Csi,
+    /// Within a string sequence such as OSC, which ends with BEL or ST.
+    Str,
+    /// After an ESC byte within a string sequence.
+    StrEscape,
+}
+
+/// Tells the escape sequences in output apart from the text, byte by byte.
+///
+/// This recognizes CSI sequences, string sequences (OSC, DCS, APC, PM and SOS) and other escape
+/// sequences. Everything interpreting output goes through it, so that they all agree on where the
+/// sequences end.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Machine {
+    state: State,
+}
+
+impl Machine {
+    pub(crate) fn new() -> Machine {
+        Machine { state: State::Ground }
+    }
+
+    /// Where the bytes so far ended.
+    pub(crate) fn state(&self) -> State {
+        self.state
+    }
+
+    /// Advance over `byte`, returning whether it belongs to an escape sequence.
+    pub(crate) fn advance(&mut self, byte: u8) -> bool {
+        if self.ends_before(byte) {
+            self.state = State::Ground;
+            return self.advance(byte);
+        }
+
+        let (state, sequence) = match (self.state, byte) {
+            (State::Ground, b'\x1B') => (State::Escape, true),
+            (State::Ground, _) => (State::Ground, false),
+            (State::Escape, b'[') => (State::Csi, true),
+            (State::Escape, b']') | (State::Escape, b'P') | (State::Escape, b'X') |
+            (State::Escape, b'^') | (State::Escape, b'_') => (State::Str, true),
+            (State::Escape, 0x20..=0x2F) |
+            (State::EscapeIntermediate, 0x20..=0x2F) => (State::EscapeIntermediate, true),
+            (State::Escape, _) | (State::EscapeIntermediate, _) => (State::Ground, true),
+            (State::Csi, 0x40..=0x7E) => (State::Ground, true),
+            (State::Csi, _) => (State::Csi, true),
+            (State::Str, b'\x07') => (State::Ground, true),
+            (State::Str, b'\x1B') => (State::StrEscape, true),
+            (State::Str, _) => (State::Str, true),
+            (State::StrEscape, b'\\') => (State::Ground, true),
+            (State::StrEscape, _) => (State::Str, true),
+        };
+        self.state = state;
+        sequence
+    }
+
+    /// Whether the escape sequence so far ends before `byte`, which is not part of it.
+    ///
+    /// Escape sequences end with an ASCII final byte, so anything else (such as the first byte of
+    /// a UTF-8 character) is left to the text after the sequence.
+    pub(crate) fn ends_before(&self, byte: u8) -> bool {
+        matches!(self.state, State::Escape | State::EscapeIntermediate) &&
+        !(0x20..=0x7E).contains(&byte)
+    }
+
+    /// Whether the bytes so far ended within a sequence.
+    pub(crate) fn in_sequence(&self) -> bool {
+        self.state != State::Ground
+    }
+}
+
+/// A part of output split by `Sequences`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Chunk<'a> {
+    /// Text between escape sequences.
+    Text(&'a [u8]),
+    /// A whole escape sequence, or one cut short by a byte that can't be part of it.
+    Sequence(&'a [u8]),
+}
+
+/// Splits output written in pieces into text and escape sequences, for writers rewriting the
+/// sequences.
+///
+/// A sequence split over several writes is kept until it is complete, so it is always passed on in
+/// one piece.
+#[derive(Debug, Clone)]
+pub(crate) struct Sequences {
+    machine: Machine,
+    pending: Vec<u8>,
+}
+
+impl Sequences {
+    pub(crate) fn new() -> Sequences {
+        Sequences {
+            machine: Machine::new(),
+            pending: Vec::new(),
+        }
+    }
+
+    /// Split `buf`, passing its chunks to `f` in order.
+    ///
+    /// This stops at the first error returned by `f`.
+    pub(crate) fn split<F>(&mut self, buf: &[u8], mut f: F) -> io::Result<()>
+        where F: FnMut(Chunk) -> io::Result<()>
+    {
+        let mut start = 0;
+        for (i, &byte) in buf.iter().enumerate() {
+            if self.machine.ends_before(byte) {
+                let sequence = mem::take(&mut self.pending);
+                f(Chunk::Sequence(&sequence))?;
+                start = i;
+            }
+            if !self.machine.advance(byte) {
+                continue;
+            }
+
+            if start < i {
+                f(Chunk::Text(&buf[start..i]))?;
+            }
+            self.pending.push(byte);
+            if !self.machine.in_sequence() {
+                let sequence = mem::take(&mut self.pending);
+                f(Chunk::Sequence(&sequence))?;
+            }
+            start = i + 1;
+        }
+        if start < buf.len() {
+            f(Chunk::Text(&buf[start..]))?;
+        }
+        Ok(())
+    }
+
+    /// The incomplete sequence at the end of the output so far.
+    pub(crate) fn pending(&self) -> &[u8] {
+        &self.pending
+    }
+}
+
+/// Remove all escape sequences from `text`, keeping the text and control characters such as
+/// newlines.
+pub fn strip(text: &str) -> String {
+    segments(text)
+        .filter_map(|segment| match segment {
+            Segment::Text(text) => Some(text),
+            Segment::Sequence(_) => None,
+        })
+        .collect()
+}
+
+/// Remove all escape sequences from `bytes`, like `strip`.
+pub fn strip_bytes(bytes: &[u8]) -> Vec<u8> {
+    let mut machine = Machine::new();
+    bytes.iter().cloned().filter(|&byte| !machine.advance(byte)).collect()
+}
+
+/// The number of columns `text` takes up when printed, ignoring escape sequences.
+///
+/// Wide characters, such as CJK ideographs and most emoji, take up two columns, while combining
+/// marks and other zero-width characters take up none (see `char_width`). The text is assumed to
+/// be a single line, so control characters such as tabs and newlines are not accounted for.
+///
+/// # Example
+///
+/// ```rust
+/// use termion::{ansi, style};
+///
+/// assert_eq!(ansi::width(&format!("{}日本{}e\u{301}", style::Bold, style::Reset)), 5);
+/// ```
+pub fn width(text: &str) -> usize {
+    segments(text)
+        .map(|segment| match segment {
+            Segment::Text(text) => text.chars().map(char_width).sum(),
+            Segment::Sequence(_) => 0,
+        })
+        .sum()
+}
+
+/// The number of columns a character takes up when printed.
+///
+/// This is 2 for East Asian wide and fullwidth characters and emoji, 0 for combining marks,
+/// zero-width characters and control characters, and 1 otherwise.
+pub fn char_width(c: char) -> usize {
+    let c = c as u32;
+    if c < 0x20 || (0x7F..0xA0).contains(&c) {
+        0
+    } else if c < 0x300 {
+        1
+    } else if in_table(c, &ZERO_WIDTH) {
+        0
+    } else if in_table(c, &WIDE) {
+        2
+    } else {
+        1
+    }
+}
+
+/// Whether `c` is in one of the sorted, inclusive ranges of `table`.
+fn in_table(c: u32, table: &[(u32, u32)]) -> bool {
+    table
+        .binary_search_by(|&(start, end)| if end < c {
+            cmp::Ordering::Less
+        } else if start > c {
+            cmp::Ordering::Greater
+        } else {
+            cmp::Ordering::Equal
+        })
+        .is_ok()
+}
+
+/// A part of text written with escape sequences, see `segments`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Segment<'a> {
+    /// Text between escape sequences.
+    Text(&'a str),
+    /// A whole escape sequence, or the beginning of one at the end of the text.
+    Sequence(&'a str),
+}
+
+/// Split `text` into the text and the escape sequences between it.
+///
+/// # Example
+///
+/// ```rust
+/// use termion::ansi::{self, Segment};
+///
+/// let segments: Vec<_> = ansi::segments("a\x1B[1mb").collect();
+/// assert_eq!(segments, [Segment::Text("a"), Segment::Sequence("\x1B[1m"), Segment::Text("b")]);
+/// ```
+pub fn segments(text: &str) -> Segments<'_> {
+    Segments { rest: text }
+}
+
+/// An iterator over the segments of text, see `segments`.
+#[derive(Debug, Clone)]
+pub struct Segments<'a> {
+    rest: &'a str,
+}
+
+impl<'a> Iterator for Segments<'a> {
+    type Item = Segment<'a>;
+
+    fn next(&mut self) -> Option<Segment<'a>> {
+        let bytes = self.rest.as_bytes();
+        let len = match bytes.first() {
+            None => return None,
+            Some(&b'\x1B') => {
+                let mut machine = Machine::new();
+                machine.advance(b'\x1B');
+                let mut len = bytes.len();
+                for (i, &byte) in bytes.iter().enumerate().skip(1) {
+                    if machine.ends_before(byte) {
+                        len = i;
+                        break;
+                    }
+                    machine.advance(byte);
+                    if !machine.in_sequence() {
+                        len = i + 1;
+                        break;
+                    }
+                }
+                len
+            }
+            Some(_) => bytes.iter().position(|&byte| byte == b'\x1B').unwrap_or(bytes.len()),
+        };
+
+        // Sequences end with ASCII bytes, so the split is at a character boundary.
+        let (segment, rest) = self.rest.split_at(len);
+        self.rest = rest;
+        Some(if segment.starts_with('\x1B') {
+            Segment::Sequence(segment)
+        } else {
+            Segment::Text(segment)
+        })
+    }
+}
+
+/// Split `text` into spans of text and the style they are printed with, e.g. for rendering
+/// captured output of another program again.
+///
+/// The spans start from the default style, and change it for every SGR sequence like a terminal
+/// would (see `Style::apply_sgr`). Other escape sequences are skipped.
+///
+/// # Example
+///
+/// ```rust
+/// use termion::{ansi, style};
+/// use termion::color::{self, AnyColor};
+///
+/// let text = format!("a{}b{}c", color::Fg(color::Red), style::Reset);
+/// let spans: Vec<_> = ansi::spans(&text).map(|(style, text)| (style.fg, text)).collect();
+/// assert_eq!(spans,
+///            [(AnyColor::Reset, "a"), (AnyColor::Indexed(1), "b"), (AnyColor::Reset, "c")]);
+/// ```
+pub fn spans(text: &str) -> Spans<'_> {
+    Spans {
+        segments: segments(text),
+        style: Style::default(),
+    }
+}
+
+/// An iterator over the styled spans of text, see `spans`.
+#[derive(Debug, Clone)]
+pub struct Spans<'a> {
+    segments: Segments<'a>,
+    /// The style of the next span.
+    style: Style,
+}
+
+impl<'a> Spans<'a> {
+    /// The style after the spans so far, which the next span is printed with.
+    pub fn style(&self) -> &Style {
+        &self.style
+    }
+}
+
+impl<'a> Iterator for Spans<'a> {
+    type Item = (Style, &'a str);
+
+    fn next(&mut self) -> Option<(Style, &'a str)> {
+        loop {
+            match self.segments.next()? {
+                Segment::Text(text) => return Some((self.style, text)),
+                Segment::Sequence(sequence) => {
+                    // Only SGR sequences, such as `ESC [ 1 ; 31 m`, without private markers.
+                    let params = sequence.strip_prefix("\x1B[").and_then(|s| s.strip_suffix('m'));
+                    if let Some(params) = params {
+                        if params.bytes().all(|b| b.is_ascii_digit() || b == b';' || b == b':') {
+                            self.style.apply_sgr(params);
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Convert styled text to HTML with inline styles, e.g. for showing logs of colored output on a
+/// web page.
+///
+/// The styles are taken from the spans of text (see `spans`), while other escape sequences are
+/// dropped. Line breaks are kept, so the HTML is meant to be put in a `<pre>` element. Indexed
+/// colors are shown like xterm by default, and default colors are left to the page.
+///
+/// # Example
+///
+/// ```rust
+/// use termion::{ansi, color, style};
+///
+/// let red = color::Fg(color::Rgb(255, 0, 0));
+/// let text = format!("{}<b>{} & {}c", style::Bold, red, style::Reset);
+/// assert_eq!(ansi::to_html(&text),
+///            "<span style=\"font-weight:bold\">&lt;b&gt;</span>\
+///             <span style=\"color:#ff0000;font-weight:bold\"> &amp; </span>c");
+/// ```
+pub fn to_html(text: &str) -> String {
+    let mut html = String::new();
+    // The CSS of the open `<span>` element, if any (empty otherwise).
+    let mut open = String::new();
+    for (style, text) in spans(text) {
+        let css = css(&style);
+        if css != open {
+            if !open.is_empty() {
+                html.push_str("</span>");
+            }
+            if !css.is_empty() {
+                let _ = write!(html, "<span style=\"{}\">", css);
+            }
+            open = css;
+        }
+        for c in text.chars() {
+            match c {
+                '&' => html.push_str("&amp;"),
+                '<' => html.push_str("&lt;"),
+                '>' => html.push_str("&gt;"),
+                '"' => html.push_str("&quot;"),
+                '\'' => html.push_str("&#39;"),
+                c => html.push(c),
+            }
+        }
+    }
+    if !open.is_empty() {
+        html.push_str("</span>");
+    }
+    html
+}
+
+/// The CSS declarations showing text in `style`.
+fn css(style: &Style) -> String {
+    let mut declarations = Vec::new();
+
+    // Inverted default colors are the colors of the page, swapped.
+    let (fg, bg) = if style.attributes.contains(Attributes::INVERT) {
+        (Some(css_color(style.bg).unwrap_or_else(|| "Canvas".to_string())),
+         Some(css_color(style.fg).unwrap_or_else(|| "CanvasText".to_string())))
+    } else {
+        (css_color(style.fg), css_color(style.bg))
+    };
+    if let Some(fg) = fg {
+        declarations.push(format!("color:{}", fg));
+    }
+    if let Some(bg) = bg {
+        declarations.push(format!("background-color:{}", bg));
+    }
+
+    let attributes = style.attributes;
+    if attributes.contains(Attributes::BOLD) {
+        declarations.push("font-weight:bold".to_string());
+    }
+    if attributes.contains(Attributes::FAINT) {
+        declarations.push("opacity:0.5".to_string());
+    }
+    if attributes.contains(Attributes::ITALIC) {
+        declarations.push("font-style:italic".to_string());
+    }
+
+    let lines: Vec<&str> = [(Attributes::UNDERLINE, "underline"),
+                            (Attributes::CURLY_UNDERLINE, "underline"),
+                            (Attributes::OVERLINE, "overline"),
+                            (Attributes::CROSSED_OUT, "line-through")]
+        .iter()
+        .filter(|&&(attribute, _)| attributes.contains(attribute))
+        .map(|&(_, line)| line)
+        .collect();
+    if !lines.is_empty() {
+        declarations.push(format!("text-decoration-line:{}", lines.join(" ")));
+        if attributes.contains(Attributes::CURLY_UNDERLINE) {
+            declarations.push("text-decoration-style:wavy".to_string());
+        }
+        if let Some(color) = css_color(style.underline_color) {
+            declarations.push(format!("text-decoration-color:{}", color));
+        }
+    }
+
+    if attributes.contains(Attributes::CONCEAL) {
+        declarations.push("visibility:hidden".to_string());
+    }
+    declarations.join(";")
+}
+
+/// The CSS color of `color`, unless it is the default color.
+fn css_color(color: AnyColor) -> Option<String> {
+    let rgb = match color {
+        AnyColor::Ansi(n) | AnyColor::Indexed(n) => Rgb::from(AnsiValue(n)),
+        AnyColor::Rgb(r, g, b) => Rgb(r, g, b),
+        AnyColor::Reset => return None,
+    };
+    Some(format!("#{:02x}{:02x}{:02x}", rgb.0, rgb.1, rgb.2))
+}
+
+/// A writer removing all escape sequences from the output written through it, e.g. for logging
+/// styled output to a file.
+///
+/// Sequences may be split across writes.
+///
+/// # Example
+///
+/// ```rust
+/// use std::io::Write;
+/// use termion::ansi::StripWriter;
+/// use termion::style;
+///
+/// let mut log = StripWriter::new(Vec::new());
+/// write!(log, "{}Done{}.", style::Bold, style::Reset).unwrap();
+/// assert_eq!(log.into_inner(), b"Done.");
+/// ```
+pub struct StripWriter<W: Write> {
+    output: W,
+    machine: Machine,
+}
+
+impl<W: Write> StripWriter<W> {
+    /// Remove the escape sequences written to `output`.
+    pub fn new(output: W) -> StripWriter<W> {
+        StripWriter {
+            output,
+            machine: Machine::new(),
+        }
+    }
+
+    /// Get a reference to the output.
+    pub fn get_ref(&self) -> &W {
+        &self.output
+    }
+
+    /// Get a mutable reference to the output.
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.output
+    }
+
+    /// Get back the output.
+    pub fn into_inner(self) -> W {
+        self.output
+    }
+}
+
+impl<W: Write> Write for StripWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut start = 0;
+        for (i, &byte) in buf.iter().enumerate() {
+            if self.machine.advance(byte) {
+                self.output.write_all(&buf[start..i])?;
+                start = i + 1;
+            }
+        }
+        self.output.write_all(&buf[start..])?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.output.flush()
+    }
+}
+
+/// Combining marks, emoji modifiers and other characters taking up no columns.
+const ZERO_WIDTH: [(u32, u32); 40] = [
+    (0x0300, 0x036F), (0x0483, 0x0489), (0x0591, 0x05BD), (0x05BF, 0x05BF), (0x05C1, 0x05C2),
+    (0x05C4, 0x05C5), (0x05C7, 0x05C7), (0x0610, 0x061A), (0x064B, 0x065F), (0x0670, 0x0670),
+    (0x06D6, 0x06DC), (0x06DF, 0x06E4), (0x06E7, 0x06E8), (0x06EA, 0x06ED), (0x0900, 0x0902),
+    (0x093A, 0x093A), (0x093C, 0x093C), (0x0941, 0x0948), (0x094D, 0x094D), (0x0951, 0x0957),
+    (0x0962, 0x0963), (0x0E31, 0x0E31), (0x0E34, 0x0E3A), (0x0E47, 0x0E4E), (0x1160, 0x11FF),
+    (0x1AB0, 0x1AFF), (0x1DC0, 0x1DFF), (0x200B, 0x200F), (0x202A, 0x202E), (0x2060, 0x2064),
+    (0x20D0, 0x20FF), (0x302A, 0x302D), (0x3099, 0x309A), (0xFE00, 0xFE0F), (0xFE20, 0xFE2F),
+    (0xFEFF, 0xFEFF), (0x1F3FB, 0x1F3FF), (0xE0001, 0xE0001), (0xE0020, 0xE007F),
+    (0xE0100, 0xE01EF),
+];
+
+/// East Asian wide and fullwidth characters and emoji, taking up two columns.
+const WIDE: [(u32, u32); 56] = [
+    (0x1100, 0x115F), (0x231A, 0x231B), (0x2329, 0x232A), (0x23E9, 0x23EC), (0x23F0, 0x23F0),
+    (0x23F3, 0x23F3), (0x25FD, 0x25FE), (0x2614, 0x2615), (0x2648, 0x2653), (0x267F, 0x267F),
+    (0x2693, 0x2693), (0x26A1, 0x26A1), (0x26AA, 0x26AB), (0x26BD, 0x26BE), (0x26C4, 0x26C5),
+    (0x26CE, 0x26CE), (0x26D4, 0x26D4), (0x26EA, 0x26EA), (0x26F2, 0x26F3), (0x26F5, 0x26F5),
+    (0x26FA, 0x26FA), (0x26FD, 0x26FD), (0x2705, 0x2705), (0x270A, 0x270B), (0x2728, 0x2728),
+    (0x274C, 0x274C), (0x274E, 0x274E), (0x2753, 0x2755), (0x2757, 0x2757), (0x2795, 0x2797),
+    (0x27B0, 0x27B0), (0x27BF, 0x27BF), (0x2B1B, 0x2B1C), (0x2B50, 0x2B50), (0x2B55, 0x2B55),
+    (0x2E80, 0x3029), (0x302E, 0x303E), (0x3041, 0x3098), (0x309B, 0xA4CF), (0xA960, 0xA97F),
+    (0xAC00, 0xD7A3), (0xF900, 0xFAFF), (0xFE10, 0xFE19), (0xFE30, 0xFE6F), (0xFF00, 0xFF60),
+    (0xFFE0, 0xFFE6), (0x16FE0, 0x16FE4), (0x17000, 0x18CFF), (0x1B000, 0x1B2FF),
+    (0x1F004, 0x1F004), (0x1F300, 0x1F3FA), (0x1F400, 0x1F64F), (0x1F680, 0x1F6FF),
+    (0x1F900, 0x1F9FF), (0x1FA70, 0x1FAFF), (0x20000, 0x3FFFD),
+];
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_strip() {
+        let text = "a\x1B[1;31mb\x1B]8;;http://x\x1B\\c\x1B]0;title\x07d\x1B(Be\x1B7\tü\n\x1B[";
+        assert_eq!(strip(text), "abcde\tü\n");
+        assert_eq!(strip_bytes(text.as_bytes()), "abcde\tü\n".as_bytes());
+
+        let segments: Vec<_> = segments("\x1B[mx\x1B]0;ü\x07\x1B[1").collect();
+        assert_eq!(segments,
+                   [Segment::Sequence("\x1B[m"),
+                    Segment::Text("x"),
+                    Segment::Sequence("\x1B]0;ü\x07"),
+                    Segment::Sequence("\x1B[1")]);
+
+        let mut out = StripWriter::new(Vec::new());
+        for chunk in text.as_bytes().chunks(2) {
+            out.write_all(chunk).unwrap();
+        }
+        assert_eq!(out.into_inner(), "abcde\tü\n".as_bytes());
+    }
+
+    #[test]
+    fn test_strip_unfinished_escape() {
+        // Escape sequences end before bytes which cannot end them, such as those of `ü`.
+        assert_eq!(strip("\x1Bü"), "ü");
+        assert_eq!(strip("a\x1B(ü\x1B\x1B[1mb"), "aüb");
+        assert_eq!(strip_bytes("\x1B(ü\x1B\n".as_bytes()), "ü\n".as_bytes());
+        assert_eq!(segments("\x1B(ü").collect::<Vec<_>>(),
+                   [Segment::Sequence("\x1B("), Segment::Text("ü")]);
+        assert_eq!(width("\x1B日"), 2);
+        assert_eq!(to_html("\x1B\u{e9}"), "\u{e9}");
+    }
+
+    #[test]
+    fn test_spans() {
+        use color::AnyColor;
+        use style::Attributes;
+
+        let text = "\x1B[1ma\x1B[>4;1m\x1B]0;t\x07b\x1B[31;4mc\x1B[22md\x1B[m\x1B[";
+        let bold = Style { attributes: Attributes::BOLD, ..Style::default() };
+        let red = Style {
+            fg: AnyColor::Ansi(1),
+            attributes: Attributes::BOLD | Attributes::UNDERLINE,
+            ..Style::default()
+        };
+        let underline = Style { attributes: Attributes::UNDERLINE, ..red };
+        let mut spans = spans(text);
+        assert_eq!(spans.by_ref().collect::<Vec<_>>(),
+                   [(bold, "a"), (bold, "b"), (red, "c"), (underline, "d")]);
+        assert_eq!(spans.style(), &Style::default());
+    }
+
+    #[test]
+    fn test_to_html() {
+        assert_eq!(to_html("plain 'text'\n"), "plain &#39;text&#39;\n");
+        assert_eq!(to_html("\x1B[7ma\x1B[31mb\x1B[5mc\x1B]0;t\x07d\x1B[m"),
+                   "<span style=\"color:Canvas;background-color:CanvasText\">a</span>\
+                    <span style=\"color:Canvas;background-color:#cd0000\">bcd</span>");
+        assert_eq!(to_html("\x1B[2;3;4:3;9;58;5;15;8mx"),
+                   "<span style=\"opacity:0.5;font-style:italic;\
+                    text-decoration-line:underline line-through;text-decoration-style:wavy;\
+                    text-decoration-color:#ffffff;visibility:hidden\">x</span>");
+        assert_eq!(to_html("\x1B[1m\x1B[m"), "");
+    }
+
+    #[test]
+    fn test_width() {
+        assert_eq!(width(""), 0);
+        assert_eq!(width("\x1B[31mabc\x1B[m\x1B]8;;http://x\x1B\\"), 3);
+        assert_eq!(width("한국어"), 6);
+        assert_eq!(width("ｆｕｌｌ"), 8);
+        assert_eq!(width("🎉!"), 3);
+        assert_eq!(width("e\u{301}\u{200B}x"), 2);
+        assert_eq!(char_width('\x07'), 0);
+        assert_eq!(char_width('ü'), 1);
+        assert_eq!(char_width('\u{3099}'), 0);
+        assert_eq!(char_width('\u{20000}'), 2);
+    }
+}
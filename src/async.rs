@@ -1,48 +1,494 @@
+use std::collections::VecDeque;
+use std::fs;
 use std::io::{self, Read};
-use std::sync::mpsc;
+#[cfg(feature = "futures")]
+use std::pin::Pin;
+use std::sync::{Arc, Condvar, Mutex, Weak};
+#[cfg(feature = "futures")]
+use std::task::{Context, Poll};
+use std::task::Waker;
 use std::thread;
+#[cfg(windows)]
+use std::time::Duration;
+
+#[cfg(feature = "futures")]
+use futures_core::Stream;
 
+#[cfg(feature = "futures")]
+use event::Event;
+#[cfg(windows)]
+use input::{ConsoleEvents, console_events};
+#[cfg(feature = "futures")]
+use input::{Events, TermRead};
+use signal::{self, Listener, Signal};
+use size::terminal_size;
+#[cfg(not(windows))]
 use tty;
 
 /// Construct an asynchronous handle to the TTY standard input.
 ///
 /// This allows you to read from standard input _without blocking_ the current thread.
 /// Specifically, it works by firing up another thread to handle the event stream, which will then
-/// be buffered in a mpsc queue, which will eventually be read by the current thread.
+/// be buffered in a bounded queue, which will eventually be read by the current thread. See
+/// `AsyncReader::bounded` for how it is limited.
 ///
 /// This will not read the piped standard input, but rather read from the TTY device, since reading
 /// asyncronized from piped input would rarely make sense. In other words, if you pipe standard
 /// output from another process, it won't be reflected in the stream returned by this function, as
 /// this represents the TTY device, and not the piped standard input.
+///
+/// On Windows, the console's input records are read instead (see `console_events`), and passed on
+/// as the sequences a terminal would send for their events.
 #[cfg(not(windows))]
 pub fn async_stdin() -> AsyncReader {
-    let (send, recv) = mpsc::channel();
+    spawn(|| tty::get_tty().unwrap(), None).unwrap()
+}
+
+#[cfg(windows)]
+pub fn async_stdin() -> AsyncReader {
+    spawn(None).unwrap()
+}
+
+/// Construct an asynchronous handle to the TTY standard input, which stops reading once
+/// `delimiter` has been read.
+///
+/// This is like `async_stdin`, except that the background thread exits after passing on the
+/// delimiter, so the input following it is left for whatever reads the TTY next (e.g. the shell,
+/// once a prompt has quit on `q`).
+#[cfg(not(windows))]
+pub fn async_stdin_until(delimiter: u8) -> AsyncReader {
+    spawn(|| tty::get_tty().unwrap(), Some(delimiter)).unwrap()
+}
+
+#[cfg(windows)]
+pub fn async_stdin_until(delimiter: u8) -> AsyncReader {
+    spawn(Some(delimiter)).unwrap()
+}
+
+/// Construct an asynchronous handle to the controlling terminal.
+///
+/// Like `async_stdin`, this reads from the TTY device, so programs whose standard input is a pipe
+/// (as in `foo | mytool`) still receive the keys typed. Unlike `async_stdin`, the device is
+/// opened right away, so failing to open it (e.g. without a controlling terminal) is returned as
+/// an error instead of panicking in the background thread.
+#[cfg(not(windows))]
+pub fn async_tty() -> io::Result<AsyncReader> {
+    let tty = tty::get_tty()?;
+    spawn(move || tty, None)
+}
+
+#[cfg(windows)]
+pub fn async_tty() -> io::Result<AsyncReader> {
+    spawn(None)
+}
+
+/// Start the background thread reading from the file returned by `open`, up to and including
+/// `delimiter`, if any.
+#[cfg(not(any(windows, target_os = "redox")))]
+fn spawn<F>(open: F, delimiter: Option<u8>) -> io::Result<AsyncReader>
+    where F: FnOnce() -> fs::File + Send + 'static
+{
+    let shared = Arc::new(Shared::new());
+    let send = shared.clone();
+    signal::listen(Arc::downgrade(&shared) as Weak<dyn Listener>);
+    let (stop, stopped) = pipe()?;
+    let thread = thread::spawn(move || {
+        forward(open(), stopped, delimiter, &send);
+        send.finish();
+    });
+
+    Ok(AsyncReader {
+        shared,
+        stop: Some(stop),
+        thread: Some(thread),
+    })
+}
 
+#[cfg(target_os = "redox")]
+fn spawn<F>(open: F, delimiter: Option<u8>) -> io::Result<AsyncReader>
+    where F: FnOnce() -> fs::File + Send + 'static
+{
+    let shared = Arc::new(Shared::new());
+    let send = shared.clone();
+    signal::listen(Arc::downgrade(&shared) as Weak<dyn Listener>);
+    // Without `poll` the thread can't be woken up, so it is left to exit on its own.
     thread::spawn(move || {
-        for i in tty::get_tty().unwrap().bytes() {
-            if send.send(i).is_err() {
-                return;
+        forward(open(), delimiter, &send);
+        send.finish();
+    });
+
+    Ok(AsyncReader {
+        shared,
+        stop: None,
+        thread: None,
+    })
+}
+
+/// Start the background thread reading the console's input records, up to and including the
+/// event whose sequence contains `delimiter`, if any.
+#[cfg(windows)]
+fn spawn(delimiter: Option<u8>) -> io::Result<AsyncReader> {
+    let events = console_events()?;
+    let shared = Arc::new(Shared::new());
+    let send = shared.clone();
+    // The console reports resizes itself, so the signal listener isn't needed.
+    let thread = thread::spawn(move || {
+        forward(events, delimiter, &send);
+        send.finish();
+    });
+
+    Ok(AsyncReader {
+        shared,
+        stop: None,
+        thread: Some(thread),
+    })
+}
+
+/// Create a pipe, returning its write and read end.
+#[cfg(not(any(windows, target_os = "redox")))]
+pub fn pipe() -> io::Result<(fs::File, fs::File)> {
+    use libc;
+    use std::os::unix::io::FromRawFd;
+
+    let mut fds = [0; 2];
+    if unsafe { libc::pipe(fds.as_mut_ptr()) } == -1 {
+        return Err(io::Error::last_os_error());
+    }
+
+    unsafe { Ok((fs::File::from_raw_fd(fds[1]), fs::File::from_raw_fd(fds[0]))) }
+}
+
+/// Push the bytes read from `source` to `send`, until the reader is gone, `delimiter` has been
+/// pushed or `stopped` becomes readable (which happens once its write end is closed).
+///
+/// The source is read without blocking while `send` is locked (see `Shared::read`), so no input is
+/// consumed after the reader has been closed.
+#[cfg(not(any(windows, target_os = "redox")))]
+fn forward(mut source: fs::File,
+           stopped: fs::File,
+           delimiter: Option<u8>,
+           send: &Shared) {
+    use libc;
+    use std::os::unix::io::AsRawFd;
+
+    unsafe {
+        let flags = libc::fcntl(source.as_raw_fd(), libc::F_GETFL);
+        if flags == -1 || libc::fcntl(source.as_raw_fd(), libc::F_SETFL, flags | libc::O_NONBLOCK)
+                          == -1 {
+            send.push(Err(io::Error::last_os_error()));
+            return;
+        }
+    }
+
+    let mut fds = [
+        libc::pollfd { fd: source.as_raw_fd(), events: libc::POLLIN, revents: 0 },
+        libc::pollfd { fd: stopped.as_raw_fd(), events: libc::POLLIN, revents: 0 },
+    ];
+
+    loop {
+        if !send.wait_for_room() {
+            return;
+        }
+
+        if unsafe { libc::poll(fds.as_mut_ptr(), 2, -1) } == -1 {
+            let err = io::Error::last_os_error();
+            if err.kind() == io::ErrorKind::Interrupted {
+                continue;
             }
+            send.push(Err(err));
+            return;
         }
-    });
 
-    AsyncReader {
-        recv: recv,
+        if fds[1].revents != 0 || !send.read(&mut source, delimiter) {
+            return;
+        }
     }
 }
 
+/// Push the sequences of the events read from `events` to `send`, until the reader is gone or
+/// `delimiter` has been pushed.
+///
+/// A blocking read of the console can't be interrupted, so the records are waited for with a
+/// timeout instead, checking whether the reader is gone in between.
 #[cfg(windows)]
-pub fn async_stdin() -> AsyncReader {
-    unimplemented!()
+fn forward(mut events: ConsoleEvents, delimiter: Option<u8>, send: &Shared) {
+    loop {
+        if !send.wait_for_room() {
+            return;
+        }
+
+        let event = match events.poll(Duration::from_millis(100)) {
+            Ok(Some(event)) => event,
+            Ok(None) => continue,
+            Err(err) => {
+                send.push(Err(err));
+                return;
+            }
+        };
+
+        let mut bytes = Vec::new();
+        // Every event of the console has a sequence.
+        event.write_to(&mut bytes).unwrap();
+        for &byte in &bytes {
+            if !send.push(Ok(byte)) || Some(byte) == delimiter {
+                return;
+            }
+        }
+    }
+}
+
+#[cfg(target_os = "redox")]
+fn forward(source: fs::File, delimiter: Option<u8>, send: &Shared) {
+    for i in source.bytes() {
+        let done = i.is_ok() && i.as_ref().ok() == delimiter.as_ref();
+        if !send.wait_for_room() || !send.push(i) || done {
+            return;
+        }
+    }
+}
+
+/// The default number of bytes an `AsyncReader` buffers.
+const DEFAULT_CAPACITY: usize = 4096;
+
+/// What an `AsyncReader` does with input arriving while its buffer is full.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Overflow {
+    /// Discard the oldest buffered byte to make room for the new one.
+    DropOldest,
+    /// Discard the new byte.
+    DropNewest,
+    /// Stop reading until there is room, leaving further input with the TTY.
+    Block,
+}
+
+/// The buffer between the background thread and the `AsyncReader`.
+struct Buffer {
+    /// The bytes read, oldest first.
+    bytes: VecDeque<io::Result<u8>>,
+    /// The maximum number of bytes buffered.
+    capacity: usize,
+    /// What to do when the buffer is full.
+    overflow: Overflow,
+    /// Whether the reader is gone, so nothing should be buffered anymore.
+    closed: bool,
+    /// Whether the background thread has exited, so nothing more will be buffered.
+    finished: bool,
+}
+
+impl Buffer {
+    /// Buffer `byte`, applying the overflow policy if full. Returns `false` if the reader is gone.
+    fn push(&mut self, byte: io::Result<u8>) -> bool {
+        if self.closed {
+            return false;
+        }
+
+        if self.bytes.len() >= self.capacity {
+            match self.overflow {
+                Overflow::DropOldest => {
+                    self.bytes.pop_front();
+                }
+                Overflow::DropNewest => return true,
+                // The room was waited for before reading the byte, so it is only full if the
+                // capacity has shrunk since. Keep the byte anyway rather than losing it.
+                Overflow::Block => {}
+            }
+        }
+
+        self.bytes.push_back(byte);
+        true
+    }
+}
+
+/// The state shared by the background thread and the `AsyncReader`.
+struct Shared {
+    buffer: Mutex<Buffer>,
+    /// Notified when the buffer has room or is closed.
+    room: Condvar,
+    /// The task to wake up when input is buffered, see `EventStream`.
+    waker: Mutex<Option<Waker>>,
+}
+
+impl Shared {
+    fn new() -> Shared {
+        Shared {
+            buffer: Mutex::new(Buffer {
+                bytes: VecDeque::new(),
+                capacity: DEFAULT_CAPACITY,
+                overflow: Overflow::Block,
+                closed: false,
+                finished: false,
+            }),
+            room: Condvar::new(),
+            waker: Mutex::new(None),
+        }
+    }
+
+    /// With `Overflow::Block`, wait until there is room in the buffer. Returns `false` if the
+    /// reader is gone.
+    fn wait_for_room(&self) -> bool {
+        let mut buffer = self.buffer.lock().unwrap();
+        while buffer.overflow == Overflow::Block && buffer.bytes.len() >= buffer.capacity &&
+              !buffer.closed {
+            buffer = self.room.wait(buffer).unwrap();
+        }
+        !buffer.closed
+    }
+
+    /// Buffer `byte`, applying the overflow policy if full. Returns `false` if the reader is gone.
+    fn push(&self, byte: io::Result<u8>) -> bool {
+        let pushed = self.buffer.lock().unwrap().push(byte);
+        self.wake();
+        pushed
+    }
+
+    /// Read a byte from the non-blocking `source` and buffer it. Returns `false` once there is
+    /// nothing left to read, because the reader is gone, the source has ended or `delimiter` has
+    /// been read.
+    ///
+    /// The buffer stays locked while reading, so the reader can't be closed in the meantime, and no
+    /// input is consumed once it has been.
+    #[cfg(not(any(windows, target_os = "redox")))]
+    fn read(&self, source: &mut fs::File, delimiter: Option<u8>) -> bool {
+        let keep_reading = {
+            let mut buffer = self.buffer.lock().unwrap();
+            if buffer.closed {
+                return false;
+            }
+
+            let mut byte = [0];
+            match source.read(&mut byte) {
+                Ok(0) => return false,
+                Ok(_) => buffer.push(Ok(byte[0])) && Some(byte[0]) != delimiter,
+                // The input was taken by another reader of the source meanwhile.
+                Err(ref err) if err.kind() == io::ErrorKind::WouldBlock ||
+                                err.kind() == io::ErrorKind::Interrupted => return true,
+                Err(err) => buffer.push(Err(err)),
+            }
+        };
+        self.wake();
+        keep_reading
+    }
+
+    /// Buffer `bytes` even if full, since they must not be torn apart.
+    fn insert(&self, bytes: &[u8]) {
+        {
+            let mut buffer = self.buffer.lock().unwrap();
+            if !buffer.closed {
+                buffer.bytes.extend(bytes.iter().map(|&b| Ok(b)));
+            }
+        }
+        self.wake();
+    }
+
+    /// Mark the background thread as exited.
+    fn finish(&self) {
+        self.buffer.lock().unwrap().finished = true;
+        self.wake();
+    }
+
+    /// Wake up the task waiting for input, if any.
+    fn wake(&self) {
+        if let Some(waker) = self.waker.lock().unwrap().take() {
+            waker.wake();
+        }
+    }
+
+    /// Wake up the task of `waker` once input is buffered. Returns whether the background thread
+    /// has exited, in which case nothing more will be.
+    #[cfg(feature = "futures")]
+    fn register(&self, waker: &Waker) -> bool {
+        *self.waker.lock().unwrap() = Some(waker.clone());
+        self.buffer.lock().unwrap().finished
+    }
+
+    /// Mark the reader as gone, waking up the thread if it waits for room.
+    fn close(&self) {
+        self.buffer.lock().unwrap().closed = true;
+        self.room.notify_all();
+    }
+}
+
+impl Listener for Shared {
+    fn signal(&self, signal: Signal) {
+        match signal {
+            // Report the new size like xterm does when asked with `ESC [ 18 t`.
+            Signal::Resize => {
+                if let Ok((width, height)) = terminal_size() {
+                    self.insert(format!("\x1B[8;{};{}t", height, width).as_bytes());
+                }
+            }
+            // These have no sequences, so they are reported by the event iterators instead.
+            Signal::Suspend | Signal::Resume | Signal::Interrupt => self.wake(),
+        }
+    }
 }
 
 /// An asynchronous reader.
 ///
 /// This acts as any other stream, with the exception that reading from it won't block. Instead,
 /// the buffer will only be partially updated based on how much the internal buffer holds.
+///
+/// Dropping the reader (or calling `close`) stops its background thread and waits for it to exit.
 pub struct AsyncReader {
-    /// The underlying mpsc receiver.
-    recv: mpsc::Receiver<io::Result<u8>>,
+    /// The buffer filled by the background thread.
+    shared: Arc<Shared>,
+    /// The write end of the pipe telling the thread to stop, which it does once this is closed.
+    stop: Option<fs::File>,
+    /// The background thread reading the input.
+    thread: Option<thread::JoinHandle<()>>,
+}
+
+impl AsyncReader {
+    /// Limit the buffer to `capacity` bytes, handling input beyond that according to `overflow`.
+    ///
+    /// By default, 4096 bytes are buffered with `Overflow::Block`, so nothing is lost, but large
+    /// pastes (or floods of mouse motion) stay with the TTY until read. The dropping policies
+    /// instead keep the thread draining the TTY, losing input when it isn't read in time.
+    ///
+    /// # Panics
+    ///
+    /// This panics if `capacity` is zero.
+    pub fn bounded(self, capacity: usize, overflow: Overflow) -> AsyncReader {
+        assert!(capacity > 0, "The buffer must have room for at least one byte.");
+
+        {
+            let mut buffer = self.shared.buffer.lock().unwrap();
+            buffer.capacity = capacity;
+            buffer.overflow = overflow;
+            while buffer.bytes.len() > capacity {
+                match overflow {
+                    Overflow::DropOldest => buffer.bytes.pop_front(),
+                    _ => buffer.bytes.pop_back(),
+                };
+            }
+        }
+        self.shared.room.notify_all();
+
+        self
+    }
+
+    /// Whether the background thread has stopped reading, because the input ended or the
+    /// delimiter of `async_stdin_until` was read. Nothing is buffered after that.
+    pub fn is_finished(&self) -> bool {
+        self.shared.buffer.lock().unwrap().finished
+    }
+
+    /// Stop the background thread and wait for it to exit.
+    ///
+    /// Input not read yet is discarded, and the thread reads no further bytes from the TTY, so
+    /// they are left to whatever reads it next. This is the same as dropping the reader.
+    pub fn close(self) {}
+}
+
+impl Drop for AsyncReader {
+    fn drop(&mut self) {
+        self.shared.close();
+        self.stop.take();
+        if let Some(thread) = self.thread.take() {
+            // A panic in the thread has already been reported, and there's nothing left to do.
+            let _ = thread.join();
+        }
+    }
 }
 
 // FIXME: Allow constructing an async reader from an arbitrary stream.
@@ -56,25 +502,87 @@ impl Read for AsyncReader {
     fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
         let mut total = 0;
 
-        loop {
-            if total >= buf.len() {
-                break;
-            }
-
-            match self.recv.try_recv() {
-                Ok(Ok(b)) => {
-                    buf[total] = b;
-                    total += 1;
-                },
-                Ok(Err(e)) => return Err(e),
-                Err(_) => break,
+        {
+            let mut buffer = self.shared.buffer.lock().unwrap();
+            while total < buf.len() {
+                match buffer.bytes.pop_front() {
+                    Some(Ok(b)) => {
+                        buf[total] = b;
+                        total += 1;
+                    },
+                    Some(Err(e)) => {
+                        self.shared.room.notify_all();
+                        return Err(e);
+                    },
+                    None => break,
+                }
             }
         }
+        self.shared.room.notify_all();
 
         Ok(total)
     }
 }
 
+/// A stream of the input events read by an `AsyncReader`, for async runtimes.
+///
+/// The task polling the stream is woken up whenever the background thread buffers input (or a
+/// reported signal is received), so terminal input can be awaited alongside sockets and timers,
+/// e.g. with `select!`. The stream ends once the background thread exits, see
+/// `AsyncReader::is_finished`.
+///
+/// This needs the `futures` feature.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// extern crate futures_core;
+/// extern crate termion;
+///
+/// use futures_core::Stream;
+/// use termion::{EventStream, async_stdin};
+///
+/// fn is_stream<S: Stream>(_: &S) {}
+///
+/// let events = EventStream::new(async_stdin());
+/// is_stream(&events);
+/// ```
+#[cfg(feature = "futures")]
+pub struct EventStream {
+    /// The events of the reader.
+    events: Events<AsyncReader>,
+    /// The state shared with the reader's background thread.
+    shared: Arc<Shared>,
+}
+
+#[cfg(feature = "futures")]
+impl EventStream {
+    /// Stream the events read by `reader`.
+    pub fn new(reader: AsyncReader) -> EventStream {
+        EventStream {
+            shared: reader.shared.clone(),
+            events: reader.events(),
+        }
+    }
+}
+
+#[cfg(feature = "futures")]
+impl Stream for EventStream {
+    type Item = io::Result<Event>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>)
+                 -> Poll<Option<io::Result<Event>>> {
+        // Register before reading, so input buffered meanwhile wakes the task up again.
+        let finished = self.shared.register(cx.waker());
+        match self.events.next() {
+            Some(event) => Poll::Ready(Some(event)),
+            // Everything buffered has been read, so only incomplete input may be left.
+            None if finished => Poll::Ready(self.events.flush_pending().map(Ok)),
+            None => Poll::Pending,
+        }
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -85,4 +593,187 @@ mod test {
         let stdin = async_stdin();
         stdin.bytes().next();
     }
+
+    #[test]
+    fn test_async_tty() {
+        // There may not be a controlling terminal to open.
+        if let Ok(mut tty) = async_tty() {
+            assert!(tty.read(&mut [0]).unwrap() <= 1);
+        }
+    }
+
+    #[cfg(not(any(windows, target_os = "redox")))]
+    #[test]
+    fn test_close() {
+        use std::io::Write;
+        use std::time::Duration;
+
+        let (mut write, mut read) = pipe().unwrap();
+        let source = read.try_clone().unwrap();
+        let mut reader = spawn(move || source, None).unwrap();
+
+        write.write_all(b"a").unwrap();
+        let mut buf = [0];
+        while reader.read(&mut buf).unwrap() == 0 {
+            thread::sleep(Duration::from_millis(1));
+        }
+        assert_eq!(&buf, b"a");
+
+        // Once closed, the thread must neither linger nor consume any more input.
+        reader.close();
+        write.write_all(b"b").unwrap();
+        read.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf, b"b");
+    }
+
+    #[cfg(not(any(windows, target_os = "redox")))]
+    #[test]
+    fn test_close_while_reading() {
+        use std::io::Write;
+        use std::time::Duration;
+
+        let (mut write, mut read) = pipe().unwrap();
+        let source = read.try_clone().unwrap();
+        let reader = spawn(move || source, None).unwrap();
+
+        // Close the reader while the thread is about to read the pending input.
+        {
+            let mut buffer = reader.shared.buffer.lock().unwrap();
+            write.write_all(b"a").unwrap();
+            thread::sleep(Duration::from_millis(20));
+            buffer.closed = true;
+        }
+        reader.close();
+
+        let mut buf = [0];
+        assert_eq!(read.read(&mut buf).unwrap(), 1);
+        assert_eq!(&buf, b"a");
+    }
+
+    #[cfg(not(any(windows, target_os = "redox")))]
+    #[test]
+    fn test_until() {
+        use std::io::Write;
+
+        let (mut write, mut read) = pipe().unwrap();
+        let source = read.try_clone().unwrap();
+        let mut reader = spawn(move || source, Some(b'q')).unwrap();
+
+        write.write_all(b"abq").unwrap();
+        // The thread exits after the delimiter.
+        reader.thread.take().unwrap().join().unwrap();
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).unwrap();
+        assert_eq!(bytes, b"abq");
+
+        write.write_all(b"c").unwrap();
+        let mut buf = [0];
+        read.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf, b"c");
+    }
+
+    #[cfg(not(any(windows, target_os = "redox")))]
+    #[test]
+    fn test_bounded() {
+        use std::io::Write;
+
+        let (mut write, mut read) = pipe().unwrap();
+        let source = read.try_clone().unwrap();
+        let mut reader = spawn(move || source, None).unwrap().bounded(2, Overflow::Block);
+
+        // The thread stops reading when the buffer is full, leaving the rest in the pipe.
+        write.write_all(b"abcd").unwrap();
+        while reader.shared.buffer.lock().unwrap().bytes.len() < 2 {
+            thread::yield_now();
+        }
+        let mut buf = [0; 2];
+        read.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf, b"cd");
+        let mut buf = [0; 4];
+        assert_eq!(reader.read(&mut buf).unwrap(), 2);
+        assert_eq!(&buf[..2], b"ab");
+
+        let mut reader = reader.bounded(2, Overflow::DropOldest);
+        write.write_all(b"efgh").unwrap();
+        loop {
+            if let Some(&Ok(b'h')) = reader.shared.buffer.lock().unwrap().bytes.back() {
+                break;
+            }
+            thread::yield_now();
+        }
+        assert_eq!(reader.read(&mut buf).unwrap(), 2);
+        assert_eq!(&buf[..2], b"gh");
+    }
+
+    #[cfg(all(feature = "futures", not(any(windows, target_os = "redox"))))]
+    #[test]
+    fn test_event_stream() {
+        use event::Key;
+        use std::io::Write;
+        use std::task::Wake;
+        use std::time::Duration;
+
+        struct Unpark(thread::Thread);
+
+        impl Wake for Unpark {
+            fn wake(self: Arc<Self>) {
+                self.0.unpark();
+            }
+        }
+
+        let waker = Waker::from(Arc::new(Unpark(thread::current())));
+        let mut cx = Context::from_waker(&waker);
+        let (mut write, read) = pipe().unwrap();
+        let mut stream = EventStream::new(spawn(move || read, None).unwrap());
+        assert_eq!(Pin::new(&mut stream).poll_next(&mut cx).map(|_| ()), Poll::Pending);
+
+        let mut next = |stream: &mut EventStream| loop {
+            match Pin::new(&mut *stream).poll_next(&mut cx) {
+                Poll::Ready(event) => return event.map(|event| event.unwrap()),
+                // Time out eventually, in case a wake-up is missed.
+                Poll::Pending => thread::park_timeout(Duration::from_secs(1)),
+            }
+        };
+
+        write.write_all(b"a\x1B[A").unwrap();
+        assert_eq!(next(&mut stream), Some(Event::Key(Key::Char('a'))));
+        assert_eq!(next(&mut stream), Some(Event::Key(Key::Up)));
+
+        // The stream ends along with its input, reporting what is left of it.
+        write.write_all(b"\xC3").unwrap();
+        drop(write);
+        assert_eq!(next(&mut stream), Some(Event::Unsupported(b"\xC3".to_vec())));
+        assert_eq!(next(&mut stream), None);
+    }
+
+    #[cfg(not(any(windows, target_os = "redox")))]
+    #[test]
+    fn test_resize() {
+        use libc;
+        use std::time::Duration;
+
+        let (width, height) = match terminal_size() {
+            Ok(size) => size,
+            // The size can't be reported without a terminal.
+            Err(_) => return,
+        };
+
+        let (_write, read) = pipe().unwrap();
+        let mut reader = spawn(move || read, None).unwrap();
+        signal::report_signal(Signal::Resize).unwrap();
+        unsafe {
+            libc::raise(libc::SIGWINCH);
+        }
+
+        let expected = format!("\x1B[8;{};{}t", height, width).into_bytes();
+        let mut bytes = Vec::new();
+        for _ in 0..1000 {
+            reader.read_to_end(&mut bytes).unwrap();
+            if bytes.len() >= expected.len() {
+                break;
+            }
+            thread::sleep(Duration::from_millis(1));
+        }
+        assert_eq!(bytes, expected);
+    }
 }
@@ -0,0 +1,998 @@
+//! A grid of cells to draw frames into, writing only the changes to the terminal.
+//!
+//! # Example
+//!
+//! ```rust,no_run
+//! use std::io::{Write, stdout};
+//! use termion::buffer::{Buffer, Cell};
+//! use termion::color;
+//!
+//! let stdout = stdout();
+//! let mut stdout = stdout.lock();
+//!
+//! let prev = Buffer::new(80, 24);
+//! let mut next = prev.clone();
+//! next.set(0, 0, Cell { ch: 'x', fg: color::Red.into(), ..Cell::default() });
+//! next.flush_diff(&mut stdout, &prev).unwrap();
+//! stdout.flush().unwrap();
+//! ```
+
+use std::io::{self, Write};
+
+use ansi::{self, char_width};
+use color::AnyColor;
+use cursor::{Goto, Right};
+use style::{Attributes, Style, StyleState};
+
+/// A character cell of a buffer, with the style it is printed in.
+///
+/// Wide characters, such as CJK ideographs, take up two cells: the cell of the character, and a
+/// continuation cell to the right of it, with the character `'\0'`. The default cell is a blank
+/// in the default style.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Cell {
+    /// The character, which has to be printable (or `'\0'` for continuation cells).
+    pub ch: char,
+    /// The foreground color.
+    pub fg: AnyColor,
+    /// The background color.
+    pub bg: AnyColor,
+    /// The attributes, such as bold text.
+    pub attrs: Attributes,
+}
+
+impl Default for Cell {
+    fn default() -> Cell {
+        Cell::new(' ')
+    }
+}
+
+impl Cell {
+    /// A transparent cell, showing the cell below it when composited (see `Cell::over`).
+    ///
+    /// Its character is the noncharacter U+FFFF, which Unicode reserves for internal use such as
+    /// this. Transparent cells may have colors and attributes, which are merged into those of the
+    /// cell below, e.g. for a shaded background. They are meant for overlays, so buffers with them
+    /// should not be written.
+    pub const TRANSPARENT: Cell = Cell {
+        ch: '\u{FFFF}',
+        fg: AnyColor::Reset,
+        bg: AnyColor::Reset,
+        attrs: Attributes::NONE,
+    };
+
+    /// A cell of `ch` in the default style.
+    pub fn new(ch: char) -> Cell {
+        Cell {
+            ch,
+            fg: AnyColor::Reset,
+            bg: AnyColor::Reset,
+            attrs: Attributes::NONE,
+        }
+    }
+
+    /// A cell of `ch` in `style`. The underline color of the style is left out.
+    pub fn styled(ch: char, style: &Style) -> Cell {
+        Cell {
+            ch,
+            fg: style.fg,
+            bg: style.bg,
+            attrs: style.attributes,
+        }
+    }
+
+    /// Whether the character is transparent, like that of `Cell::TRANSPARENT`.
+    pub fn is_transparent(&self) -> bool {
+        self.ch == Cell::TRANSPARENT.ch
+    }
+
+    /// The cell shown when this cell is composited over `base`.
+    ///
+    /// The default colors of this cell are see-through, showing the colors of `base`, except for
+    /// the foreground color of characters, which are opaque. Transparent cells show the character
+    /// of `base`, with the attributes of both.
+    pub fn over(&self, base: &Cell) -> Cell {
+        let merge = |color, base| if color == AnyColor::Reset { base } else { color };
+        if self.is_transparent() {
+            Cell {
+                ch: base.ch,
+                fg: merge(self.fg, base.fg),
+                bg: merge(self.bg, base.bg),
+                attrs: self.attrs | base.attrs,
+            }
+        } else {
+            Cell { bg: merge(self.bg, base.bg), ..*self }
+        }
+    }
+
+    /// Whether this is the continuation cell of a wide character to the left of it.
+    pub fn is_continuation(&self) -> bool {
+        self.ch == '\0'
+    }
+
+    /// The style the cell is printed in.
+    pub fn style(&self) -> Style {
+        Style {
+            fg: self.fg,
+            bg: self.bg,
+            attributes: self.attrs,
+            ..Style::default()
+        }
+    }
+}
+
+/// A rectangle of cells, given by its top left corner and size.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Rect {
+    /// The column of the left edge.
+    pub x: u16,
+    /// The row of the top edge.
+    pub y: u16,
+    /// The number of columns.
+    pub width: u16,
+    /// The number of rows.
+    pub height: u16,
+}
+
+impl Rect {
+    /// A rectangle with the given top left corner and size.
+    pub fn new(x: u16, y: u16, width: u16, height: u16) -> Rect {
+        Rect { x, y, width, height }
+    }
+
+    /// Whether the rectangle has no cells.
+    pub fn is_empty(&self) -> bool {
+        self.width == 0 || self.height == 0
+    }
+
+    /// The column after the right edge.
+    pub fn right(&self) -> u16 {
+        self.x.saturating_add(self.width)
+    }
+
+    /// The row below the bottom edge.
+    pub fn bottom(&self) -> u16 {
+        self.y.saturating_add(self.height)
+    }
+
+    /// Whether the given position is within the rectangle.
+    pub fn contains(&self, x: u16, y: u16) -> bool {
+        (self.x..self.right()).contains(&x) && (self.y..self.bottom()).contains(&y)
+    }
+
+    /// The part of the rectangle within `other`, which is empty if they do not overlap.
+    pub fn intersection(&self, other: &Rect) -> Rect {
+        let (x, y) = (self.x.max(other.x), self.y.max(other.y));
+        Rect {
+            x,
+            y,
+            width: self.right().min(other.right()).saturating_sub(x),
+            height: self.bottom().min(other.bottom()).saturating_sub(y),
+        }
+    }
+
+    /// The rectangle shrunk by `margin` cells on every side.
+    pub fn inner(&self, margin: u16) -> Rect {
+        Rect {
+            x: self.x.saturating_add(margin),
+            y: self.y.saturating_add(margin),
+            width: self.width.saturating_sub(margin.saturating_mul(2)),
+            height: self.height.saturating_sub(margin.saturating_mul(2)),
+        }
+    }
+}
+
+/// A grid of cells, such as a frame of a terminal application.
+///
+/// Positions are zero-based columns and rows, so `(0, 0)` is the top left cell, which is written
+/// to the top left corner of the terminal.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Buffer {
+    width: u16,
+    height: u16,
+    /// The cells, row by row.
+    cells: Vec<Cell>,
+}
+
+impl Buffer {
+    /// A buffer of blank cells with the given number of columns and rows.
+    pub fn new(width: u16, height: u16) -> Buffer {
+        Buffer {
+            width,
+            height,
+            cells: vec![Cell::default(); width as usize * height as usize],
+        }
+    }
+
+    /// The number of columns.
+    pub fn width(&self) -> u16 {
+        self.width
+    }
+
+    /// The number of rows.
+    pub fn height(&self) -> u16 {
+        self.height
+    }
+
+    /// The rectangle of all cells.
+    pub fn area(&self) -> Rect {
+        Rect::new(0, 0, self.width, self.height)
+    }
+
+    /// The cell at the given position, if it is within the buffer.
+    pub fn get(&self, x: u16, y: u16) -> Option<&Cell> {
+        self.index(x, y).map(|i| &self.cells[i])
+    }
+
+    /// A mutable reference to the cell at the given position, if it is within the buffer.
+    ///
+    /// Changing the character this way does not place wide characters, unlike `set`.
+    pub fn get_mut(&mut self, x: u16, y: u16) -> Option<&mut Cell> {
+        self.index(x, y).map(move |i| &mut self.cells[i])
+    }
+
+    /// Set the cell at the given position. Positions outside of the buffer are ignored.
+    ///
+    /// A wide character sets the continuation cell to the right of it as well, or is replaced by
+    /// a blank in the last column. Wide characters partly overwritten are replaced by blanks, and
+    /// zero-width characters (such as combining marks) are ignored.
+    pub fn set(&mut self, x: u16, y: u16, cell: Cell) {
+        let i = match self.index(x, y) {
+            Some(i) => i,
+            None => return,
+        };
+        let wide = match char_width(cell.ch) {
+            0 => return,
+            width => width == 2,
+        };
+
+        self.split_wide(x, i);
+        if wide && x + 1 < self.width {
+            self.split_wide(x + 1, i + 1);
+            self.cells[i] = cell;
+            self.cells[i + 1] = Cell { ch: '\0', ..cell };
+        } else if wide {
+            self.cells[i] = Cell { ch: ' ', ..cell };
+        } else {
+            self.cells[i] = cell;
+        }
+    }
+
+    /// Set the cells from the given position on to the characters of `text` in `style`, like
+    /// `set`, returning the column after the text.
+    ///
+    /// The text is a single line without escape sequences, and is cut off at the last column.
+    pub fn set_str(&mut self, x: u16, y: u16, text: &str, style: &Style) -> u16 {
+        let area = self.area();
+        self.view(area).set_str(x, y, text, style)
+    }
+
+    /// Replace the wide character partly covered by the cell at column `x` and index `i`, which
+    /// is about to be overwritten, by blanks.
+    fn split_wide(&mut self, x: u16, i: usize) {
+        if self.cells[i].is_continuation() && x > 0 {
+            self.cells[i - 1].ch = ' ';
+        } else if char_width(self.cells[i].ch) == 2 && x + 1 < self.width &&
+                  self.cells[i + 1].is_continuation() {
+            self.cells[i + 1].ch = ' ';
+        }
+    }
+
+    /// Make every cell blank.
+    pub fn clear(&mut self) {
+        for cell in &mut self.cells {
+            *cell = Cell::default();
+        }
+    }
+
+    /// Composite `overlay` over the cells from the given position on, e.g. for drawing a popup
+    /// over the screen without drawing the screen again. The overlay is clipped to the buffer.
+    pub fn composite(&mut self, overlay: &Buffer, x: u16, y: u16) {
+        let area = self.area();
+        self.view(area).composite(overlay, x, y);
+    }
+
+    /// A view of the cells within `rect`, for drawing into them without affecting the others.
+    pub fn view(&mut self, rect: Rect) -> View<'_> {
+        View {
+            rect: rect.intersection(&self.area()),
+            buffer: self,
+        }
+    }
+
+    /// Write every cell, e.g. for the first frame or after the screen was cleared.
+    pub fn draw<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        self.write_cells(w, |_, _| true)
+    }
+
+    /// Write the cells which differ from `prev`, the buffer which was written last, moving the
+    /// cursor and changing the style as little as possible.
+    ///
+    /// If `prev` is of another size, every cell is written. The style is reset afterwards.
+    pub fn flush_diff<W: Write>(&self, w: &mut W, prev: &Buffer) -> io::Result<()> {
+        if prev.width == self.width && prev.height == self.height {
+            self.write_cells(w, |i, cell| prev.cells[i] != *cell)
+        } else {
+            self.draw(w)
+        }
+    }
+
+    /// The index of the cell at the given position, if it is within the buffer.
+    fn index(&self, x: u16, y: u16) -> Option<usize> {
+        if x < self.width && y < self.height {
+            Some(y as usize * self.width as usize + x as usize)
+        } else {
+            None
+        }
+    }
+
+    /// Write the cells for which `changed` is true, given their index and the cell.
+    fn write_cells<W, F>(&self, w: &mut W, changed: F) -> io::Result<()>
+        where W: Write,
+              F: Fn(usize, &Cell) -> bool
+    {
+        // The output is written at once, so it is not flushed halfway through a frame.
+        let mut out = Vec::new();
+        let mut state = StyleState::new();
+        // The position of the cursor, if known.
+        let mut cursor = None;
+
+        let width = self.width as usize;
+        for (i, cell) in self.cells.iter().enumerate() {
+            // Continuation cells are written with their wide character.
+            if cell.is_continuation() || !changed(i, cell) {
+                continue;
+            }
+
+            let position = ((i % width) as u16, (i / width) as u16);
+            if cursor != Some(position) {
+                self.move_cursor(&mut out, cursor, position, state.current());
+            }
+            state.transition(&mut out, &cell.style())?;
+            write!(out, "{}", cell.ch)?;
+            // The cursor stays in the last column until the next character wraps.
+            let next = position.0 + char_width(cell.ch) as u16;
+            cursor = if next < self.width {
+                Some((next, position.1))
+            } else {
+                None
+            };
+        }
+
+        if state.current().is_some() {
+            state.transition(&mut out, &Style::default())?;
+        }
+        w.write_all(&out)
+    }
+
+    /// Move the cursor from `from`, if known, to `to` with the fewest bytes, given the current
+    /// style.
+    fn move_cursor(&self,
+                   out: &mut Vec<u8>,
+                   from: Option<(u16, u16)>,
+                   to: (u16, u16),
+                   style: Option<&Style>) {
+        let goto = Goto(to.0 + 1, to.1 + 1).to_string();
+        match from {
+            Some((x, y)) if y == to.1 && x < to.0 => {
+                let right = Right(to.0 - x).to_string();
+                // Writing the cells in between again is shorter, if they are in this style.
+                let start = y as usize * self.width as usize + x as usize;
+                let between = &self.cells[start..start + (to.0 - x) as usize];
+                let rewrite = between.len() < right.len() &&
+                              between.iter().all(|cell| {
+                                  matches!(cell.ch, ' '..='~') && Some(&cell.style()) == style
+                              });
+                if rewrite {
+                    out.extend(between.iter().map(|cell| cell.ch as u8));
+                } else if right.len() < goto.len() {
+                    out.extend_from_slice(right.as_bytes());
+                } else {
+                    out.extend_from_slice(goto.as_bytes());
+                }
+            }
+            Some((_, y)) if to == (0, y + 1) => out.extend_from_slice(b"\r\n"),
+            _ => out.extend_from_slice(goto.as_bytes()),
+        }
+    }
+}
+
+/// A rectangle of a buffer to draw into, e.g. by a widget, made by `Buffer::view`.
+///
+/// Positions are relative to the top left corner of the rectangle, and the cells outside of it
+/// are left alone: drawing beyond its edges is clipped.
+///
+/// # Example
+///
+/// ```rust
+/// use termion::buffer::{Buffer, Rect};
+/// use termion::style::Style;
+///
+/// let mut buffer = Buffer::new(10, 3);
+/// buffer.view(Rect::new(2, 1, 3, 1)).set_str(0, 0, "Hello", &Style::default());
+/// assert_eq!(buffer.get(4, 1).unwrap().ch, 'l');
+/// assert_eq!(buffer.get(5, 1).unwrap().ch, ' ');
+/// ```
+#[derive(Debug)]
+pub struct View<'a> {
+    buffer: &'a mut Buffer,
+    /// The rectangle of the buffer, which is within it.
+    rect: Rect,
+}
+
+impl<'a> View<'a> {
+    /// The rectangle of the buffer this is a view of.
+    pub fn rect(&self) -> Rect {
+        self.rect
+    }
+
+    /// The number of columns.
+    pub fn width(&self) -> u16 {
+        self.rect.width
+    }
+
+    /// The number of rows.
+    pub fn height(&self) -> u16 {
+        self.rect.height
+    }
+
+    /// The cell at the given position, if it is within the view.
+    pub fn get(&self, x: u16, y: u16) -> Option<&Cell> {
+        if x < self.rect.width && y < self.rect.height {
+            self.buffer.get(self.rect.x + x, self.rect.y + y)
+        } else {
+            None
+        }
+    }
+
+    /// Set the cell at the given position, like `Buffer::set`. Positions outside of the view are
+    /// ignored, and wide characters in its last column are replaced by blanks.
+    pub fn set(&mut self, x: u16, y: u16, cell: Cell) {
+        if x >= self.rect.width || y >= self.rect.height {
+            return;
+        }
+        let cell = if char_width(cell.ch) == 2 && x + 1 == self.rect.width {
+            Cell { ch: ' ', ..cell }
+        } else {
+            cell
+        };
+        self.buffer.set(self.rect.x + x, self.rect.y + y, cell);
+    }
+
+    /// Set the cells from the given position on to the characters of `text`, like
+    /// `Buffer::set_str`, cut off at the edge of the view. Returns the column after the text.
+    pub fn set_str(&mut self, x: u16, y: u16, text: &str, style: &Style) -> u16 {
+        let mut x = x;
+        for ch in text.chars() {
+            let width = char_width(ch) as u16;
+            if width == 0 {
+                continue;
+            }
+            if x as u32 + width as u32 > self.rect.width as u32 {
+                break;
+            }
+            self.set(x, y, Cell::styled(ch, style));
+            x += width;
+        }
+        x
+    }
+
+    /// Write styled `text` into the view from its top left corner on, wrapping it at spaces to fit
+    /// its width. Returns the number of rows the text takes, which may be more than the view
+    /// has, so the rows beyond are left out.
+    ///
+    /// The text is styled with escape sequences (see `ansi::spans`), and lines are broken at
+    /// newlines as well. Words wider than the view are broken anywhere.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use termion::buffer::{Buffer, Rect};
+    /// use termion::style;
+    ///
+    /// let mut buffer = Buffer::new(10, 2);
+    /// let text = format!("Hello {}wide{} world", style::Bold, style::Reset);
+    /// assert_eq!(buffer.view(Rect::new(0, 0, 10, 2)).write_wrapped(&text), 2);
+    /// assert_eq!(buffer.get(0, 1).unwrap().ch, 'w');
+    /// ```
+    pub fn write_wrapped(&mut self, text: &str) -> u16 {
+        let width = self.rect.width as u32;
+        if width == 0 {
+            return 0;
+        }
+
+        let mut y = 0u16;
+        for line in styled_lines(text) {
+            let mut x = 0;
+            // The spaces before the next word, which are left out at the end of a row.
+            let mut spaces: &[(char, Style)] = &[];
+            let mut start = 0;
+            while start < line.len() {
+                let space = line[start].0 == ' ';
+                let len = line[start..].iter().take_while(|&&(ch, _)| (ch == ' ') == space).count();
+                let run = &line[start..start + len];
+                start += len;
+                if space {
+                    spaces = run;
+                    continue;
+                }
+
+                let word_width: u32 = run.iter().map(|&(ch, _)| char_width(ch) as u32).sum();
+                if x > 0 && x + spaces.len() as u32 + word_width > width {
+                    y = y.saturating_add(1);
+                    x = 0;
+                    spaces = &[];
+                }
+                for &(ch, ref style) in spaces.iter().chain(run) {
+                    let ch_width = char_width(ch) as u32;
+                    if x + ch_width > width {
+                        y = y.saturating_add(1);
+                        x = 0;
+                    }
+                    self.set(x as u16, y, Cell::styled(ch, style));
+                    x += ch_width;
+                }
+                spaces = &[];
+            }
+            y = y.saturating_add(1);
+        }
+        y
+    }
+
+    /// Write the first line of styled `text` into the given row of the view, cutting it off with
+    /// `ellipsis` (e.g. `"…"`) if it is wider than the view.
+    ///
+    /// The text is styled with escape sequences (see `ansi::spans`), and the ellipsis is written
+    /// in the style of the text it replaces.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use termion::buffer::{Buffer, Rect};
+    ///
+    /// let mut buffer = Buffer::new(6, 1);
+    /// buffer.view(Rect::new(0, 0, 6, 1)).write_truncated(0, "日本語です", "…");
+    /// let row: String = (0..6).map(|x| buffer.get(x, 0).unwrap().ch).collect();
+    /// assert_eq!(row, "日\0本\0… ");
+    /// ```
+    pub fn write_truncated(&mut self, y: u16, text: &str, ellipsis: &str) {
+        let line = styled_lines(text).swap_remove(0);
+        let width = self.rect.width as u32;
+        let text_width: u32 = line.iter().map(|&(ch, _)| char_width(ch) as u32).sum();
+        let limit = if text_width <= width {
+            width
+        } else {
+            width.saturating_sub(ansi::width(ellipsis) as u32)
+        };
+
+        let mut x = 0;
+        for &(ch, ref style) in &line {
+            let ch_width = char_width(ch) as u32;
+            if x + ch_width > limit {
+                self.set_str(x as u16, y, ellipsis, style);
+                return;
+            }
+            self.set(x as u16, y, Cell::styled(ch, style));
+            x += ch_width;
+        }
+    }
+
+    /// Composite `overlay` over the cells from the given position on, like `Buffer::composite`,
+    /// clipped to the view.
+    pub fn composite(&mut self, overlay: &Buffer, x: u16, y: u16) {
+        let width = overlay.width as usize;
+        for (i, cell) in overlay.cells.iter().enumerate() {
+            let (dx, dy) = ((i % width) as u16, (i / width) as u16);
+            let (x, y) = match (x.checked_add(dx), y.checked_add(dy)) {
+                (Some(x), Some(y)) => (x, y),
+                _ => continue,
+            };
+            // The continuation cells are set with their wide characters.
+            let base = match self.get(x, y) {
+                Some(base) if !cell.is_continuation() => *base,
+                _ => continue,
+            };
+            if cell.is_transparent() {
+                let i = self.buffer.index(self.rect.x + x, self.rect.y + y).unwrap();
+                self.buffer.cells[i] = cell.over(&base);
+            } else {
+                self.set(x, y, cell.over(&base));
+            }
+        }
+    }
+
+    /// Make every cell of the view blank.
+    pub fn clear(&mut self) {
+        for y in 0..self.rect.height {
+            for x in 0..self.rect.width {
+                self.set(x, y, Cell::default());
+            }
+        }
+    }
+
+    /// A view of the cells within `rect`, relative to this view and clipped to it.
+    pub fn view(&mut self, rect: Rect) -> View<'_> {
+        let rect = Rect {
+            x: self.rect.x.saturating_add(rect.x),
+            y: self.rect.y.saturating_add(rect.y),
+            ..rect
+        };
+        View {
+            rect: rect.intersection(&self.rect),
+            buffer: self.buffer,
+        }
+    }
+}
+
+/// The characters of styled `text` with their styles, split into lines and without zero-width
+/// and control characters.
+fn styled_lines(text: &str) -> Vec<Vec<(char, Style)>> {
+    let mut lines = vec![Vec::new()];
+    for (style, text) in ansi::spans(text) {
+        for ch in text.chars() {
+            if ch == '\n' {
+                lines.push(Vec::new());
+            } else if char_width(ch) > 0 {
+                lines.last_mut().unwrap().push((ch, style));
+            }
+        }
+    }
+    lines
+}
+
+/// A pair of buffers for drawing frames: the back buffer, which is drawn into, and the front
+/// buffer, which has been written to the terminal.
+///
+/// The rows changed since the last frame are tracked, so presenting a frame only compares and
+/// writes those. This cuts down the output a lot, especially over slow connections. The back
+/// buffer keeps its content after presenting, so only the changes have to be drawn.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use std::io::{Write, stdout};
+/// use termion::buffer::{Cell, DoubleBuffer};
+///
+/// let stdout = stdout();
+/// let mut stdout = stdout.lock();
+///
+/// let mut screen = DoubleBuffer::new(80, 24);
+/// for (x, ch) in "Hello".chars().enumerate() {
+///     screen.set(x as u16, 0, Cell::new(ch));
+/// }
+/// screen.present(&mut stdout).unwrap();
+/// stdout.flush().unwrap();
+/// ```
+#[derive(Debug, Clone)]
+pub struct DoubleBuffer {
+    front: Buffer,
+    back: Buffer,
+    /// Whether each row of the back buffer may have changed since the last frame.
+    dirty: Vec<bool>,
+    /// Whether the front buffer does not match the terminal, so every cell is written.
+    invalid: bool,
+}
+
+impl DoubleBuffer {
+    /// A pair of blank buffers with the given number of columns and rows. The first frame writes
+    /// every cell.
+    pub fn new(width: u16, height: u16) -> DoubleBuffer {
+        DoubleBuffer {
+            front: Buffer::new(width, height),
+            back: Buffer::new(width, height),
+            dirty: vec![false; height as usize],
+            invalid: true,
+        }
+    }
+
+    /// The number of columns.
+    pub fn width(&self) -> u16 {
+        self.back.width
+    }
+
+    /// The number of rows.
+    pub fn height(&self) -> u16 {
+        self.back.height
+    }
+
+    /// The back buffer, which is drawn into.
+    pub fn back(&self) -> &Buffer {
+        &self.back
+    }
+
+    /// The front buffer, which has been written to the terminal.
+    pub fn front(&self) -> &Buffer {
+        &self.front
+    }
+
+    /// The cell of the back buffer at the given position, if it is within the buffers.
+    pub fn get(&self, x: u16, y: u16) -> Option<&Cell> {
+        self.back.get(x, y)
+    }
+
+    /// A mutable reference to the cell of the back buffer at the given position, if it is within
+    /// the buffers, like `Buffer::get_mut`. Its row is taken as damaged.
+    pub fn get_mut(&mut self, x: u16, y: u16) -> Option<&mut Cell> {
+        self.damage(y);
+        self.back.get_mut(x, y)
+    }
+
+    /// Set the cell of the back buffer at the given position, like `Buffer::set`.
+    pub fn set(&mut self, x: u16, y: u16, cell: Cell) {
+        self.damage(y);
+        self.back.set(x, y, cell);
+    }
+
+    /// Set the cells of the back buffer from the given position on to the characters of `text`,
+    /// like `Buffer::set_str`.
+    pub fn set_str(&mut self, x: u16, y: u16, text: &str, style: &Style) -> u16 {
+        self.damage(y);
+        self.back.set_str(x, y, text, style)
+    }
+
+    /// A view of the cells of the back buffer within `rect`, like `Buffer::view`. Its rows are
+    /// taken as damaged.
+    pub fn view(&mut self, rect: Rect) -> View<'_> {
+        let rect = rect.intersection(&self.back.area());
+        for y in rect.y..rect.bottom() {
+            self.damage(y);
+        }
+        self.back.view(rect)
+    }
+
+    /// Composite `overlay` over the cells of the back buffer from the given position on, like
+    /// `Buffer::composite`.
+    pub fn composite(&mut self, overlay: &Buffer, x: u16, y: u16) {
+        for dy in 0..overlay.height {
+            self.damage(y.saturating_add(dy));
+        }
+        self.back.composite(overlay, x, y);
+    }
+
+    /// Make every cell of the back buffer blank.
+    pub fn clear(&mut self) {
+        self.back.clear();
+        for dirty in &mut self.dirty {
+            *dirty = true;
+        }
+    }
+
+    /// Change the size of the buffers, e.g. when the terminal was resized, making every cell
+    /// blank. The next frame writes every cell.
+    pub fn resize(&mut self, width: u16, height: u16) {
+        *self = DoubleBuffer::new(width, height);
+    }
+
+    /// Take the given row as changed.
+    fn damage(&mut self, y: u16) {
+        if let Some(dirty) = self.dirty.get_mut(y as usize) {
+            *dirty = true;
+        }
+    }
+
+    /// Write every cell in the next frame, e.g. after the screen was cleared elsewhere.
+    pub fn invalidate(&mut self) {
+        self.invalid = true;
+    }
+
+    /// Write the cells of the back buffer which changed since the last frame, and take them as
+    /// written.
+    pub fn present<W: Write>(&mut self, w: &mut W) -> io::Result<()> {
+        let width = self.back.width as usize;
+        if self.invalid {
+            self.back.draw(w)?;
+        } else {
+            let (front, dirty) = (&self.front, &self.dirty);
+            self.back.write_cells(w, |i, cell| dirty[i / width] && front.cells[i] != *cell)?;
+        }
+
+        for (y, dirty) in self.dirty.iter_mut().enumerate() {
+            if *dirty || self.invalid {
+                let row = y * width..(y + 1) * width;
+                self.front.cells[row.clone()].copy_from_slice(&self.back.cells[row]);
+                *dirty = false;
+            }
+        }
+        self.invalid = false;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use color::Red;
+
+    #[test]
+    fn test_flush_diff() {
+        let prev = Buffer::new(4, 3);
+        let mut next = prev.clone();
+        next.set(1, 0, Cell::new('a'));
+        next.set(3, 0, Cell { ch: 'b', fg: Red.into(), ..Cell::default() });
+        next.set(0, 1, Cell::new('c'));
+        next.set(2, 1, Cell::new('d'));
+        next.set(0, 2, Cell { attrs: Attributes::BOLD, ..Cell::new('e') });
+        next.set(4, 0, Cell::new('x'));
+        assert_eq!(next.get(4, 0), None);
+        assert_eq!(next.get(3, 0).map(|cell| cell.ch), Some('b'));
+
+        let mut out = Vec::new();
+        next.flush_diff(&mut out, &prev).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(),
+                   "\x1B[1;2H\x1B[0ma \x1B[31mb\x1B[2;1H\x1B[0mc d\r\n\x1B[1me\x1B[0m");
+
+        let mut out = Vec::new();
+        next.flush_diff(&mut out, &next).unwrap();
+        assert_eq!(out, b"");
+
+        let mut out = Vec::new();
+        Buffer::new(2, 1).flush_diff(&mut out, &next).unwrap();
+        assert_eq!(out, b"\x1B[1;1H\x1B[0m  ");
+
+        next.clear();
+        assert_eq!(next, prev);
+    }
+
+    #[test]
+    fn test_wide_chars() {
+        let cells = |buffer: &Buffer| buffer.cells.iter().map(|cell| cell.ch).collect::<String>();
+
+        let mut buffer = Buffer::new(5, 1);
+        assert_eq!(buffer.set_str(0, 0, "日e\u{301}本x", &Style::default()), 5);
+        assert_eq!(cells(&buffer), "日\0e本\0");
+        buffer.set(1, 0, Cell::new('a'));
+        assert_eq!(cells(&buffer), " ae本\0");
+        buffer.set(2, 0, Cell::new('語'));
+        assert_eq!(cells(&buffer), " a語\0 ");
+        buffer.set(4, 0, Cell::new('語'));
+        buffer.set(0, 0, Cell::new('\u{301}'));
+        assert_eq!(cells(&buffer), " a語\0 ");
+
+        let mut out = Vec::new();
+        buffer.draw(&mut out).unwrap();
+        assert_eq!(out, "\x1B[1;1H\x1B[0m a語 ".as_bytes());
+
+        buffer.set(3, 0, Cell::new('x'));
+        assert_eq!(cells(&buffer), " a x ");
+        let prev = buffer.clone();
+        buffer.set_str(0, 0, "한", &Style::default());
+        let mut out = Vec::new();
+        buffer.flush_diff(&mut out, &prev).unwrap();
+        assert_eq!(out, "\x1B[1;1H\x1B[0m한".as_bytes());
+    }
+
+    #[test]
+    fn test_rect() {
+        let rect = Rect::new(2, 1, 4, 3);
+        assert_eq!((rect.right(), rect.bottom()), (6, 4));
+        assert!(rect.contains(2, 3) && !rect.contains(6, 1) && !rect.contains(1, 1));
+        assert_eq!(rect.intersection(&Rect::new(4, 0, 10, 2)), Rect::new(4, 1, 2, 1));
+        assert!(rect.intersection(&Rect::new(7, 0, 1, 1)).is_empty());
+        assert_eq!(rect.inner(1), Rect::new(3, 2, 2, 1));
+        assert!(rect.inner(2).is_empty());
+    }
+
+    #[test]
+    fn test_view() {
+        let cells = |buffer: &Buffer| buffer.cells.iter().map(|cell| cell.ch).collect::<String>();
+
+        let mut buffer = Buffer::new(4, 3);
+        for y in 0..3 {
+            buffer.set_str(0, y, "abcd", &Style::default());
+        }
+        {
+            let mut view = buffer.view(Rect::new(1, 1, 10, 10));
+            assert_eq!(view.rect(), Rect::new(1, 1, 3, 2));
+            assert_eq!(view.get(0, 0).map(|cell| cell.ch), Some('b'));
+            assert_eq!(view.get(3, 0), None);
+            assert_eq!(view.set_str(1, 0, "日本", &Style::default()), 3);
+            view.set(0, 5, Cell::new('x'));
+            view.view(Rect::new(0, 1, 1, 5)).clear();
+        }
+        assert_eq!(cells(&buffer), "abcdab日\0a cd");
+
+        let mut screen = DoubleBuffer::new(2, 2);
+        screen.present(&mut Vec::new()).unwrap();
+        screen.view(Rect::new(1, 1, 1, 1)).set(0, 0, Cell::new('한'));
+        screen.view(Rect::new(0, 1, 1, 1)).set(0, 0, Cell::new('x'));
+        let mut out = Vec::new();
+        screen.present(&mut out).unwrap();
+        assert_eq!(out, b"\x1B[2;1H\x1B[0mx");
+    }
+
+    #[test]
+    fn test_composite() {
+        use color::Blue;
+
+        let bold = Style { attributes: Attributes::BOLD, bg: Blue.into(), ..Style::default() };
+        let mut base = Buffer::new(5, 2);
+        base.set_str(0, 0, "abc日", &bold);
+        base.set_str(0, 1, "defgh", &Style::default());
+
+        let shaded = Cell { bg: Red.into(), ..Cell::TRANSPARENT };
+        let mut popup = Buffer::new(3, 3);
+        popup.set_str(0, 0, "x本", &Style::default());
+        popup.set(0, 1, shaded);
+        popup.set(1, 1, Cell::TRANSPARENT);
+        popup.set(2, 1, Cell::TRANSPARENT);
+        base.composite(&popup, 2, 0);
+
+        let cells: String = base.cells.iter().map(|cell| cell.ch).collect();
+        assert_eq!(cells, "abx本\0defgh");
+        assert_eq!(base.get(2, 0), Some(&Cell { bg: Blue.into(), ..Cell::new('x') }));
+        assert_eq!(base.get(2, 1), Some(&Cell { bg: Red.into(), ..Cell::new('f') }));
+        assert_eq!(base.get(3, 1), Some(&Cell::new('g')));
+        assert_eq!(Cell::TRANSPARENT.over(&Cell::TRANSPARENT), Cell::TRANSPARENT);
+    }
+
+    #[test]
+    fn test_write_text() {
+        let rows = |buffer: &Buffer| -> Vec<String> {
+            buffer.cells
+                .chunks(buffer.width as usize)
+                .map(|row| row.iter().map(|cell| cell.ch).collect())
+                .collect()
+        };
+
+        let mut buffer = Buffer::new(7, 5);
+        let text = "  one two  three\x1B[1mfour\x1B[m\n\n日本語です x";
+        assert_eq!(buffer.view(Rect::new(1, 0, 5, 5)).write_wrapped(text), 8);
+        assert_eq!(rows(&buffer),
+                   [
+                       "   one ",
+                       " two   ",
+                       " three ",
+                       " four  ",
+                       "       ",
+                   ]);
+        assert!(buffer.get(1, 3).unwrap().attrs.contains(Attributes::BOLD));
+        assert!(!buffer.get(5, 2).unwrap().attrs.contains(Attributes::BOLD));
+
+        buffer.clear();
+        assert_eq!(buffer.view(Rect::new(0, 0, 5, 2)).write_wrapped("日本語です x"), 3);
+        assert_eq!(rows(&buffer)[..2], ["日\0本\0   ", "語\0で\0   "]);
+
+        let mut buffer = Buffer::new(5, 3);
+        buffer.view(Rect::new(0, 0, 4, 3)).write_truncated(0, "abcd\nefgh", "…");
+        buffer.view(Rect::new(0, 0, 4, 3)).write_truncated(1, "a\x1B[4mbcde", "...");
+        buffer.view(Rect::new(0, 0, 4, 3)).write_truncated(2, "a日本", "…");
+        assert_eq!(rows(&buffer), ["abcd ", "a... ", "a日\0… "]);
+        assert!(buffer.get(1, 1).unwrap().attrs.contains(Attributes::UNDERLINE));
+
+        buffer.clear();
+        assert_eq!(buffer.view(Rect::new(0, 0, 4, 3)).write_wrapped("\x1Bü"), 1);
+        buffer.view(Rect::new(0, 0, 4, 3)).write_truncated(1, "\x1B(日本語", "…");
+        assert_eq!(rows(&buffer)[..2], ["ü    ", "日\0…  "]);
+    }
+
+    #[test]
+    fn test_double_buffer() {
+        let present = |screen: &mut DoubleBuffer| {
+            let mut out = Vec::new();
+            screen.present(&mut out).unwrap();
+            String::from_utf8(out).unwrap()
+        };
+
+        let mut screen = DoubleBuffer::new(3, 2);
+        screen.set(1, 1, Cell::new('a'));
+        assert_eq!(present(&mut screen), "\x1B[1;1H\x1B[0m   \x1B[2;1H a ");
+        assert_eq!(screen.front(), screen.back());
+        assert_eq!(present(&mut screen), "");
+
+        // Changing a cell to the same character damages its row, but writes nothing.
+        screen.set(1, 1, Cell::new('a'));
+        screen.set(2, 0, Cell::new('b'));
+        screen.set(3, 0, Cell::new('c'));
+        assert_eq!(present(&mut screen), "\x1B[1;3H\x1B[0mb");
+        assert_eq!(screen.front().get(2, 0), Some(&Cell::new('b')));
+
+        screen.clear();
+        assert_eq!(present(&mut screen), "\x1B[1;3H\x1B[0m \x1B[2;2H ");
+        screen.invalidate();
+        assert_eq!(present(&mut screen), "\x1B[1;1H\x1B[0m   \x1B[2;1H   ");
+
+        screen.resize(1, 1);
+        assert_eq!((screen.width(), screen.height()), (1, 1));
+        assert_eq!(present(&mut screen), "\x1B[1;1H\x1B[0m ");
+    }
+}
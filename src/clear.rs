@@ -1,9 +1,82 @@
 //! Clearing the screen.
 
 use std::fmt;
+use std::io::{self, Write};
+
+use input::{DisableBracketedPaste, DisableFocusReporting, DisableKittyKeyboard,
+            DisableModifyOtherKeys};
+use mouse::{DisableAnyMotion, DisableButtonMotion, DisableNormal, DisableSgr, DisableUrxvt,
+            DisableX10};
+use screen::ToMainScreen;
 
 derive_csi_sequence!("Clear the entire screen.", All, "2J");
 derive_csi_sequence!("Clear everything after the cursor.", AfterCursor, "J");
 derive_csi_sequence!("Clear everything before the cursor.", BeforeCursor, "1J");
 derive_csi_sequence!("Clear the current line.", CurrentLine, "2K");
 derive_csi_sequence!("Clear from cursor to newline.", UntilNewline, "K");
+derive_csi_sequence!("Clear the scrollback buffer, but not the screen (an xterm extension).",
+                     ClearScrollback,
+                     "3J");
+derive_csi_sequence!("Reset the style and modes, such as origin mode and the scrolling region, \
+                      without clearing the screen (DECSTR). This shows the cursor as well.",
+                     SoftReset,
+                     "!p");
+
+/// Reset the terminal to its initial state (RIS), clearing the screen and resetting the style,
+/// every mode and the tab stops.
+#[derive(Copy, Clone)]
+pub struct HardReset;
+
+impl fmt::Display for HardReset {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("\x1Bc")
+    }
+}
+
+impl HardReset {
+    /// The sequence as bytes, for writing it without formatting.
+    #[inline]
+    pub fn as_bytes(&self) -> &'static [u8] {
+        b"\x1Bc"
+    }
+}
+
+/// Clean up a terminal left in a weird state (e.g. by a crashed program), like `reset(1)`.
+///
+/// This disables the modes set by this crate, such as mouse reporting and the alternate screen,
+/// and resets the terminal with `SoftReset` and `HardReset`, for terminals only supporting some
+/// of these. The screen and scrollback buffer are cleared afterwards. Settings of the tty itself,
+/// such as raw mode, are left alone.
+pub fn reset_terminal<W: Write>(w: &mut W) -> io::Result<()> {
+    write!(w,
+           "{}{}{}{}{}{}",
+           DisableAnyMotion,
+           DisableButtonMotion,
+           DisableNormal,
+           DisableX10,
+           DisableSgr,
+           DisableUrxvt)?;
+    write!(w,
+           "{}{}{}{}",
+           DisableBracketedPaste,
+           DisableFocusReporting,
+           DisableKittyKeyboard,
+           DisableModifyOtherKeys)?;
+    write!(w, "{}{}{}{}", ToMainScreen, SoftReset, HardReset, ClearScrollback)?;
+    w.flush()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_reset_terminal() {
+        let mut out = Vec::new();
+        reset_terminal(&mut out).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.starts_with("\x1B[?1003l\x1B[?1002l\x1B[?1000l"));
+        assert!(out.ends_with("\x1B[?1049l\x1B[!p\x1Bc\x1B[3J"));
+        assert_eq!(HardReset.to_string().as_bytes(), HardReset.as_bytes());
+    }
+}
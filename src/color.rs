@@ -13,11 +13,17 @@
 //! ```
 
 use std::fmt;
+use ansi::{Chunk, Sequences};
 use raw::RawTerminal;
 use std::io::{self, Write, Read};
 use std::time::{SystemTime, Duration};
 use async::async_stdin;
+use input::Events;
 use std::env;
+use std::error;
+use std::mem;
+use std::str;
+use std::sync::OnceLock;
 
 /// A terminal color.
 pub trait Color {
@@ -25,6 +31,25 @@ pub trait Color {
     fn write_fg(&self, f: &mut fmt::Formatter) -> fmt::Result;
     /// Write the background version of this color.
     fn write_bg(&self, f: &mut fmt::Formatter) -> fmt::Result;
+
+    /// Write the underline version of this color, see `UnderlineColor`.
+    ///
+    /// By default, this is derived from the foreground version, which works for colors written
+    /// as `ESC [ 38 ; ... m`.
+    fn write_underline(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        struct Foreground<'a, C: ?Sized + 'a>(&'a C);
+        impl<'a, C: Color + ?Sized> fmt::Display for Foreground<'a, C> {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                self.0.write_fg(f)
+            }
+        }
+
+        let fg = Foreground(self).to_string();
+        match fg.strip_prefix(csi!("3")) {
+            Some(rest) if rest.starts_with('8') || rest == "9m" => write!(f, csi!("5{}"), rest),
+            _ => Ok(()),
+        }
+    }
 }
 
 macro_rules! derive_color {
@@ -43,26 +68,37 @@ macro_rules! derive_color {
             fn write_bg(&self, f: &mut fmt::Formatter) -> fmt::Result {
                 write!(f, csi!("48;5;", $value, "m"))
             }
+
+            #[inline]
+            fn write_underline(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                write!(f, csi!("58;5;", $value, "m"))
+            }
+        }
+
+        impl From<$name> for AnyColor {
+            fn from(_: $name) -> AnyColor {
+                AnyColor::Ansi($value)
+            }
         }
     };
 }
 
-derive_color!("Black.", Black, "0");
-derive_color!("Red.", Red, "1");
-derive_color!("Green.", Green, "2");
-derive_color!("Yellow.", Yellow, "3");
-derive_color!("Blue.", Blue, "4");
-derive_color!("Magenta.", Magenta, "5");
-derive_color!("Cyan.", Cyan, "6");
-derive_color!("White.", White, "7");
-derive_color!("High-intensity light black.", LightBlack, "8");
-derive_color!("High-intensity light red.", LightRed, "9");
-derive_color!("High-intensity light green.", LightGreen, "10");
-derive_color!("High-intensity light yellow.", LightYellow, "11");
-derive_color!("High-intensity light blue.", LightBlue, "12");
-derive_color!("High-intensity light magenta.", LightMagenta, "13");
-derive_color!("High-intensity light cyan.", LightCyan, "14");
-derive_color!("High-intensity light white.", LightWhite, "15");
+derive_color!("Black.", Black, 0);
+derive_color!("Red.", Red, 1);
+derive_color!("Green.", Green, 2);
+derive_color!("Yellow.", Yellow, 3);
+derive_color!("Blue.", Blue, 4);
+derive_color!("Magenta.", Magenta, 5);
+derive_color!("Cyan.", Cyan, 6);
+derive_color!("White.", White, 7);
+derive_color!("High-intensity light black.", LightBlack, 8);
+derive_color!("High-intensity light red.", LightRed, 9);
+derive_color!("High-intensity light green.", LightGreen, 10);
+derive_color!("High-intensity light yellow.", LightYellow, 11);
+derive_color!("High-intensity light blue.", LightBlue, 12);
+derive_color!("High-intensity light magenta.", LightMagenta, 13);
+derive_color!("High-intensity light cyan.", LightCyan, 14);
+derive_color!("High-intensity light white.", LightWhite, 15);
 
 impl<'a> Color for &'a Color {
     #[inline]
@@ -74,14 +110,21 @@ impl<'a> Color for &'a Color {
     fn write_bg(&self, f: &mut fmt::Formatter) -> fmt::Result {
         (*self).write_bg(f)
     }
+
+    #[inline]
+    fn write_underline(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        (*self).write_underline(f)
+    }
 }
 
 /// An arbitrary ANSI color value.
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub struct AnsiValue(pub u8);
 
 impl AnsiValue {
     /// 216-color (r, g, b ≤ 5) RGB.
+    ///
+    /// The components are checked in debug builds only, see `checked_rgb` otherwise.
     pub fn rgb(r: u8, g: u8, b: u8) -> AnsiValue {
         debug_assert!(r <= 5, "Red color fragment (r = {}) is out of bound. Make sure r ≤ 5.", r);
         debug_assert!(g <= 5, "Green color fragment (g = {}) is out of bound. Make sure g ≤ 5.", g);
@@ -90,9 +133,19 @@ impl AnsiValue {
         AnsiValue(16 + 36 * r + 6 * g + b)
     }
 
+    /// 216-color (r, g, b ≤ 5) RGB, or `None` if a component is out of bounds.
+    pub fn checked_rgb(r: u8, g: u8, b: u8) -> Option<AnsiValue> {
+        if r <= 5 && g <= 5 && b <= 5 {
+            Some(AnsiValue::rgb(r, g, b))
+        } else {
+            None
+        }
+    }
+
     /// Grayscale color.
     ///
-    /// There are 24 shades of gray.
+    /// There are 24 shades of gray. The shade is checked in debug builds only, see
+    /// `checked_grayscale` otherwise.
     pub fn grayscale(shade: u8) -> AnsiValue {
         // Unfortunately, there are a little less than fifty shades.
         debug_assert!(shade < 24, "Grayscale out of bound (shade = {}). There are only 24 shades of \
@@ -100,24 +153,477 @@ impl AnsiValue {
 
         AnsiValue(0xE8 + shade)
     }
+
+    /// Grayscale color, or `None` if the shade is out of bounds.
+    pub fn checked_grayscale(shade: u8) -> Option<AnsiValue> {
+        if shade < 24 {
+            Some(AnsiValue::grayscale(shade))
+        } else {
+            None
+        }
+    }
+
+    /// The components (r, g, b ≤ 5) of a color of the 216-color cube, the inverse of `rgb`.
+    pub fn as_rgb(self) -> Option<(u8, u8, u8)> {
+        match self.0 {
+            n @ 16..=231 => Some(((n - 16) / 36, (n - 16) / 6 % 6, (n - 16) % 6)),
+            _ => None,
+        }
+    }
+
+    /// The foreground sequence of this color, without formatting at runtime.
+    ///
+    /// This gives the same sequence as `Fg(self)`, which makes it handy in render loops writing
+    /// a color for every cell.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use std::io::Write;
+    /// use termion::color::AnsiValue;
+    ///
+    /// let mut out = Vec::new();
+    /// out.write_all(AnsiValue(208).fg_str().as_bytes()).unwrap();
+    /// assert_eq!(out, b"\x1B[38;5;208m");
+    /// ```
+    pub const fn fg_str(self) -> &'static str {
+        FG_SEQUENCES[self.0 as usize]
+    }
+
+    /// The background sequence of this color, like `fg_str`.
+    pub const fn bg_str(self) -> &'static str {
+        BG_SEQUENCES[self.0 as usize]
+    }
+
+    /// The shade (below 24) of a grayscale color, the inverse of `grayscale`.
+    pub fn as_grayscale(self) -> Option<u8> {
+        match self.0 {
+            n @ 0xE8..=0xFF => Some(n - 0xE8),
+            _ => None,
+        }
+    }
 }
 
 impl Color for AnsiValue {
     #[inline]
     fn write_fg(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, csi!("38;5;{}m"), self.0)
+        f.write_str(self.fg_str())
     }
 
     #[inline]
     fn write_bg(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, csi!("48;5;{}m"), self.0)
+        f.write_str(self.bg_str())
     }
+
+    #[inline]
+    fn write_underline(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, csi!("58;5;{}m"), self.0)
+    }
+}
+
+/// Make an array of the sequences `ESC [ <prefix> <n> m` for the given numbers.
+macro_rules! indexed_sequences {
+    ($prefix:expr; $($n:tt)*) => {
+        [$(csi!($prefix, stringify!($n), "m")),*]
+    };
 }
 
+/// The foreground sequences of the 256 colors, see `AnsiValue::fg_str`.
+const FG_SEQUENCES: [&str; 256] = indexed_sequences!("38;5;";
+    0 1 2 3 4 5 6 7 8 9 10 11 12 13 14 15 16 17 18 19 20 21 22 23 24 25 26 27 28 29 30 31 32 33 34
+    35 36 37 38 39 40 41 42 43 44 45 46 47 48 49 50 51 52 53 54 55 56 57 58 59 60 61 62 63 64 65 66
+    67 68 69 70 71 72 73 74 75 76 77 78 79 80 81 82 83 84 85 86 87 88 89 90 91 92 93 94 95 96 97 98
+    99 100 101 102 103 104 105 106 107 108 109 110 111 112 113 114 115 116 117 118 119 120 121 122
+    123 124 125 126 127 128 129 130 131 132 133 134 135 136 137 138 139 140 141 142 143 144 145 146
+    147 148 149 150 151 152 153 154 155 156 157 158 159 160 161 162 163 164 165 166 167 168 169 170
+    171 172 173 174 175 176 177 178 179 180 181 182 183 184 185 186 187 188 189 190 191 192 193 194
+    195 196 197 198 199 200 201 202 203 204 205 206 207 208 209 210 211 212 213 214 215 216 217 218
+    219 220 221 222 223 224 225 226 227 228 229 230 231 232 233 234 235 236 237 238 239 240 241 242
+    243 244 245 246 247 248 249 250 251 252 253 254 255
+);
+
+/// The background sequences of the 256 colors, see `AnsiValue::bg_str`.
+const BG_SEQUENCES: [&str; 256] = indexed_sequences!("48;5;";
+    0 1 2 3 4 5 6 7 8 9 10 11 12 13 14 15 16 17 18 19 20 21 22 23 24 25 26 27 28 29 30 31 32 33 34
+    35 36 37 38 39 40 41 42 43 44 45 46 47 48 49 50 51 52 53 54 55 56 57 58 59 60 61 62 63 64 65 66
+    67 68 69 70 71 72 73 74 75 76 77 78 79 80 81 82 83 84 85 86 87 88 89 90 91 92 93 94 95 96 97 98
+    99 100 101 102 103 104 105 106 107 108 109 110 111 112 113 114 115 116 117 118 119 120 121 122
+    123 124 125 126 127 128 129 130 131 132 133 134 135 136 137 138 139 140 141 142 143 144 145 146
+    147 148 149 150 151 152 153 154 155 156 157 158 159 160 161 162 163 164 165 166 167 168 169 170
+    171 172 173 174 175 176 177 178 179 180 181 182 183 184 185 186 187 188 189 190 191 192 193 194
+    195 196 197 198 199 200 201 202 203 204 205 206 207 208 209 210 211 212 213 214 215 216 217 218
+    219 220 221 222 223 224 225 226 227 228 229 230 231 232 233 234 235 236 237 238 239 240 241 242
+    243 244 245 246 247 248 249 250 251 252 253 254 255
+);
+
+/// The colors of xterm's default palette for the 16 ANSI colors.
+const ANSI_PALETTE: [(u8, u8, u8); 16] = [
+    (0, 0, 0),
+    (205, 0, 0),
+    (0, 205, 0),
+    (205, 205, 0),
+    (0, 0, 238),
+    (205, 0, 205),
+    (0, 205, 205),
+    (229, 229, 229),
+    (127, 127, 127),
+    (255, 0, 0),
+    (0, 255, 0),
+    (255, 255, 0),
+    (92, 92, 255),
+    (255, 0, 255),
+    (0, 255, 255),
+    (255, 255, 255),
+];
+
+/// The intensities of each component in the 6x6x6 color cube.
+const CUBE_LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
 /// A truecolor RGB.
-#[derive(Debug, Clone, Copy, PartialEq)]
+///
+/// Terminals without truecolor support may show something else entirely, so converting to an
+/// `AnsiValue` (with `From`) may be the better choice for them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct Rgb(pub u8, pub u8, pub u8);
 
+impl From<AnsiValue> for Rgb {
+    /// The color shown for `value` by default, assuming xterm's palette for the 16 ANSI colors.
+    fn from(value: AnsiValue) -> Rgb {
+        match value.0 {
+            n @ 0..=15 => {
+                let (r, g, b) = ANSI_PALETTE[n as usize];
+                Rgb(r, g, b)
+            }
+            n @ 16..=231 => {
+                let n = n - 16;
+                Rgb(CUBE_LEVELS[(n / 36) as usize],
+                    CUBE_LEVELS[(n / 6 % 6) as usize],
+                    CUBE_LEVELS[(n % 6) as usize])
+            }
+            n => {
+                let level = 8 + 10 * (n - 232);
+                Rgb(level, level, level)
+            }
+        }
+    }
+}
+
+impl From<Rgb> for AnsiValue {
+    /// The closest color of the color cube and the grayscale ramp, see `nearest_256`.
+    fn from(rgb: Rgb) -> AnsiValue {
+        nearest_256(rgb)
+    }
+}
+
+impl Rgb {
+    /// The perceived difference between two colors.
+    ///
+    /// This is the Euclidean distance of the colors in the CIELAB color space (CIE76), where a
+    /// difference of about 2.3 is just noticeable. Unlike the distance of the RGB components, it
+    /// accounts for e.g. the eye being more sensitive to differences of green than of blue.
+    pub fn distance(self, other: Rgb) -> f64 {
+        lab_distance(lab(self), lab(other))
+    }
+
+    /// The color `t` of the way from this color to `other`, interpolating each component.
+    ///
+    /// `t` is clamped to the range from 0 (this color) to 1 (`other`).
+    pub fn lerp(self, other: Rgb, t: f64) -> Rgb {
+        let t = t.clamp(0.0, 1.0);
+        let mix = |a: u8, b: u8| (f64::from(a) + (f64::from(b) - f64::from(a)) * t).round() as u8;
+        Rgb(mix(self.0, other.0), mix(self.1, other.1), mix(self.2, other.2))
+    }
+}
+
+/// A color given by hue (in degrees, from 0 to 360), saturation and lightness (from 0 to 1).
+///
+/// Converting it to `Rgb` (with `From`) gives the color to write. HSL is handy for deriving
+/// colors, e.g. a lighter variant of a theme color, or colors of evenly spaced hues.
+///
+/// # Example
+///
+/// ```rust
+/// use termion::color::{Hsl, Rgb};
+///
+/// let Hsl(hue, saturation, lightness) = Hsl::from(Rgb(255, 135, 0));
+/// let lighter = Rgb::from(Hsl(hue, saturation, lightness + 0.2));
+/// assert_eq!(lighter, Rgb(255, 183, 102));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Hsl(pub f64, pub f64, pub f64);
+
+impl From<Rgb> for Hsl {
+    fn from(rgb: Rgb) -> Hsl {
+        let component = |v: u8| f64::from(v) / 255.0;
+        let (r, g, b) = (component(rgb.0), component(rgb.1), component(rgb.2));
+        let max = r.max(g).max(b);
+        let min = r.min(g).min(b);
+        let lightness = (max + min) / 2.0;
+        let chroma = max - min;
+        if chroma == 0.0 {
+            return Hsl(0.0, 0.0, lightness);
+        }
+
+        let saturation = chroma / (1.0 - (2.0 * lightness - 1.0).abs());
+        let sector = if max == r {
+            (g - b) / chroma
+        } else if max == g {
+            (b - r) / chroma + 2.0
+        } else {
+            (r - g) / chroma + 4.0
+        };
+        Hsl((60.0 * sector).rem_euclid(360.0), saturation, lightness)
+    }
+}
+
+impl From<Hsl> for Rgb {
+    /// The color of `hsl`, with the hue taken modulo 360 and the rest clamped to their range.
+    fn from(hsl: Hsl) -> Rgb {
+        let hue = hsl.0.rem_euclid(360.0) / 60.0;
+        let saturation = hsl.1.clamp(0.0, 1.0);
+        let lightness = hsl.2.clamp(0.0, 1.0);
+
+        let chroma = (1.0 - (2.0 * lightness - 1.0).abs()) * saturation;
+        let x = chroma * (1.0 - (hue % 2.0 - 1.0).abs());
+        let (r, g, b) = match hue as u8 {
+            0 => (chroma, x, 0.0),
+            1 => (x, chroma, 0.0),
+            2 => (0.0, chroma, x),
+            3 => (0.0, x, chroma),
+            4 => (x, 0.0, chroma),
+            _ => (chroma, 0.0, x),
+        };
+        let m = lightness - chroma / 2.0;
+        let component = |v: f64| ((v + m) * 255.0).round() as u8;
+        Rgb(component(r), component(g), component(b))
+    }
+}
+
+/// An iterator over `steps` colors evenly spaced from `from` to `to`, both included.
+///
+/// A single step gives `from`, and no steps give no colors.
+///
+/// # Example
+///
+/// ```rust
+/// use termion::color::{self, Rgb};
+///
+/// let colors: Vec<Rgb> = color::gradient(Rgb(0, 0, 0), Rgb(255, 100, 0), 3).collect();
+/// assert_eq!(colors, [Rgb(0, 0, 0), Rgb(128, 50, 0), Rgb(255, 100, 0)]);
+/// ```
+pub fn gradient(from: Rgb, to: Rgb, steps: usize) -> Gradient {
+    Gradient {
+        from,
+        to,
+        steps,
+        next: 0,
+    }
+}
+
+/// An iterator over the colors of a gradient, see `gradient`.
+#[derive(Debug, Clone)]
+pub struct Gradient {
+    from: Rgb,
+    to: Rgb,
+    steps: usize,
+    next: usize,
+}
+
+impl Iterator for Gradient {
+    type Item = Rgb;
+
+    fn next(&mut self) -> Option<Rgb> {
+        if self.next >= self.steps {
+            return None;
+        }
+        let t = match self.steps {
+            1 => 0.0,
+            steps => self.next as f64 / (steps - 1) as f64,
+        };
+        self.next += 1;
+        Some(self.from.lerp(self.to, t))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let left = self.steps - self.next;
+        (left, Some(left))
+    }
+}
+
+impl ExactSizeIterator for Gradient {}
+
+impl str::FromStr for Rgb {
+    type Err = ParseColorError;
+
+    /// Parse a color given as `"#rrggbb"`, `"#rgb"`, `"rgb(r, g, b)"` or by name.
+    ///
+    /// The names are those of CSS (and mostly X11), e.g. `"orange"` or `"DarkSlateGray"`. They
+    /// are case-insensitive, and may be split by spaces, dashes or underscores. Note that the named
+    /// colors are truecolors, e.g. `"red"` is `Rgb(255, 0, 0)`; see `AnsiValue` for the ANSI
+    /// colors.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use termion::color::Rgb;
+    ///
+    /// assert_eq!("#ff8700".parse(), Ok(Rgb(255, 135, 0)));
+    /// assert_eq!("rgb(0, 43, 54)".parse(), Ok(Rgb(0, 43, 54)));
+    /// assert_eq!("rebecca purple".parse(), Ok(Rgb(102, 51, 153)));
+    /// ```
+    fn from_str(s: &str) -> Result<Rgb, ParseColorError> {
+        let error = || ParseColorError(s.to_string());
+        let trimmed = s.trim();
+
+        if let Some(hex) = trimmed.strip_prefix('#') {
+            if !hex.is_ascii() || hex.len() != 3 && hex.len() != 6 {
+                return Err(error());
+            }
+            // `#rgb` is short for `#rrggbb`.
+            let width = hex.len() / 3;
+            let component = |i: usize| {
+                let value = u8::from_str_radix(&hex[i * width..(i + 1) * width], 16);
+                value.map(|v| if width == 1 { v * 17 } else { v }).map_err(|_| error())
+            };
+            return Ok(Rgb(component(0)?, component(1)?, component(2)?));
+        }
+
+        let lower = trimmed.to_lowercase();
+        if let Some(args) = lower.strip_prefix("rgb(").and_then(|s| s.strip_suffix(')')) {
+            let mut components = args.split(',').map(|c| c.trim().parse().map_err(|_| error()));
+            let rgb = Rgb(components.next().ok_or_else(error)??,
+                          components.next().ok_or_else(error)??,
+                          components.next().ok_or_else(error)??);
+            return match components.next() {
+                None => Ok(rgb),
+                Some(_) => Err(error()),
+            };
+        }
+
+        let name = normalize_name(&lower);
+        match WEB_COLORS.binary_search_by_key(&&*name, |&(name, _)| name) {
+            Ok(i) => {
+                let (r, g, b) = WEB_COLORS[i].1;
+                Ok(Rgb(r, g, b))
+            }
+            Err(_) => Err(error()),
+        }
+    }
+}
+
+impl str::FromStr for AnsiValue {
+    type Err = ParseColorError;
+
+    /// Parse a color value (`"0"` to `"255"`) or the name of an ANSI color.
+    ///
+    /// The names are those of the color types, e.g. `"red"` or `"light-red"` (also
+    /// `"bright-red"`), case-insensitive and optionally split by spaces, dashes or underscores.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use termion::color::AnsiValue;
+    ///
+    /// assert_eq!("light red".parse(), Ok(AnsiValue(9)));
+    /// assert_eq!("208".parse(), Ok(AnsiValue(208)));
+    /// ```
+    fn from_str(s: &str) -> Result<AnsiValue, ParseColorError> {
+        if let Ok(value) = s.trim().parse() {
+            return Ok(AnsiValue(value));
+        }
+
+        let name = normalize_name(&s.trim().to_lowercase());
+        let base = name.strip_prefix("light").or_else(|| name.strip_prefix("bright"));
+        let (bright, base) = match base {
+            Some(base) => (8, base),
+            None => (0, &*name),
+        };
+        ANSI_NAMES.iter()
+            .position(|&n| n == base)
+            .map(|i| AnsiValue(bright + i as u8))
+            .ok_or_else(|| ParseColorError(s.to_string()))
+    }
+}
+
+/// The names of the 8 basic ANSI colors, in order.
+const ANSI_NAMES: [&str; 8] = [
+    "black", "red", "green", "yellow", "blue", "magenta", "cyan", "white",
+];
+
+/// A lowercase color name without separators.
+fn normalize_name(name: &str) -> String {
+    name.chars().filter(|&c| c != ' ' && c != '-' && c != '_').collect()
+}
+
+/// An error returned when parsing a color from a string fails.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseColorError(String);
+
+impl fmt::Display for ParseColorError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "invalid color: {:?}", self.0)
+    }
+}
+
+impl error::Error for ParseColorError {
+    fn description(&self) -> &str {
+        "invalid color"
+    }
+}
+
+/// The perceptually closest color of the color cube and the grayscale ramp (16 to 255).
+///
+/// The 16 ANSI colors are never picked, since terminals commonly change them with themes.
+pub fn nearest_256(rgb: Rgb) -> AnsiValue {
+    nearest(rgb, 16..=255)
+}
+
+/// The perceptually closest of the 16 ANSI colors, assuming xterm's default palette.
+pub fn nearest_16(rgb: Rgb) -> AnsiValue {
+    nearest(rgb, 0..=15)
+}
+
+/// The perceptually closest color of `candidates`.
+fn nearest<I: Iterator<Item = u8>>(rgb: Rgb, candidates: I) -> AnsiValue {
+    static PALETTE: OnceLock<Vec<[f64; 3]>> = OnceLock::new();
+    let palette = PALETTE.get_or_init(|| (0..=255).map(|i| lab(Rgb::from(AnsiValue(i)))).collect());
+
+    let target = lab(rgb);
+    let mut best = (0, f64::INFINITY);
+    for i in candidates {
+        let distance = lab_distance(target, palette[i as usize]);
+        if distance < best.1 {
+            best = (i, distance);
+        }
+    }
+    AnsiValue(best.0)
+}
+
+/// Convert an sRGB color to CIELAB, relative to the D65 white point.
+fn lab(rgb: Rgb) -> [f64; 3] {
+    let linear = |v: u8| {
+        let v = f64::from(v) / 255.0;
+        if v <= 0.04045 {
+            v / 12.92
+        } else {
+            ((v + 0.055) / 1.055).powf(2.4)
+        }
+    };
+    let (r, g, b) = (linear(rgb.0), linear(rgb.1), linear(rgb.2));
+
+    let f = |t: f64| if t > 0.008856 { t.cbrt() } else { 7.787 * t + 16.0 / 116.0 };
+    let x = f((0.4124 * r + 0.3576 * g + 0.1805 * b) / 0.95047);
+    let y = f(0.2126 * r + 0.7152 * g + 0.0722 * b);
+    let z = f((0.0193 * r + 0.1192 * g + 0.9505 * b) / 1.08883);
+
+    [116.0 * y - 16.0, 500.0 * (x - y), 200.0 * (y - z)]
+}
+
+/// The Euclidean distance between two CIELAB colors.
+fn lab_distance(a: [f64; 3], b: [f64; 3]) -> f64 {
+    ((a[0] - b[0]).powi(2) + (a[1] - b[1]).powi(2) + (a[2] - b[2]).powi(2)).sqrt()
+}
+
 impl Color for Rgb {
     #[inline]
     fn write_fg(&self, f: &mut fmt::Formatter) -> fmt::Result {
@@ -128,6 +634,195 @@ impl Color for Rgb {
     fn write_bg(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, csi!("48;2;{};{};{}m"), self.0, self.1, self.2)
     }
+
+    #[inline]
+    fn write_underline(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, csi!("58;2;{};{};{}m"), self.0, self.1, self.2)
+    }
+}
+
+/// Any color, for storing colors of different types together without boxing them.
+///
+/// # Example
+///
+/// ```rust
+/// use termion::color::{self, AnyColor};
+///
+/// let theme: Vec<AnyColor> = vec![color::Red.into(), color::Rgb(255, 135, 0).into(),
+///                                 "#333".parse().unwrap(), AnyColor::Reset];
+/// assert_eq!(color::Fg(theme[0]).to_string(), "\x1B[31m");
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AnyColor {
+    /// One of the 16 ANSI colors (below 16), like `Red` or `LightBlue`.
+    ///
+    /// Unlike `Indexed`, these are written with the basic sequences (e.g. `ESC [ 31 m`), which
+    /// every color terminal supports.
+    Ansi(u8),
+    /// One of the 256 indexed colors, like `AnsiValue`.
+    Indexed(u8),
+    /// A truecolor, like `Rgb`.
+    Rgb(u8, u8, u8),
+    /// The default color, like `Reset`.
+    Reset,
+}
+
+impl Color for AnyColor {
+    fn write_fg(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            AnyColor::Ansi(n @ 0..=7) => write!(f, csi!("{}m"), 30 + n),
+            AnyColor::Ansi(n @ 8..=15) => write!(f, csi!("{}m"), 90 + n - 8),
+            AnyColor::Ansi(n) | AnyColor::Indexed(n) => AnsiValue(n).write_fg(f),
+            AnyColor::Rgb(r, g, b) => Rgb(r, g, b).write_fg(f),
+            AnyColor::Reset => Reset.write_fg(f),
+        }
+    }
+
+    fn write_bg(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            AnyColor::Ansi(n @ 0..=7) => write!(f, csi!("{}m"), 40 + n),
+            AnyColor::Ansi(n @ 8..=15) => write!(f, csi!("{}m"), 100 + n - 8),
+            AnyColor::Ansi(n) | AnyColor::Indexed(n) => AnsiValue(n).write_bg(f),
+            AnyColor::Rgb(r, g, b) => Rgb(r, g, b).write_bg(f),
+            AnyColor::Reset => Reset.write_bg(f),
+        }
+    }
+
+    fn write_underline(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            // There are no basic sequences for underline colors.
+            AnyColor::Ansi(n) | AnyColor::Indexed(n) => AnsiValue(n).write_underline(f),
+            AnyColor::Rgb(r, g, b) => Rgb(r, g, b).write_underline(f),
+            AnyColor::Reset => Reset.write_underline(f),
+        }
+    }
+}
+
+impl From<AnsiValue> for AnyColor {
+    fn from(value: AnsiValue) -> AnyColor {
+        AnyColor::Indexed(value.0)
+    }
+}
+
+impl From<Rgb> for AnyColor {
+    fn from(rgb: Rgb) -> AnyColor {
+        AnyColor::Rgb(rgb.0, rgb.1, rgb.2)
+    }
+}
+
+impl From<Reset> for AnyColor {
+    fn from(_: Reset) -> AnyColor {
+        AnyColor::Reset
+    }
+}
+
+impl str::FromStr for AnyColor {
+    type Err = ParseColorError;
+
+    /// Parse any color: `"reset"` (or `"default"`), an ANSI color name (like `AnsiValue`, but
+    /// giving `Ansi`), a color value from `"0"` to `"255"` (giving `Indexed`), or a truecolor
+    /// (like `Rgb`).
+    ///
+    /// Names of both ANSI colors and CSS colors, like `"red"`, are taken to be ANSI colors.
+    fn from_str(s: &str) -> Result<AnyColor, ParseColorError> {
+        let name = normalize_name(&s.trim().to_lowercase());
+        if name == "reset" || name == "default" {
+            return Ok(AnyColor::Reset);
+        }
+        if let Ok(value) = name.parse() {
+            return Ok(AnyColor::Indexed(value));
+        }
+        match s.parse::<AnsiValue>() {
+            Ok(value) => Ok(AnyColor::Ansi(value.0)),
+            Err(_) => s.parse::<Rgb>().map(AnyColor::from),
+        }
+    }
+}
+
+/// Write the foreground sequence of `color` to `w` as bytes, without going through `fmt`.
+///
+/// This writes the same as `Fg(color)`, but faster, for renderers writing many cells at once.
+/// The sequence is written with a single `write_all`.
+///
+/// # Example
+///
+/// ```rust
+/// use termion::color::{self, Rgb};
+///
+/// let mut out = Vec::new();
+/// color::write_fg(&mut out, Rgb(255, 135, 0)).unwrap();
+/// color::write_bg(&mut out, color::Blue).unwrap();
+/// assert_eq!(out, b"\x1B[38;2;255;135;0m\x1B[44m");
+/// ```
+pub fn write_fg<W: Write, C: Into<AnyColor>>(w: &mut W, color: C) -> io::Result<()> {
+    write_color(w, 38, color.into())
+}
+
+/// Write the background sequence of `color` to `w` as bytes, like `write_fg`.
+pub fn write_bg<W: Write, C: Into<AnyColor>>(w: &mut W, color: C) -> io::Result<()> {
+    write_color(w, 48, color.into())
+}
+
+/// Write the underline sequence of `color` to `w` as bytes, like `write_fg`.
+pub fn write_underline<W: Write, C: Into<AnyColor>>(w: &mut W, color: C) -> io::Result<()> {
+    write_color(w, 58, color.into())
+}
+
+/// Write the sequence setting `color` as foreground (`target` 38), background (48) or underline
+/// (58) color, as `AnyColor` formats it.
+fn write_color<W: Write>(w: &mut W, target: u8, color: AnyColor) -> io::Result<()> {
+    let mut buf = SequenceBuf {
+        buf: [0; 20],
+        len: 0,
+    };
+    buf.push(b"\x1B[");
+    match color {
+        AnyColor::Ansi(n) if n < 16 && target != 58 => {
+            // The basic sequences, e.g. `31` and `91` (or `41` and `101`) for red and bright red.
+            let base = target - 8;
+            buf.push_number(if n < 8 { base + n } else { base + 60 + n - 8 });
+        }
+        AnyColor::Ansi(n) | AnyColor::Indexed(n) => {
+            buf.push_number(target);
+            buf.push(b";5;");
+            buf.push_number(n);
+        }
+        AnyColor::Rgb(r, g, b) => {
+            buf.push_number(target);
+            buf.push(b";2;");
+            buf.push_number(r);
+            buf.push(b";");
+            buf.push_number(g);
+            buf.push(b";");
+            buf.push_number(b);
+        }
+        AnyColor::Reset => buf.push_number(target + 1),
+    }
+    buf.push(b"m");
+    w.write_all(&buf.buf[..buf.len])
+}
+
+/// A buffer for a color sequence, which is at most 19 bytes long.
+struct SequenceBuf {
+    buf: [u8; 20],
+    len: usize,
+}
+
+impl SequenceBuf {
+    fn push(&mut self, bytes: &[u8]) {
+        self.buf[self.len..self.len + bytes.len()].copy_from_slice(bytes);
+        self.len += bytes.len();
+    }
+
+    fn push_number(&mut self, n: u8) {
+        if n >= 100 {
+            self.push(&[b'0' + n / 100]);
+        }
+        if n >= 10 {
+            self.push(&[b'0' + n / 10 % 10]);
+        }
+        self.push(&[b'0' + n % 10]);
+    }
 }
 
 /// Reset colors to defaults.
@@ -144,6 +839,11 @@ impl Color for Reset {
     fn write_bg(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, csi!("49m"))
     }
+
+    #[inline]
+    fn write_underline(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, csi!("59m"))
+    }
 }
 
 /// A foreground color.
@@ -166,6 +866,257 @@ impl<C: Color> fmt::Display for Bg<C> {
     }
 }
 
+/// Change the color shown for a palette entry (OSC 4).
+///
+/// This changes the color of all text using the entry, including text already shown. The
+/// previous color can be queried first with `Events::palette_color`, or the terminal's own color
+/// restored with `ResetPalette`, e.g. on exit.
+#[derive(Debug, Clone, Copy)]
+pub struct SetPalette(pub u8, pub Rgb);
+
+impl fmt::Display for SetPalette {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let Rgb(r, g, b) = self.1;
+        write!(f, "\x1B]4;{};rgb:{:02x}/{:02x}/{:02x}\x1B\\", self.0, r, g, b)
+    }
+}
+
+/// Restore the terminal's configured color for a palette entry (OSC 104).
+#[derive(Debug, Clone, Copy)]
+pub struct ResetPalette(pub u8);
+
+impl fmt::Display for ResetPalette {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "\x1B]104;{}\x1B\\", self.0)
+    }
+}
+
+/// Restore the terminal's configured colors for all palette entries (OSC 104).
+#[derive(Debug, Clone, Copy)]
+pub struct ResetPalettes;
+
+impl fmt::Display for ResetPalettes {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("\x1B]104\x1B\\")
+    }
+}
+
+/// An underline color, set independently of the foreground color (SGR 58).
+///
+/// This is an extension of kitty, VTE and others, for e.g. marking spelling errors with red
+/// underlines. `UnderlineColor(Reset)` goes back to underlining in the foreground color. The
+/// underline itself is turned on with `style::Underline`.
+///
+/// # Example
+///
+/// ```rust
+/// use termion::color::{Reset, Rgb, UnderlineColor};
+/// use termion::style;
+///
+/// println!("{}{}Misspeled{}{}", style::Underline, UnderlineColor(Rgb(255, 0, 0)),
+///          UnderlineColor(Reset), style::NoUnderline);
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct UnderlineColor<C: Color>(pub C);
+
+impl<C: Color> fmt::Display for UnderlineColor<C> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.0.write_underline(f)
+    }
+}
+
+/// The colors a terminal supports.
+///
+/// The variants are ordered by how many colors they support.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ColorSupport {
+    /// No colors at all.
+    None,
+    /// The 16 ANSI colors (the named colors such as `Red` and `LightRed`).
+    Ansi16,
+    /// The 256 colors of `AnsiValue`.
+    Ansi256,
+    /// Any `Rgb` color.
+    TrueColor,
+}
+
+/// Detect the colors the terminal supports from the environment.
+///
+/// `COLORTERM` set to `truecolor` or `24bit` means truecolor, like a `TERM` naming it (e.g.
+/// `xterm-direct`), while `TERM` containing `256color` means 256 colors. Other terminals are
+/// assumed to support the 16 ANSI colors, unless `TERM` is `dumb`. On Windows, consoles supporting
+/// VT processing are taken to support truecolor, and legacy consoles the 16 ANSI colors (which
+/// `LegacyConsole` shows there).
+///
+/// Like `DetectColors`, this isn't authoritative: many terminals support more colors than they
+/// claim. See `query_support` for asking the terminal as well.
+pub fn detect_support() -> ColorSupport {
+    let var = |name| env::var(name).ok();
+    match support_from_env(var("COLORTERM").as_ref().map(|s| &s[..]),
+                           var("TERM").as_ref().map(|s| &s[..])) {
+        Some(support) => support,
+        None => console_support(),
+    }
+}
+
+/// Detect the colors to write to an output, respecting the user's preference for colors.
+///
+/// This follows the `NO_COLOR` and `CLICOLOR` conventions: no colors are written if `NO_COLOR`
+/// is set to anything but an empty string, or if the output isn't a terminal (`tty` is `false`)
+/// or `CLICOLOR` is `0`, unless `CLICOLOR_FORCE` is set to anything but `0`. Otherwise, the
+/// colors are those of `detect_support`, or at least the 16 ANSI colors if forced.
+///
+/// Writing through `Downgrade` with the result drops the colors, but nothing else.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use std::io::{self, Write};
+/// use termion::color::{self, Downgrade};
+///
+/// let stdout = io::stdout();
+/// let support = color::output_support(termion::is_tty(&stdout));
+/// let mut out = Downgrade::new(stdout.lock(), support);
+/// writeln!(out, "{}Warning{}: ...", color::Fg(color::Yellow), color::Fg(color::Reset)).unwrap();
+/// ```
+pub fn output_support(tty: bool) -> ColorSupport {
+    let var = |name| env::var(name).ok();
+    let preference = preference_from_env(var("NO_COLOR").as_ref().map(|s| &s[..]),
+                                         var("CLICOLOR").as_ref().map(|s| &s[..]),
+                                         var("CLICOLOR_FORCE").as_ref().map(|s| &s[..]),
+                                         tty);
+    match preference {
+        Some(true) => detect_support().max(ColorSupport::Ansi16),
+        Some(false) => ColorSupport::None,
+        None => detect_support(),
+    }
+}
+
+/// Whether colors are forced (`true`) or disabled (`false`) according to `NO_COLOR`, `CLICOLOR`
+/// and `CLICOLOR_FORCE`, if either.
+fn preference_from_env(no_color: Option<&str>,
+                       clicolor: Option<&str>,
+                       force: Option<&str>,
+                       tty: bool)
+                       -> Option<bool> {
+    if no_color.is_some_and(|v| !v.is_empty()) {
+        Some(false)
+    } else if force.is_some_and(|v| v != "0") {
+        Some(true)
+    } else if !tty || clicolor == Some("0") {
+        Some(false)
+    } else {
+        None
+    }
+}
+
+/// The colors supported according to `COLORTERM` and `TERM`, or `None` if `TERM` isn't set.
+fn support_from_env(colorterm: Option<&str>, term: Option<&str>) -> Option<ColorSupport> {
+    if colorterm == Some("truecolor") || colorterm == Some("24bit") {
+        return Some(ColorSupport::TrueColor);
+    }
+
+    let term = term?;
+    Some(if term == "dumb" {
+        ColorSupport::None
+    } else if term.contains("direct") || term.contains("truecolor") || term.contains("24bit") {
+        ColorSupport::TrueColor
+    } else if term.contains("256color") {
+        ColorSupport::Ansi256
+    } else {
+        ColorSupport::Ansi16
+    })
+}
+
+/// The colors supported without `TERM` being set, as on Windows.
+#[cfg(not(windows))]
+fn console_support() -> ColorSupport {
+    ColorSupport::None
+}
+
+#[cfg(windows)]
+fn console_support() -> ColorSupport {
+    use kernel32;
+    use winapi;
+
+    const ENABLE_VIRTUAL_TERMINAL_PROCESSING: winapi::DWORD = 0x0004;
+
+    unsafe {
+        let handle = kernel32::GetStdHandle(winapi::STD_OUTPUT_HANDLE);
+        let mut mode = 0;
+        if kernel32::GetConsoleMode(handle, &mut mode) == 0 {
+            return ColorSupport::None;
+        }
+        // VT processing is supported if it can be enabled.
+        if mode & ENABLE_VIRTUAL_TERMINAL_PROCESSING != 0 {
+            return ColorSupport::TrueColor;
+        }
+        if kernel32::SetConsoleMode(handle, mode | ENABLE_VIRTUAL_TERMINAL_PROCESSING) != 0 {
+            kernel32::SetConsoleMode(handle, mode);
+            return ColorSupport::TrueColor;
+        }
+        ColorSupport::Ansi16
+    }
+}
+
+/// Detect the colors the terminal supports from the environment, and from its primary device
+/// attributes, which it is asked for on `writer` through `events`.
+///
+/// This is like `detect_support`, except that terminals reporting ANSI color support (attribute
+/// 22) are taken to support at least the 16 ANSI colors, even if `TERM` says otherwise.
+pub fn query_support<R: Read, W: Write>(events: &mut Events<R>, writer: &mut W)
+                                        -> io::Result<ColorSupport> {
+    let support = detect_support();
+    if events.primary_attributes(writer)?.iter().skip(1).any(|&attribute| attribute == 22) {
+        Ok(support.max(ColorSupport::Ansi16))
+    } else {
+        Ok(support)
+    }
+}
+
+/// Whether the terminal's background is dark or light.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Theme {
+    /// Light text on a dark background.
+    Dark,
+    /// Dark text on a light background.
+    Light,
+}
+
+impl Theme {
+    /// The theme of a background color, telling them apart by their perceived lightness.
+    pub fn of(background: Rgb) -> Theme {
+        if lab(background)[0] > 50.0 {
+            Theme::Light
+        } else {
+            Theme::Dark
+        }
+    }
+}
+
+/// Ask the terminal for its background color on `writer`, and tell whether it is dark or light.
+///
+/// The response is read from `events`, see `Events::background_color`. Terminals not supporting
+/// the query give an error of kind `TimedOut`, in which case a dark background is a good guess.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use std::io::stdout;
+/// use termion::async_stdin;
+/// use termion::color::{self, Theme};
+/// use termion::input::TermRead;
+/// use termion::raw::IntoRawMode;
+///
+/// let mut stdout = stdout().into_raw_mode().unwrap();
+/// let mut events = async_stdin().events();
+/// let theme = color::query_theme(&mut events, &mut stdout).unwrap_or(Theme::Dark);
+/// ```
+pub fn query_theme<R: Read, W: Write>(events: &mut Events<R>, writer: &mut W)
+                                      -> io::Result<Theme> {
+    Ok(Theme::of(events.background_color(writer)?))
+}
+
 /// Types that allow detection of the colors they support.
 pub trait DetectColors {
     /// How many ANSI colors are supported (from 8 to 256)?
@@ -238,3 +1189,754 @@ fn detect_color<W: Write>(stdout: &mut RawTerminal<W>,
     // If there was a response, the color is supported.
     Ok(total_read > 0)
 }
+
+/// A writer converting the colors written through it into ones the terminal supports.
+///
+/// Truecolor and 256-color sequences (including those of `Rgb`, `AnsiValue` and the named colors)
+/// are replaced by their closest equivalent among the supported colors, or dropped altogether if
+/// the terminal supports no colors. Anything else is passed on unchanged, so a single theme can be
+/// written for every terminal.
+///
+/// # Example
+///
+/// ```rust
+/// use std::io::Write;
+/// use termion::color::{self, ColorSupport, Downgrade};
+///
+/// let mut out = Downgrade::new(Vec::new(), ColorSupport::Ansi256);
+/// write!(out, "{}Orange", color::Fg(color::Rgb(255, 135, 0))).unwrap();
+/// assert_eq!(out.into_inner(), b"\x1B[38;5;208mOrange");
+/// ```
+pub struct Downgrade<W: Write> {
+    output: W,
+    support: ColorSupport,
+    /// The escape sequences written, including one not completely written yet.
+    sequences: Sequences,
+}
+
+impl<W: Write> Downgrade<W> {
+    /// Convert the colors written to `output` into those of `support`, e.g. as returned by
+    /// `detect_support`.
+    pub fn new(output: W, support: ColorSupport) -> Downgrade<W> {
+        Downgrade {
+            output,
+            support,
+            sequences: Sequences::new(),
+        }
+    }
+
+    /// Get a reference to the output.
+    pub fn get_ref(&self) -> &W {
+        &self.output
+    }
+
+    /// Get back the output, along with any incomplete escape sequence written last.
+    pub fn into_inner(mut self) -> W {
+        let _ = self.output.write_all(self.sequences.pending());
+        self.output
+    }
+
+    /// Write an escape sequence, converting it if it sets colors.
+    fn write_sequence(&mut self, seq: &[u8]) -> io::Result<()> {
+        let params = match seq {
+            [b'\x1B', b'[', params @ .., b'm'] => str::from_utf8(params).ok(),
+            _ => None,
+        };
+        match params {
+            Some(params) => match downgrade_sgr(params, self.support) {
+                Some(params) => write!(self.output, csi!("{}m"), params),
+                None => Ok(()),
+            },
+            None => self.output.write_all(seq),
+        }
+    }
+}
+
+impl<W: Write> Write for Downgrade<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.support == ColorSupport::TrueColor && self.sequences.pending().is_empty() {
+            return self.output.write(buf);
+        }
+
+        let mut sequences = mem::replace(&mut self.sequences, Sequences::new());
+        let res = sequences.split(buf, |chunk| match chunk {
+            Chunk::Text(text) => self.output.write_all(text),
+            Chunk::Sequence(seq) => self.write_sequence(seq),
+        });
+        self.sequences = sequences;
+        res.map(|()| buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.output.flush()
+    }
+}
+
+/// A writer showing the colors written through it on legacy Windows consoles.
+///
+/// Consoles without VT processing (before Windows 10, and old builds of it) print escape sequences
+/// as they are. This converts the SGR sequences written through it (e.g. of `Fg`, `Bg` and
+/// `style::Bold`) into console text attributes, approximating the colors by the 16 ANSI colors,
+/// and drops any other escape sequence. The previous attributes are restored when it is dropped.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use std::io::{self, Write};
+/// use termion::color::{self, ColorSupport, LegacyConsole};
+///
+/// let mut out: Box<Write> = match color::detect_support() {
+///     ColorSupport::Ansi16 => Box::new(LegacyConsole::new(io::stdout()).unwrap()),
+///     _ => Box::new(io::stdout()),
+/// };
+/// writeln!(out, "{}Red{}", color::Fg(color::Red), color::Fg(color::Reset)).unwrap();
+/// ```
+#[cfg(windows)]
+pub struct LegacyConsole<W: Write> {
+    output: W,
+    handle: ::winapi::HANDLE,
+    /// The attributes to restore, which also give the default colors.
+    default: ::winapi::WORD,
+    /// The foreground and background colors, as console color values.
+    fg: ::winapi::WORD,
+    bg: ::winapi::WORD,
+    bold: bool,
+    reverse: bool,
+    /// The escape sequences written, including one not completely written yet.
+    sequences: Sequences,
+}
+
+#[cfg(windows)]
+impl<W: Write> LegacyConsole<W> {
+    /// Show the colors written to `output`, which has to be the console's standard output.
+    pub fn new(output: W) -> io::Result<LegacyConsole<W>> {
+        use kernel32;
+        use winapi;
+
+        let handle = unsafe { kernel32::GetStdHandle(winapi::STD_OUTPUT_HANDLE) };
+        if handle == winapi::INVALID_HANDLE_VALUE {
+            return Err(io::Error::last_os_error());
+        }
+        let mut info: winapi::CONSOLE_SCREEN_BUFFER_INFO = unsafe { mem::zeroed() };
+        if unsafe { kernel32::GetConsoleScreenBufferInfo(handle, &mut info) } == 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        Ok(LegacyConsole {
+            output,
+            handle,
+            default: info.wAttributes,
+            fg: info.wAttributes & 0xF,
+            bg: info.wAttributes >> 4 & 0xF,
+            bold: false,
+            reverse: false,
+            sequences: Sequences::new(),
+        })
+    }
+
+    /// Get a reference to the output.
+    pub fn get_ref(&self) -> &W {
+        &self.output
+    }
+
+    /// Get a mutable reference to the output.
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.output
+    }
+
+    /// Apply an escape sequence, dropping it unless it sets colors.
+    fn write_sequence(&mut self, seq: &[u8]) -> io::Result<()> {
+        let params = match seq {
+            [b'\x1B', b'[', params @ .., b'm'] => str::from_utf8(params).ok(),
+            _ => None,
+        };
+        // The colors are converted into basic ones by `Downgrade`'s rules first.
+        let params = match params.and_then(|p| downgrade_sgr(p, ColorSupport::Ansi16)) {
+            Some(params) => params,
+            None => return Ok(()),
+        };
+
+        // The 8 colors are given as RGB bits, which are in the opposite order for the console.
+        let console_color = |n: u16| (n & 1) << 2 | n & 2 | (n & 4) >> 2;
+        let mut params = params.split(';');
+        while let Some(param) = params.next() {
+            // Parameters with subparameters (e.g. `4:3` for curly underlines) are ignored.
+            let param = match param {
+                "" => 0,
+                param => match param.parse::<u16>() {
+                    Ok(param) => param,
+                    Err(_) => continue,
+                },
+            };
+            match param {
+                0 => {
+                    self.fg = self.default & 0xF;
+                    self.bg = self.default >> 4 & 0xF;
+                    self.bold = false;
+                    self.reverse = false;
+                }
+                1 => self.bold = true,
+                22 => self.bold = false,
+                7 => self.reverse = true,
+                27 => self.reverse = false,
+                n @ 30..=37 => self.fg = console_color(n - 30),
+                39 => self.fg = self.default & 0xF,
+                n @ 40..=47 => self.bg = console_color(n - 40),
+                49 => self.bg = self.default >> 4 & 0xF,
+                n @ 90..=97 => self.fg = console_color(n - 90) | 8,
+                n @ 100..=107 => self.bg = console_color(n - 100) | 8,
+                // Underline colors (`58;5;n`) can't be shown.
+                58 => {
+                    params.nth(1);
+                }
+                _ => (),
+            }
+        }
+        self.apply()
+    }
+
+    /// Set the console's attributes, once the text written before shows up.
+    fn apply(&mut self) -> io::Result<()> {
+        self.output.flush()?;
+
+        // Bold text is shown in the bright colors, like many terminals do.
+        let fg = if self.bold { self.fg | 8 } else { self.fg };
+        let (fg, bg) = if self.reverse { (self.bg, fg) } else { (fg, self.bg) };
+        let attributes = self.default & !0xFF | bg << 4 | fg;
+        if unsafe { ::kernel32::SetConsoleTextAttribute(self.handle, attributes) } == 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+}
+
+#[cfg(windows)]
+impl<W: Write> Drop for LegacyConsole<W> {
+    fn drop(&mut self) {
+        let _ = self.output.flush();
+        unsafe {
+            ::kernel32::SetConsoleTextAttribute(self.handle, self.default);
+        }
+    }
+}
+
+#[cfg(windows)]
+impl<W: Write> Write for LegacyConsole<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut sequences = mem::replace(&mut self.sequences, Sequences::new());
+        let res = sequences.split(buf, |chunk| match chunk {
+            Chunk::Text(text) => self.output.write_all(text),
+            Chunk::Sequence(seq) => self.write_sequence(seq),
+        });
+        self.sequences = sequences;
+        res.map(|()| buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.output.flush()
+    }
+}
+
+/// A color set by an SGR sequence.
+enum SgrColor {
+    /// One of the 256 indexed colors.
+    Indexed(u8),
+    /// A truecolor.
+    Rgb(Rgb),
+}
+
+/// Convert the colors set by the SGR parameters `params` into those of `support`. Returns `None`
+/// if nothing is left to set.
+fn downgrade_sgr(params: &str, support: ColorSupport) -> Option<String> {
+    if params.is_empty() {
+        return Some(String::new());
+    }
+
+    let mut out: Vec<String> = Vec::new();
+    let mut params = params.split(';');
+    while let Some(param) = params.next() {
+        let target = match param.split(':').next() {
+            Some("38") => 38,
+            Some("48") => 48,
+            Some("58") => 58,
+            _ => {
+                let basic = matches!(param.parse::<u8>(),
+                                     Ok(30..=37) | Ok(40..=47) | Ok(90..=97) | Ok(100..=107));
+                if !(basic && support == ColorSupport::None) {
+                    out.push(param.to_string());
+                }
+                continue;
+            }
+        };
+
+        // Either `38;5;n` and `38;2;r;g;b`, or their colon-separated forms (`38:2::r:g:b`).
+        let mut taken = Vec::new();
+        let color = if param.contains(':') {
+            let subs: Vec<&str> = param.split(':').collect();
+            parse_sgr_color(&subs[1..])
+        } else {
+            taken = match params.clone().next() {
+                Some("5") => params.by_ref().take(2).collect(),
+                Some("2") => params.by_ref().take(4).collect(),
+                _ => Vec::new(),
+            };
+            parse_sgr_color(&taken)
+        };
+
+        match color {
+            Some(color) => {
+                if let Some(param) = sgr_color(target, color, support) {
+                    out.push(param);
+                }
+            }
+            // Keep what isn't understood as it was, including the parameters taken for the color.
+            None => {
+                out.push(param.to_string());
+                out.extend(taken.iter().map(|sub| sub.to_string()));
+            }
+        }
+    }
+
+    if out.is_empty() {
+        None
+    } else {
+        Some(out.join(";"))
+    }
+}
+
+/// Parse the parameters following `38` or `48`, such as `5;n` or `2;r;g;b` (with an optional
+/// color space before the components).
+fn parse_sgr_color(subs: &[&str]) -> Option<SgrColor> {
+    let nums: Vec<u8> = subs.iter()
+        .filter(|sub| !sub.is_empty())
+        .map(|sub| sub.parse().ok())
+        .collect::<Option<_>>()?;
+    match nums[..] {
+        [5, n] => Some(SgrColor::Indexed(n)),
+        [2, r, g, b] | [2, _, r, g, b] => Some(SgrColor::Rgb(Rgb(r, g, b))),
+        _ => None,
+    }
+}
+
+/// The SGR parameters setting `color` as foreground (`target` 38), background (48) or underline
+/// (58) color with the given support, if any.
+fn sgr_color(target: u8, color: SgrColor, support: ColorSupport) -> Option<String> {
+    let index = match (color, support) {
+        (_, ColorSupport::None) => return None,
+        (SgrColor::Rgb(rgb), ColorSupport::TrueColor) => {
+            return Some(format!("{};2;{};{};{}", target, rgb.0, rgb.1, rgb.2));
+        }
+        (SgrColor::Rgb(rgb), ColorSupport::Ansi256) => AnsiValue::from(rgb).0,
+        (SgrColor::Indexed(n), ColorSupport::Ansi256) |
+        (SgrColor::Indexed(n), ColorSupport::TrueColor) => n,
+        (SgrColor::Indexed(n), ColorSupport::Ansi16) if n < 16 => n,
+        (SgrColor::Indexed(n), ColorSupport::Ansi16) => nearest_16(Rgb::from(AnsiValue(n))).0,
+        (SgrColor::Rgb(rgb), ColorSupport::Ansi16) => nearest_16(rgb).0,
+    };
+
+    // There are no basic sequences for underline colors.
+    if support == ColorSupport::Ansi16 && target != 58 {
+        // The basic sequences, e.g. `31` for red and `91` for bright red.
+        let base = if target == 38 { 30 } else { 40 };
+        Some(if index < 8 {
+            (base + index).to_string()
+        } else {
+            (base + 60 + index - 8).to_string()
+        })
+    } else {
+        Some(format!("{};5;{}", target, index))
+    }
+}
+
+/// The CSS named colors, sorted by name.
+const WEB_COLORS: [(&str, (u8, u8, u8)); 148] = [
+    ("aliceblue", (240, 248, 255)),
+    ("antiquewhite", (250, 235, 215)),
+    ("aqua", (0, 255, 255)),
+    ("aquamarine", (127, 255, 212)),
+    ("azure", (240, 255, 255)),
+    ("beige", (245, 245, 220)),
+    ("bisque", (255, 228, 196)),
+    ("black", (0, 0, 0)),
+    ("blanchedalmond", (255, 235, 205)),
+    ("blue", (0, 0, 255)),
+    ("blueviolet", (138, 43, 226)),
+    ("brown", (165, 42, 42)),
+    ("burlywood", (222, 184, 135)),
+    ("cadetblue", (95, 158, 160)),
+    ("chartreuse", (127, 255, 0)),
+    ("chocolate", (210, 105, 30)),
+    ("coral", (255, 127, 80)),
+    ("cornflowerblue", (100, 149, 237)),
+    ("cornsilk", (255, 248, 220)),
+    ("crimson", (220, 20, 60)),
+    ("cyan", (0, 255, 255)),
+    ("darkblue", (0, 0, 139)),
+    ("darkcyan", (0, 139, 139)),
+    ("darkgoldenrod", (184, 134, 11)),
+    ("darkgray", (169, 169, 169)),
+    ("darkgreen", (0, 100, 0)),
+    ("darkgrey", (169, 169, 169)),
+    ("darkkhaki", (189, 183, 107)),
+    ("darkmagenta", (139, 0, 139)),
+    ("darkolivegreen", (85, 107, 47)),
+    ("darkorange", (255, 140, 0)),
+    ("darkorchid", (153, 50, 204)),
+    ("darkred", (139, 0, 0)),
+    ("darksalmon", (233, 150, 122)),
+    ("darkseagreen", (143, 188, 143)),
+    ("darkslateblue", (72, 61, 139)),
+    ("darkslategray", (47, 79, 79)),
+    ("darkslategrey", (47, 79, 79)),
+    ("darkturquoise", (0, 206, 209)),
+    ("darkviolet", (148, 0, 211)),
+    ("deeppink", (255, 20, 147)),
+    ("deepskyblue", (0, 191, 255)),
+    ("dimgray", (105, 105, 105)),
+    ("dimgrey", (105, 105, 105)),
+    ("dodgerblue", (30, 144, 255)),
+    ("firebrick", (178, 34, 34)),
+    ("floralwhite", (255, 250, 240)),
+    ("forestgreen", (34, 139, 34)),
+    ("fuchsia", (255, 0, 255)),
+    ("gainsboro", (220, 220, 220)),
+    ("ghostwhite", (248, 248, 255)),
+    ("gold", (255, 215, 0)),
+    ("goldenrod", (218, 165, 32)),
+    ("gray", (128, 128, 128)),
+    ("green", (0, 128, 0)),
+    ("greenyellow", (173, 255, 47)),
+    ("grey", (128, 128, 128)),
+    ("honeydew", (240, 255, 240)),
+    ("hotpink", (255, 105, 180)),
+    ("indianred", (205, 92, 92)),
+    ("indigo", (75, 0, 130)),
+    ("ivory", (255, 255, 240)),
+    ("khaki", (240, 230, 140)),
+    ("lavender", (230, 230, 250)),
+    ("lavenderblush", (255, 240, 245)),
+    ("lawngreen", (124, 252, 0)),
+    ("lemonchiffon", (255, 250, 205)),
+    ("lightblue", (173, 216, 230)),
+    ("lightcoral", (240, 128, 128)),
+    ("lightcyan", (224, 255, 255)),
+    ("lightgoldenrodyellow", (250, 250, 210)),
+    ("lightgray", (211, 211, 211)),
+    ("lightgreen", (144, 238, 144)),
+    ("lightgrey", (211, 211, 211)),
+    ("lightpink", (255, 182, 193)),
+    ("lightsalmon", (255, 160, 122)),
+    ("lightseagreen", (32, 178, 170)),
+    ("lightskyblue", (135, 206, 250)),
+    ("lightslategray", (119, 136, 153)),
+    ("lightslategrey", (119, 136, 153)),
+    ("lightsteelblue", (176, 196, 222)),
+    ("lightyellow", (255, 255, 224)),
+    ("lime", (0, 255, 0)),
+    ("limegreen", (50, 205, 50)),
+    ("linen", (250, 240, 230)),
+    ("magenta", (255, 0, 255)),
+    ("maroon", (128, 0, 0)),
+    ("mediumaquamarine", (102, 205, 170)),
+    ("mediumblue", (0, 0, 205)),
+    ("mediumorchid", (186, 85, 211)),
+    ("mediumpurple", (147, 112, 219)),
+    ("mediumseagreen", (60, 179, 113)),
+    ("mediumslateblue", (123, 104, 238)),
+    ("mediumspringgreen", (0, 250, 154)),
+    ("mediumturquoise", (72, 209, 204)),
+    ("mediumvioletred", (199, 21, 133)),
+    ("midnightblue", (25, 25, 112)),
+    ("mintcream", (245, 255, 250)),
+    ("mistyrose", (255, 228, 225)),
+    ("moccasin", (255, 228, 181)),
+    ("navajowhite", (255, 222, 173)),
+    ("navy", (0, 0, 128)),
+    ("oldlace", (253, 245, 230)),
+    ("olive", (128, 128, 0)),
+    ("olivedrab", (107, 142, 35)),
+    ("orange", (255, 165, 0)),
+    ("orangered", (255, 69, 0)),
+    ("orchid", (218, 112, 214)),
+    ("palegoldenrod", (238, 232, 170)),
+    ("palegreen", (152, 251, 152)),
+    ("paleturquoise", (175, 238, 238)),
+    ("palevioletred", (219, 112, 147)),
+    ("papayawhip", (255, 239, 213)),
+    ("peachpuff", (255, 218, 185)),
+    ("peru", (205, 133, 63)),
+    ("pink", (255, 192, 203)),
+    ("plum", (221, 160, 221)),
+    ("powderblue", (176, 224, 230)),
+    ("purple", (128, 0, 128)),
+    ("rebeccapurple", (102, 51, 153)),
+    ("red", (255, 0, 0)),
+    ("rosybrown", (188, 143, 143)),
+    ("royalblue", (65, 105, 225)),
+    ("saddlebrown", (139, 69, 19)),
+    ("salmon", (250, 128, 114)),
+    ("sandybrown", (244, 164, 96)),
+    ("seagreen", (46, 139, 87)),
+    ("seashell", (255, 245, 238)),
+    ("sienna", (160, 82, 45)),
+    ("silver", (192, 192, 192)),
+    ("skyblue", (135, 206, 235)),
+    ("slateblue", (106, 90, 205)),
+    ("slategray", (112, 128, 144)),
+    ("slategrey", (112, 128, 144)),
+    ("snow", (255, 250, 250)),
+    ("springgreen", (0, 255, 127)),
+    ("steelblue", (70, 130, 180)),
+    ("tan", (210, 180, 140)),
+    ("teal", (0, 128, 128)),
+    ("thistle", (216, 191, 216)),
+    ("tomato", (255, 99, 71)),
+    ("turquoise", (64, 224, 208)),
+    ("violet", (238, 130, 238)),
+    ("wheat", (245, 222, 179)),
+    ("white", (255, 255, 255)),
+    ("whitesmoke", (245, 245, 245)),
+    ("yellow", (255, 255, 0)),
+    ("yellowgreen", (154, 205, 50)),
+];
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use input::TermRead;
+
+    #[test]
+    fn test_downgrade() {
+        let downgrade = |support, input: &str| {
+            let mut out = Downgrade::new(Vec::new(), support);
+            // Split the input, so sequences are written in parts.
+            for chunk in input.as_bytes().chunks(3) {
+                out.write_all(chunk).unwrap();
+            }
+            String::from_utf8(out.into_inner()).unwrap()
+        };
+
+        let input = format!("{}a{}b{}{}c\x1B[1;38:2::0:0:238;4m\x1B[2J\x1B7",
+                            Fg(Rgb(255, 135, 0)),
+                            Bg(LightRed),
+                            Fg(AnsiValue(196)),
+                            Fg(Reset));
+        assert_eq!(downgrade(ColorSupport::TrueColor, &input), input);
+        assert_eq!(downgrade(ColorSupport::Ansi256, &input),
+                   "\x1B[38;5;208ma\x1B[48;5;9mb\x1B[38;5;196m\x1B[39mc\x1B[1;38;5;21;4m\
+                    \x1B[2J\x1B7");
+        assert_eq!(downgrade(ColorSupport::Ansi16, &input),
+                   "\x1B[31ma\x1B[101mb\x1B[91m\x1B[39mc\x1B[1;34;4m\x1B[2J\x1B7");
+        assert_eq!(downgrade(ColorSupport::None, &input),
+                   "ab\x1B[39mc\x1B[1;4m\x1B[2J\x1B7");
+        assert_eq!(downgrade(ColorSupport::None, "\x1B[31mx\x1B[m\x1B["), "x\x1B[m\x1B[");
+        // String sequences are passed on whole.
+        assert_eq!(downgrade(ColorSupport::None, "\x1B]8;;http://a/\x1B\\\x1B[31ma\x1B]8;;\x07"),
+                   "\x1B]8;;http://a/\x1B\\a\x1B]8;;\x07");
+        // Colors which don't parse are kept whole.
+        assert_eq!(downgrade(ColorSupport::Ansi16, "\x1B[1;38;2;300;0;0;4m"),
+                   "\x1B[1;38;2;300;0;0;4m");
+        assert_eq!(downgrade(ColorSupport::Ansi256, "\x1B[48;5m"), "\x1B[48;5m");
+    }
+
+    #[test]
+    fn test_support_from_env() {
+        assert_eq!(support_from_env(Some("truecolor"), Some("xterm")),
+                   Some(ColorSupport::TrueColor));
+        assert_eq!(support_from_env(None, Some("xterm-direct")), Some(ColorSupport::TrueColor));
+        assert_eq!(support_from_env(None, Some("xterm-256color")), Some(ColorSupport::Ansi256));
+        assert_eq!(support_from_env(Some(""), Some("linux")), Some(ColorSupport::Ansi16));
+        assert_eq!(support_from_env(None, Some("dumb")), Some(ColorSupport::None));
+        assert_eq!(support_from_env(None, None), None);
+        assert!(ColorSupport::Ansi256 > ColorSupport::Ansi16);
+    }
+
+    #[test]
+    fn test_preference_from_env() {
+        assert_eq!(preference_from_env(None, None, None, true), None);
+        assert_eq!(preference_from_env(None, None, None, false), Some(false));
+        assert_eq!(preference_from_env(Some("1"), None, Some("1"), true), Some(false));
+        assert_eq!(preference_from_env(Some(""), None, None, true), None);
+        assert_eq!(preference_from_env(None, Some("0"), None, true), Some(false));
+        assert_eq!(preference_from_env(None, Some("0"), Some("1"), false), Some(true));
+        assert_eq!(preference_from_env(None, None, Some("0"), false), Some(false));
+    }
+
+    #[test]
+    fn test_theme() {
+        assert_eq!(Theme::of(Rgb(0, 43, 54)), Theme::Dark);
+        assert_eq!(Theme::of(Rgb(253, 246, 227)), Theme::Light);
+        assert_eq!(Theme::of(Rgb(0, 0, 255)), Theme::Dark);
+        assert_eq!(Theme::of(Rgb(0, 255, 0)), Theme::Light);
+
+        let mut events = b"\x1B]11;rgb:ff/ff/ff\x07".events();
+        assert_eq!(query_theme(&mut events, &mut Vec::new()).unwrap(), Theme::Light);
+    }
+
+    #[test]
+    fn test_any_color() {
+        let colors = [AnyColor::from(Red), LightCyan.into(), AnyColor::Ansi(200),
+                      AnsiValue(3).into(), Rgb(1, 2, 3).into(), Reset.into()];
+        let fg: Vec<String> = colors.iter().map(|&c| Fg(c).to_string()).collect();
+        assert_eq!(fg, ["\x1B[31m", "\x1B[96m", "\x1B[38;5;200m", "\x1B[38;5;3m",
+                        "\x1B[38;2;1;2;3m", "\x1B[39m"]);
+        let bg: Vec<String> = colors.iter().map(|&c| Bg(c).to_string()).collect();
+        assert_eq!(bg, ["\x1B[41m", "\x1B[106m", "\x1B[48;5;200m", "\x1B[48;5;3m",
+                        "\x1B[48;2;1;2;3m", "\x1B[49m"]);
+        assert_eq!(UnderlineColor(colors[1]).to_string(), "\x1B[58;5;14m");
+
+        assert_eq!("Default".parse(), Ok(AnyColor::Reset));
+        assert_eq!("light-red".parse(), Ok(AnyColor::Ansi(9)));
+        assert_eq!("208".parse(), Ok(AnyColor::Indexed(208)));
+        assert_eq!("orange".parse(), Ok(AnyColor::Rgb(255, 165, 0)));
+        assert_eq!("#010203".parse(), Ok(AnyColor::Rgb(1, 2, 3)));
+        assert!("nope".parse::<AnyColor>().is_err());
+    }
+
+    #[test]
+    fn test_write_bytes() {
+        let colors = [AnyColor::from(Red), LightCyan.into(), AnyColor::Ansi(200),
+                      AnsiValue(3).into(), Rgb(255, 0, 99).into(), Reset.into()];
+        for &color in &colors {
+            let mut out = Vec::new();
+            write_fg(&mut out, color).unwrap();
+            write_bg(&mut out, color).unwrap();
+            write_underline(&mut out, color).unwrap();
+            let expected = format!("{}{}{}", Fg(color), Bg(color), UnderlineColor(color));
+            assert_eq!(String::from_utf8(out).unwrap(), expected);
+        }
+
+        assert_eq!(::style::Bold.as_bytes(), b"\x1B[1m");
+        assert_eq!(::cursor::Hide.as_bytes(), b"\x1B[?25l");
+    }
+
+    #[test]
+    fn test_static_sequences() {
+        for i in 0..=255 {
+            assert_eq!(AnsiValue(i).fg_str(), format!("\x1B[38;5;{}m", i));
+            assert_eq!(AnsiValue(i).bg_str(), format!("\x1B[48;5;{}m", i));
+        }
+        assert_eq!(Fg(AnsiValue(42)).to_string(), "\x1B[38;5;42m");
+    }
+
+    #[test]
+    fn test_underline_color() {
+        assert_eq!(UnderlineColor(Red).to_string(), "\x1B[58;5;1m");
+        assert_eq!(UnderlineColor(AnsiValue(200)).to_string(), "\x1B[58;5;200m");
+        assert_eq!(UnderlineColor(Rgb(1, 2, 3)).to_string(), "\x1B[58;2;1;2;3m");
+        assert_eq!(UnderlineColor(Reset).to_string(), "\x1B[59m");
+        assert_eq!(UnderlineColor(&Blue as &dyn Color).to_string(), "\x1B[58;5;4m");
+
+        // Colors implementing only the foreground and background versions.
+        struct Custom(&'static str);
+        impl Color for Custom {
+            fn write_fg(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str(self.0)
+            }
+            fn write_bg(&self, _: &mut fmt::Formatter) -> fmt::Result {
+                Ok(())
+            }
+        }
+        assert_eq!(UnderlineColor(Custom("\x1B[38;5;9m")).to_string(), "\x1B[58;5;9m");
+        assert_eq!(UnderlineColor(Custom("\x1B[39m")).to_string(), "\x1B[59m");
+        assert_eq!(UnderlineColor(Custom("\x1B[31m")).to_string(), "");
+
+        let mut out = Downgrade::new(Vec::new(), ColorSupport::Ansi16);
+        write!(out, "{}{}", UnderlineColor(Rgb(255, 0, 0)), UnderlineColor(Reset)).unwrap();
+        assert_eq!(out.into_inner(), b"\x1B[58;5;9m\x1B[59m");
+    }
+
+    #[test]
+    fn test_gradient() {
+        assert_eq!(Rgb(0, 100, 200).lerp(Rgb(100, 0, 255), 0.25), Rgb(25, 75, 214));
+        assert_eq!(Rgb(0, 100, 200).lerp(Rgb(100, 0, 255), 2.0), Rgb(100, 0, 255));
+
+        for &(rgb, hsl) in &[(Rgb(255, 0, 0), Hsl(0.0, 1.0, 0.5)),
+                             (Rgb(0, 0, 128), Hsl(240.0, 1.0, 128.0 / 255.0 / 2.0)),
+                             (Rgb(128, 128, 128), Hsl(0.0, 0.0, 128.0 / 255.0)),
+                             (Rgb(255, 0, 255), Hsl(300.0, 1.0, 0.5))] {
+            assert_eq!(Hsl::from(rgb), hsl);
+            assert_eq!(Rgb::from(hsl), rgb);
+        }
+        for &rgb in &[Rgb(255, 135, 0), Rgb(12, 200, 99), Rgb(1, 2, 3), Rgb(250, 251, 255)] {
+            assert_eq!(Rgb::from(Hsl::from(rgb)), rgb);
+        }
+        assert_eq!(Rgb::from(Hsl(-120.0, 2.0, 0.5)), Rgb(0, 0, 255));
+
+        let colors = gradient(Rgb(10, 10, 10), Rgb(20, 40, 10), 4);
+        assert_eq!(colors.len(), 4);
+        assert_eq!(colors.collect::<Vec<_>>(),
+                   [Rgb(10, 10, 10), Rgb(13, 20, 10), Rgb(17, 30, 10), Rgb(20, 40, 10)]);
+        assert_eq!(gradient(Rgb(1, 1, 1), Rgb(2, 2, 2), 1).collect::<Vec<_>>(), [Rgb(1, 1, 1)]);
+        assert_eq!(gradient(Rgb(1, 1, 1), Rgb(2, 2, 2), 0).next(), None);
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!("#FF8700".parse(), Ok(Rgb(255, 135, 0)));
+        assert_eq!(" #0f8 ".parse(), Ok(Rgb(0, 255, 136)));
+        assert_eq!("RGB( 1,2 , 3)".parse(), Ok(Rgb(1, 2, 3)));
+        assert_eq!("Dark_Slate-Gray".parse(), Ok(Rgb(47, 79, 79)));
+        assert_eq!("aliceblue".parse(), Ok(Rgb(240, 248, 255)));
+        assert_eq!("yellowgreen".parse(), Ok(Rgb(154, 205, 50)));
+        for s in &["#12345", "#ggg", "rgb(1,2)", "rgb(1,2,3,4)", "rgb(256,0,0)", "blurple", ""] {
+            assert_eq!(s.parse::<Rgb>(), Err(ParseColorError(s.to_string())));
+        }
+        assert!(WEB_COLORS.windows(2).all(|w| w[0].0 < w[1].0));
+
+        assert_eq!("black".parse(), Ok(AnsiValue(0)));
+        assert_eq!("Bright-White".parse(), Ok(AnsiValue(15)));
+        assert_eq!("lightcyan".parse(), Ok(AnsiValue(14)));
+        assert_eq!("255".parse(), Ok(AnsiValue(255)));
+        assert!("256".parse::<AnsiValue>().is_err());
+        assert!("orange".parse::<AnsiValue>().is_err());
+    }
+
+    #[test]
+    fn test_palette() {
+        assert_eq!(SetPalette(4, Rgb(0, 0x80, 0xff)).to_string(), "\x1B]4;4;rgb:00/80/ff\x1B\\");
+        assert_eq!(ResetPalette(4).to_string(), "\x1B]104;4\x1B\\");
+        assert_eq!(ResetPalettes.to_string(), "\x1B]104\x1B\\");
+    }
+
+    #[test]
+    fn test_ansi_value() {
+        assert_eq!(AnsiValue::rgb(1, 2, 3), AnsiValue(16 + 36 + 12 + 3));
+        assert_eq!(AnsiValue::checked_rgb(5, 5, 5), Some(AnsiValue(231)));
+        assert_eq!(AnsiValue::checked_rgb(0, 6, 0), None);
+        assert_eq!(AnsiValue::checked_grayscale(23), Some(AnsiValue(255)));
+        assert_eq!(AnsiValue::checked_grayscale(24), None);
+
+        assert_eq!(AnsiValue::rgb(4, 0, 2).as_rgb(), Some((4, 0, 2)));
+        assert_eq!(AnsiValue::grayscale(7).as_grayscale(), Some(7));
+        assert_eq!(AnsiValue(15).as_rgb(), None);
+        assert_eq!(AnsiValue(231).as_grayscale(), None);
+        assert_eq!(Fg(AnsiValue(200)).to_string(), "\x1B[38;5;200m");
+    }
+
+    #[test]
+    fn test_rgb() {
+        assert_eq!(Fg(Rgb(1, 20, 255)).to_string(), "\x1B[38;2;1;20;255m");
+        assert_eq!(Bg(Rgb(0, 0, 0)).to_string(), "\x1B[48;2;0;0;0m");
+
+        assert_eq!(Rgb::from(AnsiValue(9)), Rgb(255, 0, 0));
+        assert_eq!(Rgb::from(AnsiValue::rgb(5, 1, 0)), Rgb(255, 95, 0));
+        assert_eq!(Rgb::from(AnsiValue::grayscale(23)), Rgb(238, 238, 238));
+
+        assert_eq!(AnsiValue::from(Rgb(250, 100, 5)), AnsiValue::rgb(5, 1, 0));
+        assert_eq!(AnsiValue::from(Rgb(130, 128, 131)), AnsiValue::grayscale(12));
+        for i in 16..=255 {
+            assert_eq!(AnsiValue::from(Rgb::from(AnsiValue(i))), AnsiValue(i));
+        }
+
+        assert_eq!(nearest_16(Rgb(10, 20, 200)), AnsiValue(4));
+        assert_eq!(nearest_16(Rgb(250, 250, 240)), AnsiValue(15));
+        for i in 0..16 {
+            assert_eq!(nearest_16(Rgb::from(AnsiValue(i))), AnsiValue(i));
+        }
+
+        assert_eq!(Rgb(1, 2, 3).distance(Rgb(1, 2, 3)), 0.0);
+        // Green differences are much more visible than blue ones.
+        assert!(Rgb(0, 0, 0).distance(Rgb(0, 40, 0)) > Rgb(0, 0, 0).distance(Rgb(0, 0, 40)));
+    }
+}
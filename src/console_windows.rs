@@ -0,0 +1,333 @@
+//! Reading the native input records of the Windows console.
+
+use std::char;
+use std::collections::VecDeque;
+use std::io;
+use std::mem;
+use std::time::{Duration, Instant};
+
+use winapi;
+use kernel32;
+
+use event::{Event, Key, ModifiedKey, Modifiers, MouseButton, MouseEvent};
+
+const ENABLE_VIRTUAL_TERMINAL_INPUT: winapi::DWORD = 0x0200;
+
+/// Construct an iterator over the events of the console's input records.
+///
+/// Unlike `TermRead::events`, this doesn't rely on the console translating its input into escape
+/// sequences (`ENABLE_VIRTUAL_TERMINAL_INPUT`), which legacy consoles and older builds of Windows
+/// 10 lack. The translation is disabled until the iterator is dropped, which restores the previous
+/// console mode.
+///
+/// Mouse input is enabled too, which disables the selection with the mouse (quick edit mode) in
+/// the meantime. The mouse is reported like with `MouseTerminal`, i.e. moves are only reported
+/// while a button is held, unless `report_motion` is called. Resizes of the screen buffer are
+/// reported as `Event::Resize`, with the size `terminal_size` returns.
+pub fn console_events() -> io::Result<ConsoleEvents> {
+    let handle = unsafe { kernel32::GetStdHandle(winapi::STD_INPUT_HANDLE) };
+    if handle == winapi::INVALID_HANDLE_VALUE {
+        return Err(io::Error::last_os_error());
+    }
+
+    let mut prev_mode = 0;
+    if unsafe { kernel32::GetConsoleMode(handle, &mut prev_mode) } == 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    let mode = (prev_mode & !(ENABLE_VIRTUAL_TERMINAL_INPUT | winapi::ENABLE_QUICK_EDIT_MODE))
+        | winapi::ENABLE_MOUSE_INPUT | winapi::ENABLE_WINDOW_INPUT | winapi::ENABLE_EXTENDED_FLAGS;
+    if unsafe { kernel32::SetConsoleMode(handle, mode) } == 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    Ok(ConsoleEvents {
+        handle,
+        prev_mode,
+        pending: VecDeque::new(),
+        buttons: 0,
+        motion: false,
+        high_surrogate: None,
+    })
+}
+
+/// An iterator over the events of the console's input records, see `console_events`.
+pub struct ConsoleEvents {
+    handle: winapi::HANDLE,
+    /// The console mode to restore.
+    prev_mode: winapi::DWORD,
+    /// The events of the last record not returned yet.
+    pending: VecDeque<Event>,
+    /// The mouse buttons held according to the last mouse record.
+    buttons: winapi::DWORD,
+    /// Whether to report mouse moves without a button held.
+    motion: bool,
+    /// The first half of a surrogate pair, if its second half is still to be read.
+    high_surrogate: Option<u16>,
+}
+
+impl ConsoleEvents {
+    /// Report mouse moves without any button held as well, like `MouseMotionTerminal`.
+    pub fn report_motion(mut self) -> ConsoleEvents {
+        self.motion = true;
+        self
+    }
+
+    /// Wait up to `timeout` for the next event, returning `None` if none arrives in time.
+    pub fn poll(&mut self, timeout: Duration) -> io::Result<Option<Event>> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            if let Some(event) = self.pending.pop_front() {
+                return Ok(Some(event));
+            }
+            if !self.wait(deadline.saturating_duration_since(Instant::now()))? {
+                return Ok(None);
+            }
+            self.read_record()?;
+        }
+    }
+
+    /// Wait up to `timeout` for an input record. Returns `false` if none arrives in time.
+    fn wait(&self, timeout: Duration) -> io::Result<bool> {
+        let millis = timeout.as_secs()
+            .saturating_mul(1000)
+            .saturating_add(u64::from(timeout.subsec_millis()));
+        let millis = millis.min(winapi::INFINITE as u64 - 1) as winapi::DWORD;
+
+        match unsafe { kernel32::WaitForSingleObject(self.handle, millis) } {
+            winapi::WAIT_OBJECT_0 => Ok(true),
+            winapi::WAIT_TIMEOUT => Ok(false),
+            _ => Err(io::Error::last_os_error()),
+        }
+    }
+
+    /// Read the next input record, adding its events to `pending`.
+    fn read_record(&mut self) -> io::Result<()> {
+        let mut record: winapi::INPUT_RECORD = unsafe { mem::zeroed() };
+        let mut read = 0;
+        if unsafe { kernel32::ReadConsoleInputW(self.handle, &mut record, 1, &mut read) } == 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        if read == 1 {
+            self.convert(&record);
+        }
+        Ok(())
+    }
+
+    fn convert(&mut self, record: &winapi::INPUT_RECORD) {
+        match record.EventType {
+            winapi::KEY_EVENT => {
+                let key_event = unsafe { record.KeyEvent() };
+                if let Some(key) = self.convert_key(key_event) {
+                    for _ in 0..key_event.wRepeatCount.max(1) {
+                        self.pending.push_back(Event::Key(key));
+                    }
+                }
+            }
+            winapi::MOUSE_EVENT => {
+                let mouse_event = unsafe { record.MouseEvent() };
+                self.convert_mouse(mouse_event);
+            }
+            winapi::WINDOW_BUFFER_SIZE_EVENT => {
+                let size = unsafe { record.WindowBufferSizeEvent() }.dwSize;
+                self.pending.push_back(Event::Resize(size.X as u16, size.Y as u16));
+            }
+            winapi::FOCUS_EVENT => {
+                let focus_event = unsafe { record.FocusEvent() };
+                self.pending.push_back(if focus_event.bSetFocus != 0 {
+                    Event::FocusGained
+                } else {
+                    Event::FocusLost
+                });
+            }
+            _ => {}
+        }
+    }
+
+    /// Convert a key record, combining the halves of surrogate pairs.
+    fn convert_key(&mut self, record: &winapi::KEY_EVENT_RECORD) -> Option<Key> {
+        let unit = record.UnicodeChar;
+        // Characters typed as their code on the keypad while holding Alt are only reported with
+        // the release of Alt, while other releases aren't reported, as terminals can't either.
+        let composed = record.wVirtualKeyCode as winapi::c_int == winapi::VK_MENU && unit != 0;
+        if record.bKeyDown == 0 && !composed {
+            return None;
+        }
+
+        match unit {
+            // Characters outside the BMP (e.g. emoji) come as a record for each half of their
+            // surrogate pair.
+            0xD800..=0xDBFF => {
+                self.high_surrogate = Some(unit);
+                None
+            }
+            0xDC00..=0xDFFF => {
+                let high = self.high_surrogate.take()?;
+                let c = char::decode_utf16([high, unit].iter().cloned()).next()?.ok()?;
+                Some(Key::Char(c))
+            }
+            _ => {
+                if unit != 0 {
+                    self.high_surrogate = None;
+                }
+                if composed {
+                    convert_char(char::from_u32(u32::from(unit))?, Modifiers::default())
+                } else {
+                    translate_key(record)
+                }
+            }
+        }
+    }
+
+    fn convert_mouse(&mut self, record: &winapi::MOUSE_EVENT_RECORD) {
+        // The position is relative to the screen buffer, which may be scrolled back.
+        let top = window_top().unwrap_or(0);
+        let x = (record.dwMousePosition.X as i32 + 1).max(1) as u16;
+        let y = (record.dwMousePosition.Y as i32 - top as i32 + 1).max(1) as u16;
+        let modifiers = modifiers(record.dwControlKeyState);
+        let mut events = Vec::new();
+
+        // The wheel's direction is the sign of the high word of the button state.
+        let delta = (record.dwButtonState >> 16) as u16 as i16;
+        if record.dwEventFlags & winapi::MOUSE_WHEELED != 0 {
+            let button = if delta > 0 { MouseButton::WheelUp } else { MouseButton::WheelDown };
+            events.push(MouseEvent::Press(button, x, y));
+        } else if record.dwEventFlags & winapi::MOUSE_HWHEELED != 0 {
+            let button = if delta > 0 { MouseButton::WheelRight } else { MouseButton::WheelLeft };
+            events.push(MouseEvent::Press(button, x, y));
+        } else {
+            let known = BUTTONS.iter().fold(0, |known, &(mask, _)| known | mask);
+            let buttons = record.dwButtonState & known;
+            let changed = buttons ^ self.buttons;
+            self.buttons = buttons;
+
+            for &(mask, button) in BUTTONS.iter() {
+                if changed & buttons & mask != 0 {
+                    events.push(MouseEvent::Press(button, x, y));
+                }
+            }
+            if changed & !buttons != 0 {
+                events.push(MouseEvent::Release(x, y));
+            }
+            if changed == 0 && record.dwEventFlags & winapi::MOUSE_MOVED != 0 {
+                if buttons != 0 {
+                    events.push(MouseEvent::Hold(x, y));
+                } else if self.motion {
+                    events.push(MouseEvent::Move(x, y));
+                }
+            }
+        }
+
+        for event in events {
+            self.pending.push_back(if modifiers == Modifiers::default() {
+                Event::Mouse(event)
+            } else {
+                Event::ModifiedMouse(modifiers, event)
+            });
+        }
+    }
+}
+
+/// The buttons reported by the console, as masks of the button state of mouse records.
+const BUTTONS: [(winapi::DWORD, MouseButton); 3] = [
+    (winapi::FROM_LEFT_1ST_BUTTON_PRESSED, MouseButton::Left),
+    (winapi::RIGHTMOST_BUTTON_PRESSED, MouseButton::Right),
+    (winapi::FROM_LEFT_2ND_BUTTON_PRESSED, MouseButton::Middle),
+];
+
+/// The row of the screen buffer shown at the top of the console window.
+fn window_top() -> io::Result<i16> {
+    let handle = unsafe { kernel32::GetStdHandle(winapi::STD_OUTPUT_HANDLE) };
+    if handle == winapi::INVALID_HANDLE_VALUE {
+        return Err(io::Error::last_os_error());
+    }
+
+    let mut buffer_info: winapi::CONSOLE_SCREEN_BUFFER_INFO = unsafe { mem::zeroed() };
+    if unsafe { kernel32::GetConsoleScreenBufferInfo(handle, &mut buffer_info) } == 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    Ok(buffer_info.srWindow.Top)
+}
+
+// The console's input handle can be used from any thread.
+unsafe impl Send for ConsoleEvents {}
+
+impl Iterator for ConsoleEvents {
+    type Item = io::Result<Event>;
+
+    fn next(&mut self) -> Option<io::Result<Event>> {
+        loop {
+            if let Some(event) = self.pending.pop_front() {
+                return Some(Ok(event));
+            }
+            if let Err(err) = self.read_record() {
+                return Some(Err(err));
+            }
+        }
+    }
+}
+
+impl Drop for ConsoleEvents {
+    fn drop(&mut self) {
+        unsafe {
+            kernel32::SetConsoleMode(self.handle, self.prev_mode);
+        }
+    }
+}
+
+/// The modifiers held according to the `dwControlKeyState` of a record.
+fn modifiers(state: winapi::DWORD) -> Modifiers {
+    Modifiers {
+        shift: state & winapi::SHIFT_PRESSED != 0,
+        alt: state & (winapi::LEFT_ALT_PRESSED | winapi::RIGHT_ALT_PRESSED) != 0,
+        ctrl: state & (winapi::LEFT_CTRL_PRESSED | winapi::RIGHT_CTRL_PRESSED) != 0,
+    }
+}
+
+/// Convert a key record into the key terminals would report, or `None` for keys they don't
+/// report at all (e.g. Shift on its own).
+///
+/// Dead keys are composed with the following key by the console, which reports no character for
+/// the dead key itself.
+fn translate_key(record: &winapi::KEY_EVENT_RECORD) -> Option<Key> {
+    let modifiers = modifiers(record.dwControlKeyState);
+    let key = match record.wVirtualKeyCode as winapi::c_int {
+        winapi::VK_LEFT => ModifiedKey::Left,
+        winapi::VK_RIGHT => ModifiedKey::Right,
+        winapi::VK_UP => ModifiedKey::Up,
+        winapi::VK_DOWN => ModifiedKey::Down,
+        winapi::VK_HOME => ModifiedKey::Home,
+        winapi::VK_END => ModifiedKey::End,
+        winapi::VK_PRIOR => ModifiedKey::PageUp,
+        winapi::VK_NEXT => ModifiedKey::PageDown,
+        winapi::VK_DELETE => ModifiedKey::Delete,
+        winapi::VK_INSERT => ModifiedKey::Insert,
+        winapi::VK_BACK => ModifiedKey::Backspace,
+        winapi::VK_ESCAPE => ModifiedKey::Esc,
+        code @ winapi::VK_F1..=winapi::VK_F24 => ModifiedKey::F((code - winapi::VK_F1 + 1) as u8),
+        _ => return convert_char(char::from_u32(u32::from(record.UnicodeChar))?, modifiers),
+    };
+
+    Some(if modifiers == Modifiers::default() {
+        key.into()
+    } else {
+        Key::Modified(modifiers, key)
+    })
+}
+
+/// Convert the character typed by a key, like the parser does for the byte a terminal sends.
+fn convert_char(c: char, modifiers: Modifiers) -> Option<Key> {
+    Some(match c {
+        '\0' if modifiers.ctrl => Key::Null,
+        '\0' => return None,
+        '\n' | '\r' => Key::Char('\n'),
+        '\t' => Key::Char('\t'),
+        '\x01'..='\x1A' => Key::Ctrl((c as u8 - 0x01 + b'a') as char),
+        '\x1C'..='\x1F' => Key::Ctrl((c as u8 - 0x1C + b'4') as char),
+        // AltGr is reported as Ctrl and Alt held, and the character it types is reported plainly.
+        c if modifiers.alt && !modifiers.ctrl => Key::Alt(c),
+        c => Key::Char(c),
+    })
+}
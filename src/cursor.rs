@@ -1,10 +1,80 @@
 //! Cursor movement.
 
 use std::fmt;
+use std::io::{self, Read, Write};
+use std::mem;
+use std::str;
+
+use ansi::{Machine, State};
+use guard::ModeGuard;
+use input::TermRead;
 
 derive_csi_sequence!("Hide the cursor.", Hide, "?25l");
 derive_csi_sequence!("Show the cursor.", Show, "?25h");
 
+/// Save the cursor position, along with the text attributes and character set (DECSC).
+///
+/// This is restored by `Restore`.
+#[derive(Copy, Clone)]
+pub struct Save;
+
+impl fmt::Display for Save {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "\x1B7")
+    }
+}
+
+/// Restore the cursor position saved by `Save`, along with the text attributes and character set
+/// (DECRC).
+#[derive(Copy, Clone)]
+pub struct Restore;
+
+impl fmt::Display for Restore {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "\x1B8")
+    }
+}
+
+derive_csi_sequence!("Save the cursor position the SCO way, as a fallback for terminals without \
+                      `Save`. It is restored by `ScoRestore`. Note that xterm sets the left and \
+                      right margins instead while they are enabled (DECLRMM).",
+                     ScoSave,
+                     "s");
+derive_csi_sequence!("Restore the cursor position saved by `ScoSave`.", ScoRestore, "u");
+
+derive_csi_sequence!("Make the cursor blink.", EnableBlinking, "?12h");
+derive_csi_sequence!("Stop the cursor from blinking.", DisableBlinking, "?12l");
+
+derive_csi_sequence!("Restore the cursor shape the terminal is configured with.",
+                     DefaultStyle,
+                     "0 q");
+derive_csi_sequence!("Use a blinking block cursor.", BlinkingBlock, "1 q");
+derive_csi_sequence!("Use a steady block cursor.", SteadyBlock, "2 q");
+derive_csi_sequence!("Use a blinking underline cursor.", BlinkingUnderline, "3 q");
+derive_csi_sequence!("Use a steady underline cursor.", SteadyUnderline, "4 q");
+derive_csi_sequence!("Use a blinking bar cursor (an xterm extension).", BlinkingBar, "5 q");
+derive_csi_sequence!("Use a steady bar cursor (an xterm extension).", SteadyBar, "6 q");
+
+derive_csi_sequence!("Address rows relative to the scrolling region set with `scroll::Region`, so \
+                      `Goto(1, 1)` is the top left of the region (DECOM). This moves the cursor \
+                      there.",
+                     EnableOriginMode,
+                     "?6h");
+derive_csi_sequence!("Address rows relative to the whole screen again, moving the cursor to its \
+                      top left.",
+                     DisableOriginMode,
+                     "?6l");
+
+derive_csi_sequence!("Wrap to the next line when writing past the last column (DECAWM), which \
+                      terminals do by default.",
+                     EnableAutoWrap,
+                     "?7h");
+derive_csi_sequence!("Stop wrapping at the last column, so characters written past it overwrite \
+                      it instead. This allows writing to the bottom right corner without \
+                      scrolling.",
+                     DisableAutoWrap,
+                     "?7l");
+
 /// Goto some position ((1,1)-based).
 ///
 /// # Why one-based?
@@ -76,3 +146,711 @@ impl fmt::Display for Down {
         write!(f, csi!("{}B"), self.0)
     }
 }
+
+/// Move the cursor by the given number of columns and rows, which are negative for moving left and
+/// up.
+///
+/// This writes nothing for zero deltas, unlike `Left(0)` and the like, which move by one cell.
+///
+/// # Example
+///
+/// ```rust
+/// use termion::cursor::GotoRelative;
+///
+/// assert_eq!(GotoRelative(-2, 3).to_string(), "\x1B[2D\x1B[3B");
+/// ```
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub struct GotoRelative(pub i16, pub i16);
+
+impl fmt::Display for GotoRelative {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let (dx, dy) = (self.0.unsigned_abs(), self.1.unsigned_abs());
+        if self.0 < 0 {
+            write!(f, "{}", Left(dx))?;
+        } else if self.0 > 0 {
+            write!(f, "{}", Right(dx))?;
+        }
+        if self.1 < 0 {
+            write!(f, "{}", Up(dy))?;
+        } else if self.1 > 0 {
+            write!(f, "{}", Down(dy))?;
+        }
+        Ok(())
+    }
+}
+
+/// Set a tab stop at the column of the cursor (HTS), so tabs stop there.
+///
+/// Terminals start with a tab stop every 8 columns.
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub struct SetTabStop;
+
+impl fmt::Display for SetTabStop {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("\x1BH")
+    }
+}
+
+impl SetTabStop {
+    /// The sequence as bytes, for writing it without formatting.
+    #[inline]
+    pub fn as_bytes(&self) -> &'static [u8] {
+        b"\x1BH"
+    }
+}
+
+derive_csi_sequence!("Clear the tab stop at the column of the cursor (TBC).", ClearTabStop, "0g");
+derive_csi_sequence!("Clear every tab stop (TBC), so tabs move to the last column.",
+                     ClearAllTabStops,
+                     "3g");
+
+/// Move the cursor forward to the given number of tab stops (CHT), or the last column.
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub struct NextTabStop(pub u16);
+
+impl fmt::Display for NextTabStop {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, csi!("{}I"), self.0)
+    }
+}
+
+/// Move the cursor back to the given number of tab stops (CBT), or the first column.
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub struct PrevTabStop(pub u16);
+
+impl fmt::Display for PrevTabStop {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, csi!("{}Z"), self.0)
+    }
+}
+
+/// A terminal whose cursor is hidden, until this is dropped.
+///
+/// The cursor is shown again even when leaving early (e.g. by returning an error or panicking), so
+/// it isn't left invisible for the shell.
+///
+/// This can be obtained through the `From` implementations.
+///
+/// # Example
+///
+/// ```rust
+/// use std::io::{Write, stdout};
+/// use termion::cursor::HideCursor;
+///
+/// let mut stdout = HideCursor::from(stdout());
+/// write!(stdout, "Loading...").unwrap();
+/// ```
+pub struct HideCursor<W: Write> {
+    guard: ModeGuard<W>,
+}
+
+impl<W: Write> From<W> for HideCursor<W> {
+    fn from(from: W) -> HideCursor<W> {
+        HideCursor { guard: ModeGuard::new(from, Hide, Show) }
+    }
+}
+
+derive_mode_guard!(HideCursor);
+
+/// A terminal not wrapping at the last column (see `DisableAutoWrap`), until this is dropped.
+///
+/// Full-width renders can write the last column of the bottom row this way, without the terminal
+/// scrolling and corrupting the frame. Wrapping is enabled again even when leaving early.
+///
+/// This can be obtained through the `From` implementations.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use std::io::{Write, stdout};
+/// use termion::cursor::{Goto, NoAutoWrap};
+///
+/// let mut stdout = NoAutoWrap::from(stdout());
+/// write!(stdout, "{}{}", Goto(1, 24), "-".repeat(80)).unwrap();
+/// ```
+pub struct NoAutoWrap<W: Write> {
+    guard: ModeGuard<W>,
+}
+
+impl<W: Write> From<W> for NoAutoWrap<W> {
+    fn from(from: W) -> NoAutoWrap<W> {
+        NoAutoWrap { guard: ModeGuard::new(from, DisableAutoWrap, EnableAutoWrap) }
+    }
+}
+
+derive_mode_guard!(NoAutoWrap);
+
+/// Get the cursor position ((1,1)-based), by asking the terminal on `writer` and reading its
+/// response from `reader`.
+///
+/// The terminal has to be in raw mode, so the response is neither echoed nor held back until a
+/// newline. With a non-blocking reader such as `AsyncReader`, a missing response gives an error of
+/// kind `TimedOut` after a second. Any other input read before the response is discarded, so
+/// applications already reading events should use `Events::cursor_position` instead.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use std::io::stdout;
+/// use termion::{async_stdin, cursor};
+/// use termion::raw::IntoRawMode;
+///
+/// let mut stdout = stdout().into_raw_mode().unwrap();
+/// let (x, y) = cursor::position(&mut async_stdin(), &mut stdout).unwrap();
+/// ```
+pub fn position<R: Read, W: Write>(reader: &mut R, writer: &mut W) -> io::Result<(u16, u16)> {
+    reader.events().cursor_position(writer)
+}
+
+/// Find out whether the cursor is hidden, by asking the terminal on `writer` (DECRQM) and reading
+/// its response from `reader`.
+///
+/// This allows restoring the cursor's visibility exactly as it was found. The requirements are the
+/// same as for `position`, and terminals which can't tell give an error of kind `Other`. Use
+/// `Events::private_mode` with mode 25 from an event loop instead, or mode 12 for whether the
+/// cursor blinks.
+pub fn is_hidden<R: Read, W: Write>(reader: &mut R, writer: &mut W) -> io::Result<bool> {
+    match reader.events().private_mode(writer, 25)? {
+        Some(shown) => Ok(!shown),
+        None => Err(io::Error::other("The terminal can't tell whether the cursor is hidden.")),
+    }
+}
+
+/// A writer keeping track of where its output leaves the cursor.
+///
+/// Everything written is interpreted like a terminal would: characters advance the cursor and wrap
+/// at the last column (unless disabled with `DisableAutoWrap`), control characters such as `\r`,
+/// `\n`, backspace and tab move it, and so do the cursor movement sequences (including `Save` and
+/// `Restore`). Other sequences, such as colors, are skipped. This allows computing relative
+/// movements without querying the terminal with `position` for every frame.
+///
+/// The terminal is assumed to be in raw mode, so `\n` only moves down (without returning to the
+/// first column). Every character is assumed to take up a single column, and moving below the last
+/// row is assumed to scroll. Anything else changing the position (e.g. a resize or output of
+/// another program) has to be accounted for with `set_position` and `set_size`.
+///
+/// # Example
+///
+/// ```rust
+/// use std::io::Write;
+/// use termion::cursor::{Goto, TrackedWriter};
+///
+/// let mut out = TrackedWriter::new(Vec::new(), (1, 1), (80, 24));
+/// write!(out, "{}Hello\r\nworld", Goto(3, 4)).unwrap();
+/// assert_eq!(out.position(), (6, 5));
+/// ```
+pub struct TrackedWriter<W: Write> {
+    output: W,
+    /// The believed position of the cursor.
+    position: (u16, u16),
+    /// The size of the terminal.
+    size: (u16, u16),
+    /// The position saved by `Save` or `ScoSave`.
+    saved: (u16, u16),
+    /// Whether the last column has been written to, so the next character wraps first.
+    wrap_pending: bool,
+    /// The top and bottom row of the scrolling region.
+    margins: (u16, u16),
+    /// Whether rows are addressed relative to the scrolling region (DECOM).
+    origin: bool,
+    /// Whether characters written past the last column wrap (DECAWM).
+    auto_wrap: bool,
+    /// Whether there is a tab stop at each column (from the first one).
+    tab_stops: Vec<bool>,
+    /// Where the interpretation of the output stopped.
+    machine: Machine,
+    /// The parameters of the CSI sequence being written.
+    params: Vec<u8>,
+}
+
+impl<W: Write> TrackedWriter<W> {
+    /// Track the output written to `output`, starting at `position` in a terminal of the given
+    /// size, all given as columns and rows.
+    pub fn new(output: W, position: (u16, u16), size: (u16, u16)) -> TrackedWriter<W> {
+        let mut writer = TrackedWriter {
+            output,
+            position,
+            size,
+            saved: (1, 1),
+            wrap_pending: false,
+            margins: (1, size.1),
+            origin: false,
+            auto_wrap: true,
+            tab_stops: Vec::new(),
+            machine: Machine::new(),
+            params: Vec::new(),
+        };
+        writer.set_size(size);
+        writer
+    }
+
+    /// The believed position of the cursor ((1,1)-based).
+    pub fn position(&self) -> (u16, u16) {
+        self.position
+    }
+
+    /// Correct the believed position of the cursor, e.g. after querying it with `position`.
+    ///
+    /// The position is moved into the terminal, if it is outside it.
+    pub fn set_position(&mut self, position: (u16, u16)) {
+        self.position = (position.0.clamp(1, self.size.0), position.1.clamp(1, self.size.1));
+        self.wrap_pending = false;
+    }
+
+    /// Update the size of the terminal, e.g. after a resize.
+    ///
+    /// The position is moved into the terminal, if it is outside it now. The scrolling region is
+    /// reset to the whole terminal, like terminals do on resizes.
+    pub fn set_size(&mut self, size: (u16, u16)) {
+        self.size = (size.0.max(1), size.1.max(1));
+        // New columns get the default tab stops.
+        let columns = self.tab_stops.len();
+        self.tab_stops.extend((columns..self.size.0 as usize).map(|i| i % 8 == 0));
+        self.margins = (1, self.size.1);
+        self.position = (self.position.0.clamp(1, self.size.0),
+                         self.position.1.clamp(1, self.size.1));
+    }
+
+    /// Get a reference to the output.
+    pub fn get_ref(&self) -> &W {
+        &self.output
+    }
+
+    /// Get a mutable reference to the output.
+    ///
+    /// Anything written to it directly isn't tracked.
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.output
+    }
+
+    /// Get back the output.
+    pub fn into_inner(self) -> W {
+        self.output
+    }
+
+    /// Interpret a byte of output.
+    fn track(&mut self, byte: u8) {
+        // A sequence cut short leaves the byte to the text after it.
+        let state = if self.machine.ends_before(byte) {
+            State::Ground
+        } else {
+            self.machine.state()
+        };
+        let sequence = self.machine.advance(byte);
+        match (state, self.machine.state()) {
+            (State::Ground, _) if !sequence => self.control(byte),
+            (State::Escape, State::Csi) => self.params.clear(),
+            (State::Escape, State::Ground) => self.escape(byte),
+            (State::Csi, State::Ground) => self.csi_final(byte),
+            (State::Csi, _) => self.params.push(byte),
+            _ => {}
+        }
+    }
+
+    /// Interpret a byte outside of any sequence.
+    fn control(&mut self, byte: u8) {
+        let (width, _) = self.size;
+        match byte {
+            b'\r' => self.goto_column(1),
+            b'\n' | b'\x0B' | b'\x0C' => self.line_feed(),
+            b'\x08' => {
+                let column = self.position.0.saturating_sub(1);
+                self.goto_column(column);
+            }
+            b'\t' => self.tab(1),
+            // Other control characters, and the continuation bytes of UTF-8 characters.
+            0x00..=0x1F | 0x7F..=0xBF => {}
+            _ => {
+                if self.wrap_pending {
+                    self.goto_column(1);
+                    self.line_feed();
+                }
+                if self.position.0 >= width {
+                    self.wrap_pending = self.auto_wrap;
+                } else {
+                    self.position.0 += 1;
+                }
+            }
+        }
+    }
+
+    /// Interpret the final byte of a two-byte escape sequence.
+    fn escape(&mut self, byte: u8) {
+        match byte {
+            b'7' => self.saved = self.position,
+            b'8' => {
+                let saved = self.saved;
+                self.set_position(saved);
+            }
+            b'D' => self.line_feed(),
+            b'E' => {
+                self.goto_column(1);
+                self.line_feed();
+            }
+            b'M' => self.move_rows(true, 1),
+            b'H' => self.set_tab_stop(true),
+            // A hard reset (see `clear::HardReset`).
+            b'c' => {
+                self.soft_reset();
+                self.auto_wrap = true;
+                // Setting the size again brings back the default tab stops.
+                self.tab_stops.clear();
+                let size = self.size;
+                self.set_size(size);
+                self.set_position((1, 1));
+            }
+            _ => {}
+        }
+    }
+
+    /// Interpret the final byte of a CSI sequence, with the parameters written before it.
+    fn csi_final(&mut self, final_byte: u8) {
+        let params = mem::take(&mut self.params);
+        self.csi(&params, final_byte);
+        self.params = params;
+    }
+
+    /// Interpret a CSI sequence with the given parameters and final byte.
+    fn csi(&mut self, params: &[u8], final_byte: u8) {
+        if params == b"?6" && (final_byte == b'h' || final_byte == b'l') {
+            self.origin = final_byte == b'h';
+            self.home();
+            return;
+        }
+        if params == b"?7" && (final_byte == b'h' || final_byte == b'l') {
+            self.auto_wrap = final_byte == b'h';
+            return;
+        }
+        if params == b"!" && final_byte == b'p' {
+            self.soft_reset();
+            return;
+        }
+        // Other private sequences (e.g. `ESC [ ? 25 l`) don't move the cursor.
+        if params.first().is_some_and(|b| !b.is_ascii_digit() && *b != b';') {
+            return;
+        }
+
+        let params: Vec<u16> = params.split(|&b| b == b';')
+            .map(|param| match str::from_utf8(param).ok().and_then(|p| p.parse::<u32>().ok()) {
+                Some(n) => n.min(u32::from(u16::MAX)) as u16,
+                None => 0,
+            })
+            .collect();
+        let param = |i: usize| params.get(i).cloned().unwrap_or(0).max(1);
+        let column = self.position.0;
+
+        match final_byte {
+            b'A' => self.move_rows(true, param(0)),
+            b'B' => self.move_rows(false, param(0)),
+            b'C' => self.goto_column(column.saturating_add(param(0))),
+            b'D' => self.goto_column(column.saturating_sub(param(0))),
+            b'E' => {
+                self.goto_column(1);
+                self.move_rows(false, param(0));
+            }
+            b'F' => {
+                self.goto_column(1);
+                self.move_rows(true, param(0));
+            }
+            b'G' | b'`' => self.goto_column(param(0)),
+            // Inserting and deleting lines (see `edit::InsertLines`).
+            b'L' | b'M' => self.goto_column(1),
+            b'I' => self.tab(param(0)),
+            b'Z' => self.back_tab(param(0)),
+            b'g' => match params.first().cloned().unwrap_or(0) {
+                0 => self.set_tab_stop(false),
+                3 => {
+                    for stop in &mut self.tab_stops {
+                        *stop = false;
+                    }
+                }
+                _ => {}
+            },
+            b'd' => self.goto_line(param(0)),
+            b'H' | b'f' => {
+                self.goto_column(param(1));
+                self.goto_line(param(0));
+            }
+            b'r' => {
+                let height = self.size.1;
+                let top = param(0);
+                let bottom = match params.get(1) {
+                    Some(&bottom) if bottom > 0 => bottom.min(height),
+                    _ => height,
+                };
+                if top < bottom {
+                    self.margins = (top, bottom);
+                    self.home();
+                }
+            }
+            b's' => self.saved = self.position,
+            b'u' => {
+                let saved = self.saved;
+                self.set_position(saved);
+            }
+            _ => {}
+        }
+    }
+
+    /// Reset the modes like a soft reset (see `clear::SoftReset`) does, leaving the cursor put.
+    fn soft_reset(&mut self) {
+        self.margins = (1, self.size.1);
+        self.origin = false;
+        self.saved = (1, 1);
+    }
+
+    /// Set or clear the tab stop at the column of the cursor.
+    fn set_tab_stop(&mut self, stop: bool) {
+        let column = self.position.0 as usize - 1;
+        self.tab_stops[column] = stop;
+    }
+
+    /// Move the cursor forward to the `n`th next tab stop, or the last column.
+    fn tab(&mut self, n: u16) {
+        let column = self.position.0 as usize;
+        let next = self.tab_stops[column.min(self.tab_stops.len())..]
+            .iter()
+            .enumerate()
+            .filter(|&(_, &stop)| stop)
+            .nth(n as usize - 1)
+            .map_or(self.size.0, |(i, _)| (column + i + 1) as u16);
+        self.goto_column(next);
+    }
+
+    /// Move the cursor back to the `n`th previous tab stop, or the first column.
+    fn back_tab(&mut self, n: u16) {
+        let column = self.position.0 as usize - 1;
+        let prev = self.tab_stops[..column]
+            .iter()
+            .enumerate()
+            .rev()
+            .filter(|&(_, &stop)| stop)
+            .nth(n as usize - 1)
+            .map_or(1, |(i, _)| (i + 1) as u16);
+        self.goto_column(prev);
+    }
+
+    /// Move the cursor to the given column of its row, as far as possible.
+    fn goto_column(&mut self, column: u16) {
+        self.position.0 = column.clamp(1, self.size.0);
+        self.wrap_pending = false;
+    }
+
+    /// Move the cursor to the given row, as far as possible.
+    fn goto_row(&mut self, row: u16) {
+        self.position.1 = row.clamp(1, self.size.1);
+        self.wrap_pending = false;
+    }
+
+    /// Move the cursor to the given row, relative to the scrolling region in origin mode.
+    fn goto_line(&mut self, row: u16) {
+        if self.origin {
+            let (top, bottom) = self.margins;
+            self.goto_row(top.saturating_add(row - 1).min(bottom));
+        } else {
+            self.goto_row(row);
+        }
+    }
+
+    /// Move the cursor to the first column of the first line.
+    fn home(&mut self) {
+        self.goto_column(1);
+        self.goto_line(1);
+    }
+
+    /// Move the cursor `rows` up or down, stopping at the margins if it starts within them.
+    fn move_rows(&mut self, up: bool, rows: u16) {
+        let (top, bottom) = self.margins;
+        let row = self.position.1;
+        let row = if up {
+            row.saturating_sub(rows).max(if row >= top { top } else { 1 })
+        } else {
+            row.saturating_add(rows).min(if row <= bottom { bottom } else { self.size.1 })
+        };
+        self.goto_row(row);
+    }
+
+    /// Move the cursor down a row, scrolling at the bottom.
+    fn line_feed(&mut self) {
+        self.move_rows(false, 1);
+    }
+}
+
+impl<W: Write> Write for TrackedWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.output.write(buf)?;
+        for &byte in &buf[..n] {
+            self.track(byte);
+        }
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.output.flush()
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_goto_relative() {
+        assert_eq!(GotoRelative(0, 0).to_string(), "");
+        assert_eq!(GotoRelative(3, 0).to_string(), "\x1B[3C");
+        assert_eq!(GotoRelative(0, -1).to_string(), "\x1B[1A");
+        assert_eq!(GotoRelative(-32768, 32767).to_string(), "\x1B[32768D\x1B[32767B");
+    }
+
+    #[test]
+    fn test_save_restore() {
+        assert_eq!(format!("{}x{}", Save, Restore), "\x1B7x\x1B8");
+        assert_eq!(format!("{}x{}", ScoSave, ScoRestore), "\x1B[sx\x1B[u");
+    }
+
+    #[test]
+    fn test_hide_cursor() {
+        let mut out = Vec::new();
+        {
+            let mut term = HideCursor::from(&mut out);
+            term.write_all(b"x").unwrap();
+        }
+        assert_eq!(out, b"\x1B[?25lx\x1B[?25h");
+    }
+
+    #[test]
+    fn test_tracked_writer() {
+        let mut out = TrackedWriter::new(Vec::new(), (1, 1), (10, 5));
+        write!(out, "abc{}", Left(2)).unwrap();
+        assert_eq!(out.position(), (2, 1));
+        // An escape sequence ends before a character which can't be part of it.
+        write!(out, "\x1B\u{e9}").unwrap();
+        assert_eq!(out.position(), (3, 1));
+        write!(out, "\x1B[31m\u{e9}\x1B]0;title\x07\t").unwrap();
+        assert_eq!(out.position(), (9, 1));
+
+        // The cursor stays in the last column until the next character wraps.
+        write!(out, "xy").unwrap();
+        assert_eq!(out.position(), (10, 1));
+        write!(out, "z\x08").unwrap();
+        assert_eq!(out.position(), (1, 2));
+
+        write!(out, "{}{}{}", Save, Goto(4, 9), Up(1)).unwrap();
+        assert_eq!(out.position(), (4, 4));
+        write!(out, "{}\n\n", Restore).unwrap();
+        assert_eq!(out.position(), (1, 4));
+        write!(out, "\n\n\r{}", Hide).unwrap();
+        assert_eq!(out.position(), (1, 5));
+
+        out.set_size((5, 3));
+        assert_eq!(out.position(), (1, 3));
+        assert!(out.into_inner().starts_with(b"abc\x1B[2D\x1B\xC3\xA9\x1B[31m"));
+    }
+
+    #[test]
+    fn test_tracked_writer_origin_mode() {
+        let mut out = TrackedWriter::new(Vec::new(), (4, 4), (10, 10));
+        write!(out, "{}", ::scroll::Region(3, 6)).unwrap();
+        assert_eq!(out.position(), (1, 1));
+        write!(out, "{}{}", EnableOriginMode, Goto(2, 2)).unwrap();
+        assert_eq!(out.position(), (2, 4));
+        write!(out, "{}\n\n\n", Goto(1, 10)).unwrap();
+        assert_eq!(out.position(), (1, 6));
+        write!(out, "{}", Up(10)).unwrap();
+        assert_eq!(out.position(), (1, 3));
+        // Restoring the cursor keeps the region.
+        write!(out, "{}{}{}{}", Goto(1, 3), Save, Up(1), Restore).unwrap();
+        assert_eq!(out.position(), (1, 5));
+        out.write_all(b"\n\n").unwrap();
+        assert_eq!(out.position(), (1, 6));
+        write!(out, "{}{}", DisableOriginMode, Goto(5, 8)).unwrap();
+        assert_eq!(out.position(), (5, 8));
+        write!(out, "{}{}", ::scroll::ResetRegion, Goto(1, 10)).unwrap();
+        out.write_all(b"\n").unwrap();
+        assert_eq!(out.position(), (1, 10));
+        write!(out, "{}{}{}", Goto(5, 5), ::edit::EraseChars(2), ::edit::DeleteLines(1)).unwrap();
+        assert_eq!(out.position(), (1, 5));
+    }
+
+    #[test]
+    fn test_auto_wrap() {
+        let mut out = Vec::new();
+        {
+            let mut term = NoAutoWrap::from(&mut out);
+            term.write_all(b"x").unwrap();
+        }
+        assert_eq!(out, b"\x1B[?7lx\x1B[?7h");
+
+        let mut out = TrackedWriter::new(Vec::new(), (1, 3), (4, 3));
+        write!(out, "{}abcdef", DisableAutoWrap).unwrap();
+        assert_eq!(out.position(), (4, 3));
+        write!(out, "{}{}ab", EnableAutoWrap, Goto(3, 1)).unwrap();
+        write!(out, "c").unwrap();
+        assert_eq!(out.position(), (2, 2));
+    }
+
+    #[test]
+    fn test_tab_stops() {
+        assert_eq!(format!("{}{}{}", SetTabStop, ClearTabStop, ClearAllTabStops),
+                   "\x1BH\x1B[0g\x1B[3g");
+        assert_eq!(format!("{}{}", NextTabStop(2), PrevTabStop(1)), "\x1B[2I\x1B[1Z");
+
+        let mut out = TrackedWriter::new(Vec::new(), (1, 1), (20, 5));
+        write!(out, "\t\t\t").unwrap();
+        assert_eq!(out.position(), (20, 1));
+        write!(out, "{}{}", PrevTabStop(1), ClearTabStop).unwrap();
+        assert_eq!(out.position(), (17, 1));
+        write!(out, "{}{}{}", Goto(4, 1), SetTabStop, Goto(1, 1)).unwrap();
+        write!(out, "\t").unwrap();
+        assert_eq!(out.position(), (4, 1));
+        write!(out, "{}", NextTabStop(2)).unwrap();
+        assert_eq!(out.position(), (20, 1));
+        write!(out, "{}", PrevTabStop(2)).unwrap();
+        assert_eq!(out.position(), (4, 1));
+
+        write!(out, "{}\t", ClearAllTabStops).unwrap();
+        assert_eq!(out.position(), (20, 1));
+        out.set_size((30, 5));
+        write!(out, "\r\t").unwrap();
+        assert_eq!(out.position(), (25, 1));
+        write!(out, "{}\r\t", ::clear::HardReset).unwrap();
+        assert_eq!(out.position(), (9, 1));
+    }
+
+    #[test]
+    fn test_tracked_writer_reset() {
+        let mut out = TrackedWriter::new(Vec::new(), (1, 1), (10, 10));
+        write!(out, "{}{}{}", ::scroll::Region(3, 6), EnableOriginMode, Goto(2, 2)).unwrap();
+        write!(out, "{}{}", ::clear::SoftReset, Goto(2, 2)).unwrap();
+        assert_eq!(out.position(), (2, 2));
+        write!(out, "{}{}", DisableAutoWrap, ::clear::HardReset).unwrap();
+        assert_eq!(out.position(), (1, 1));
+        write!(out, "{}abc", Goto(9, 1)).unwrap();
+        assert_eq!(out.position(), (2, 2));
+    }
+
+    #[test]
+    fn test_is_hidden() {
+        let mut output = Vec::new();
+        assert!(is_hidden(&mut &b"\x1B[?25;2$y"[..], &mut output).unwrap());
+        assert!(!is_hidden(&mut &b"\x1B[?25;1$y"[..], &mut output).unwrap());
+        assert!(is_hidden(&mut &b"\x1B[?25;0$y"[..], &mut output).is_err());
+    }
+
+    #[test]
+    fn test_position() {
+        let mut output = Vec::new();
+        let mut input = &b"a\x1B[12;40R"[..];
+        assert_eq!(position(&mut input, &mut output).unwrap(), (40, 12));
+        assert_eq!(output, b"\x1B[6n");
+
+        // A modified F3 key to the parser.
+        let mut input = &b"\x1B[1;5R"[..];
+        assert_eq!(position(&mut input, &mut output).unwrap(), (5, 1));
+
+        let mut input = &b""[..];
+        let err = position(&mut input, &mut output).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::TimedOut);
+    }
+}
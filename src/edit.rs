@@ -0,0 +1,86 @@
+//! Inserting and deleting lines and characters, shifting the rest of the screen.
+//!
+//! These allow editing parts of the screen without writing everything after them again, e.g.
+//! removing an item of a list or a character of a line being edited.
+//!
+//! # Example
+//!
+//! ```rust,no_run
+//! use std::io::{Write, stdout};
+//! use termion::cursor::Goto;
+//! use termion::edit::{DeleteChars, DeleteLines};
+//!
+//! let mut stdout = stdout();
+//! // Remove the third row, moving the rows below it up, and the first character of the fourth.
+//! write!(stdout, "{}{}{}{}", Goto(1, 3), DeleteLines(1), Goto(1, 3), DeleteChars(1)).unwrap();
+//! ```
+
+use std::fmt;
+
+/// Insert blank lines at the row of the cursor (IL), moving it and the rows below down within the
+/// scrolling region. The rows moved past its bottom are lost. This moves the cursor to the first
+/// column.
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub struct InsertLines(pub u16);
+
+impl fmt::Display for InsertLines {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, csi!("{}L"), self.0)
+    }
+}
+
+/// Delete lines from the row of the cursor on (DL), moving the rows below up within the scrolling
+/// region and adding blank rows at its bottom. This moves the cursor to the first column.
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub struct DeleteLines(pub u16);
+
+impl fmt::Display for DeleteLines {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, csi!("{}M"), self.0)
+    }
+}
+
+/// Insert blank characters at the cursor (ICH), moving it and the rest of the line right. The
+/// characters moved past the last column are lost.
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub struct InsertChars(pub u16);
+
+impl fmt::Display for InsertChars {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, csi!("{}@"), self.0)
+    }
+}
+
+/// Delete characters from the cursor on (DCH), moving the rest of the line left and adding blanks
+/// at its end.
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub struct DeleteChars(pub u16);
+
+impl fmt::Display for DeleteChars {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, csi!("{}P"), self.0)
+    }
+}
+
+/// Erase characters from the cursor on (ECH), replacing them with blanks without moving the rest
+/// of the line.
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub struct EraseChars(pub u16);
+
+impl fmt::Display for EraseChars {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, csi!("{}X"), self.0)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_sequences() {
+        assert_eq!(format!("{}{}", InsertLines(2), DeleteLines(1)), "\x1B[2L\x1B[1M");
+        assert_eq!(format!("{}{}{}", InsertChars(3), DeleteChars(4), EraseChars(10)),
+                   "\x1B[3@\x1B[4P\x1B[10X");
+    }
+}
@@ -1,20 +1,99 @@
 //! Mouse and key events.
 
-use std::io::{Error, ErrorKind};
-use std::ascii::AsciiExt;
+use std::error;
+use std::fmt;
+use std::io::{self, Error, ErrorKind, Write};
+use std::slice;
 use std::str;
+use std::time::{Duration, Instant};
+
+use color::Rgb;
 
 /// An event reported by the terminal.
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum Event {
     /// A key press.
     Key(Key),
+    /// A key is being held down and auto-repeats.
+    ///
+    /// This is only reported if the kitty keyboard protocol is enabled with event types.
+    KeyRepeat(Key),
+    /// A key was released.
+    ///
+    /// This is only reported if the kitty keyboard protocol is enabled with event types.
+    KeyRelease(Key),
     /// A mouse button press, release or wheel use at specific coordinates.
     Mouse(MouseEvent),
+    /// A mouse event while holding modifiers, such as Ctrl-click or Shift-drag.
+    ///
+    /// Mouse events without any modifiers are reported as `Event::Mouse`. Note that many terminals
+    /// reserve some modified clicks for themselves (e.g. Shift-click for selection).
+    ModifiedMouse(Modifiers, MouseEvent),
+    /// Text pasted while bracketed paste mode was enabled.
+    ///
+    /// The text is passed on unchanged, so it may contain control characters and escape
+    /// sequences. See `sanitize_paste` for making it safe to display or insert.
+    Paste(String),
+    /// The terminal gained focus (requires focus reporting to be enabled).
+    FocusGained,
+    /// The terminal lost focus (requires focus reporting to be enabled).
+    FocusLost,
+    /// A response of the terminal to a query.
+    Report(Report),
+    /// The terminal was resized to the given number of columns and rows.
+    ///
+    /// This is reported once enabled with `report_signal(Signal::Resize)`, and when xterm responds
+    /// to `ESC [ 18 t`. On Windows, `console_events` reports it whenever the console is resized.
+    Resize(u16, u16),
+    /// The user asked to suspend the process, once enabled with `report_signal(Signal::Suspend)`.
+    Suspend,
+    /// The process was continued after it was stopped, once enabled with
+    /// `report_signal(Signal::Resume)`.
+    Resume,
+    /// The user asked to interrupt the process, once enabled with
+    /// `report_signal(Signal::Interrupt)`.
+    Interrupt,
     /// An event that cannot currently be evaluated.
+    ///
+    /// This holds the raw bytes of the event, so applications can parse it themselves.
     Unsupported(Vec<u8>),
 }
 
+/// A response of the terminal to a query.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Report {
+    /// The cursor position (CPR), in response to `ESC [ 6 n` or `ESC [ ? 6 n`.
+    ///
+    /// The coordinates are one-based, and given as column and row, like `cursor::Goto`.
+    ///
+    /// Note that xterm reports some modified F3 keys with the same sequence as a cursor position
+    /// in the first row (e.g. `ESC [ 1 ; 5 R` for Ctrl-F3), in which case a key is reported. The
+    /// extended form `ESC [ ? 6 n` avoids this, if it is supported.
+    CursorPosition(u16, u16),
+    /// The primary device attributes (DA1), in response to `ESC [ c`.
+    ///
+    /// These are the terminal's conformance level followed by the features it supports.
+    PrimaryAttributes(Vec<u16>),
+    /// The secondary device attributes (DA2), in response to `ESC [ > c`.
+    ///
+    /// These are the terminal type, its firmware version and usually a zero.
+    SecondaryAttributes(Vec<u16>),
+    /// The operating status of the terminal (DSR), in response to `ESC [ 5 n`.
+    ///
+    /// This is `true` if the terminal is ok, and `false` if it reports a malfunction.
+    Status(bool),
+    /// The setting of a private mode (DECRPM), in response to `ESC [ ? mode $ p`.
+    ///
+    /// This holds the mode and whether it is set, or `None` if the terminal doesn't recognize it.
+    PrivateMode(u16, Option<bool>),
+    /// The color of a palette entry, in response to `ESC ] 4 ; index ; ? BEL`.
+    ///
+    /// This holds the index and the color the terminal shows for it.
+    PaletteColor(u8, Rgb),
+    /// The default background color, in response to `ESC ] 11 ; ? BEL`.
+    Background(Rgb),
+}
+
 /// A mouse related event.
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
 pub enum MouseEvent {
@@ -30,6 +109,26 @@ pub enum MouseEvent {
     ///
     /// The coordinates are one-based.
     Hold(u16, u16),
+    /// The mouse was moved to the given coordinates without any button held.
+    ///
+    /// This is only reported if any-motion tracking is enabled.
+    ///
+    /// The coordinates are one-based.
+    Move(u16, u16),
+    /// A mouse button was pressed twice in a row at the given coordinates.
+    ///
+    /// This is never reported by the terminal, but synthesized by `ClickDetector` after the
+    /// second `Press`.
+    ///
+    /// The coordinates are one-based.
+    DoubleClick(MouseButton, u16, u16),
+    /// A mouse button was pressed three times in a row at the given coordinates.
+    ///
+    /// This is never reported by the terminal, but synthesized by `ClickDetector` after the third
+    /// `Press`.
+    ///
+    /// The coordinates are one-based.
+    TripleClick(MouseButton, u16, u16),
 }
 
 /// A mouse button.
@@ -49,6 +148,14 @@ pub enum MouseButton {
     ///
     /// This event is typically only used with Mouse::Press.
     WheelDown,
+    /// Mouse wheel is going left (horizontal scrolling).
+    ///
+    /// This event is typically only used with Mouse::Press.
+    WheelLeft,
+    /// Mouse wheel is going right (horizontal scrolling).
+    ///
+    /// This event is typically only used with Mouse::Press.
+    WheelRight,
 }
 
 /// A key.
@@ -78,7 +185,7 @@ pub enum Key {
     Insert,
     /// Function keys.
     ///
-    /// Only function keys 1 through 12 are supported.
+    /// Only function keys 1 through 24 are supported.
     F(u8),
     /// Normal character.
     Char(char),
@@ -92,14 +199,971 @@ pub enum Key {
     Null,
     /// Esc key.
     Esc,
+    /// Enter key on the keypad.
+    ///
+    /// This is only distinguished from the main Enter key in application keypad mode.
+    KeypadEnter,
+    /// Digit or operator key on the keypad.
+    ///
+    /// This is only distinguished from the main keys in application keypad mode.
+    Keypad(char),
+    /// A key pressed while holding modifiers, such as Ctrl-Left or Shift-F5.
+    ///
+    /// Keys pressed without any modifiers are reported as the corresponding plain variant (e.g.
+    /// `Key::Left`). Characters with a single modifier are reported as `Key::Ctrl`, `Key::Alt` or
+    /// a shifted `Key::Char`, as legacy terminals would.
+    Modified(Modifiers, ModifiedKey),
 
     #[doc(hidden)]
     __IsNotComplete,
 }
 
+/// A key which can be reported together with modifiers, see `Key::Modified`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum ModifiedKey {
+    /// Left arrow.
+    Left,
+    /// Right arrow.
+    Right,
+    /// Up arrow.
+    Up,
+    /// Down arrow.
+    Down,
+    /// Home key.
+    Home,
+    /// End key.
+    End,
+    /// Page Up key.
+    PageUp,
+    /// Page Down key.
+    PageDown,
+    /// Delete key.
+    Delete,
+    /// Insert key.
+    Insert,
+    /// Function keys.
+    ///
+    /// Only function keys 1 through 24 are supported.
+    F(u8),
+    /// Normal character.
+    Char(char),
+    /// Backspace.
+    Backspace,
+    /// Esc key.
+    Esc,
+    /// Enter key on the keypad.
+    KeypadEnter,
+    /// Digit or operator key on the keypad.
+    Keypad(char),
+}
+
+impl From<ModifiedKey> for Key {
+    fn from(key: ModifiedKey) -> Key {
+        match key {
+            ModifiedKey::Left => Key::Left,
+            ModifiedKey::Right => Key::Right,
+            ModifiedKey::Up => Key::Up,
+            ModifiedKey::Down => Key::Down,
+            ModifiedKey::Home => Key::Home,
+            ModifiedKey::End => Key::End,
+            ModifiedKey::PageUp => Key::PageUp,
+            ModifiedKey::PageDown => Key::PageDown,
+            ModifiedKey::Delete => Key::Delete,
+            ModifiedKey::Insert => Key::Insert,
+            ModifiedKey::F(n) => Key::F(n),
+            ModifiedKey::Char(c) => Key::Char(c),
+            ModifiedKey::Backspace => Key::Backspace,
+            ModifiedKey::Esc => Key::Esc,
+            ModifiedKey::KeypadEnter => Key::KeypadEnter,
+            ModifiedKey::Keypad(c) => Key::Keypad(c),
+        }
+    }
+}
+
+/// The modifier keys held down while another key was pressed, or the mouse was used.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Default)]
+pub struct Modifiers {
+    /// Shift was held.
+    pub shift: bool,
+    /// Alt was held.
+    pub alt: bool,
+    /// Ctrl was held.
+    pub ctrl: bool,
+}
+
+impl Modifiers {
+    /// Decode the modifier parameter of an xterm sequence, which is one plus a bitmask of the
+    /// held modifiers.
+    fn from_param(param: u32) -> Modifiers {
+        let bits = param.saturating_sub(1);
+        Modifiers {
+            shift: bits & 1 != 0,
+            alt: bits & 2 != 0,
+            ctrl: bits & 4 != 0,
+        }
+    }
+
+    /// Encode the modifiers as the parameter of an xterm sequence.
+    fn to_param(self) -> u32 {
+        1 + self.shift as u32 + 2 * self.alt as u32 + 4 * self.ctrl as u32
+    }
+}
+
+impl Key {
+    /// Write the sequence a terminal sends when this key is pressed.
+    ///
+    /// Keys are encoded the way xterm reports them, falling back to the kitty keyboard protocol
+    /// for keys that have no legacy encoding (such as Ctrl-Shift-A). An error of kind
+    /// `InvalidInput` is returned for keys that cannot be encoded at all.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use termion::event::Key;
+    ///
+    /// let mut buf = Vec::new();
+    /// Key::Ctrl('c').write_to(&mut buf).unwrap();
+    /// Key::F(5).write_to(&mut buf).unwrap();
+    /// assert_eq!(buf, b"\x03\x1B[15~");
+    /// ```
+    pub fn write_to<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        write_key(w, *self, None)
+    }
+}
+
+impl Event {
+    /// Write the sequence a terminal sends to report this event.
+    ///
+    /// Keys are encoded as by `Key::write_to`, with repeats and releases using the kitty keyboard
+    /// protocol. Mouse events use the SGR encoding. Double and triple clicks are synthesized by
+    /// `ClickDetector`, so nothing is written for them, while unsupported events are written as
+    /// their raw bytes. Signals have no sequence, so they give an error of kind `InvalidInput`.
+    pub fn write_to<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        match *self {
+            Event::Key(key) => write_key(w, key, None),
+            Event::KeyRepeat(key) => write_key(w, key, Some(2)),
+            Event::KeyRelease(key) => write_key(w, key, Some(3)),
+            Event::Mouse(mouse) => write_mouse(w, Modifiers::default(), mouse),
+            Event::ModifiedMouse(modifiers, mouse) => write_mouse(w, modifiers, mouse),
+            Event::Paste(ref text) => write!(w, "\x1B[200~{}\x1B[201~", text),
+            Event::FocusGained => w.write_all(b"\x1B[I"),
+            Event::FocusLost => w.write_all(b"\x1B[O"),
+            Event::Report(ref report) => write_report(w, report),
+            Event::Resize(width, height) => write!(w, "\x1B[8;{};{}t", height, width),
+            Event::Suspend | Event::Resume | Event::Interrupt => {
+                Err(Error::new(ErrorKind::InvalidInput, "Signals have no escape sequence"))
+            }
+            Event::Unsupported(ref bytes) => w.write_all(bytes),
+        }
+    }
+
+    /// Encode this event as the sequence a terminal sends to report it, see `write_to`.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use termion::event::{self, Event, Key};
+    ///
+    /// let event = Event::Key(Key::Alt('x'));
+    /// let bytes = event.to_bytes().unwrap();
+    /// assert_eq!(bytes, b"\x1Bx");
+    /// assert_eq!(event::parse(&bytes), (Some(event), 2));
+    /// ```
+    pub fn to_bytes(&self) -> io::Result<Vec<u8>> {
+        let mut buf = Vec::new();
+        self.write_to(&mut buf)?;
+        Ok(buf)
+    }
+}
+
+impl str::FromStr for Key {
+    type Err = ParseKeyError;
+
+    /// Parse a key description such as `"ctrl-x"`, `"alt-enter"`, `"shift-f5"` or `"q"`.
+    ///
+    /// A description is a key name, optionally preceded by modifiers (`ctrl`, `alt` or `shift`),
+    /// each followed by `-` or `+`. Names are case-insensitive, except for single characters. The
+    /// named keys are `backspace`, `left`, `right`, `up`, `down`, `home`, `end`, `pageup`,
+    /// `pagedown`, `delete`, `insert`, `f1` through `f24`, `enter`, `tab`, `space`, `esc` and
+    /// `null`.
+    ///
+    /// The key is given in the form the parser reports it, e.g. `"shift-a"` is `Key::Char('A')`
+    /// and `"ctrl-alt-x"` is a `Key::Modified`.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use termion::event::Key;
+    ///
+    /// assert_eq!("ctrl-x".parse(), Ok(Key::Ctrl('x')));
+    /// assert_eq!("alt-enter".parse(), Ok(Key::Alt('\n')));
+    /// assert_eq!("f5".parse(), Ok(Key::F(5)));
+    /// assert!("hyper-x".parse::<Key>().is_err());
+    /// ```
+    fn from_str(s: &str) -> Result<Key, ParseKeyError> {
+        let error = || ParseKeyError(s.to_string());
+
+        let mut modifiers = Modifiers::default();
+        let mut rest = s;
+        // A separator in the first place is the key itself, as in `"-"` or `"alt--"`.
+        loop {
+            let split = rest.char_indices().skip(1).find(|&(_, c)| c == '-' || c == '+');
+            let (modifier, name) = match split {
+                Some((i, _)) => (&rest[..i], &rest[i + 1..]),
+                None => break,
+            };
+            match &*modifier.to_lowercase() {
+                "ctrl" | "control" | "c" => modifiers.ctrl = true,
+                "alt" | "meta" | "m" => modifiers.alt = true,
+                "shift" | "s" => modifiers.shift = true,
+                _ => return Err(error()),
+            }
+            rest = name;
+        }
+
+        let mut chars = rest.chars();
+        let key = match (chars.next(), chars.next()) {
+            (Some(c), None) if modifiers.ctrl => ModifiedKey::Char(c.to_ascii_lowercase()),
+            (Some(c), None) => ModifiedKey::Char(c),
+            _ => {
+                match &*rest.to_lowercase() {
+                    "backspace" => ModifiedKey::Backspace,
+                    "left" => ModifiedKey::Left,
+                    "right" => ModifiedKey::Right,
+                    "up" => ModifiedKey::Up,
+                    "down" => ModifiedKey::Down,
+                    "home" => ModifiedKey::Home,
+                    "end" => ModifiedKey::End,
+                    "pageup" | "pgup" => ModifiedKey::PageUp,
+                    "pagedown" | "pgdn" => ModifiedKey::PageDown,
+                    "delete" | "del" => ModifiedKey::Delete,
+                    "insert" | "ins" => ModifiedKey::Insert,
+                    "enter" | "return" => ModifiedKey::Char('\n'),
+                    "tab" => ModifiedKey::Char('\t'),
+                    "space" => ModifiedKey::Char(' '),
+                    "esc" | "escape" => ModifiedKey::Esc,
+                    "null" if modifiers == Modifiers::default() => return Ok(Key::Null),
+                    name if name.starts_with('f') => {
+                        match name[1..].parse() {
+                            Ok(n @ 1..=24) => ModifiedKey::F(n),
+                            _ => return Err(error()),
+                        }
+                    }
+                    _ => return Err(error()),
+                }
+            }
+        };
+
+        Ok(modified_key(key, Some(modifiers.to_param())))
+    }
+}
+
+/// An error returned when parsing a `Key` from a string fails.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseKeyError(String);
+
+impl fmt::Display for ParseKeyError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "invalid key description: {:?}", self.0)
+    }
+}
+
+impl error::Error for ParseKeyError {
+    fn description(&self) -> &str {
+        "invalid key description"
+    }
+}
+
+/// Write the sequence for `key`, using the kitty event type `kind` if given.
+fn write_key<W: Write>(w: &mut W, key: Key, kind: Option<u32>) -> io::Result<()> {
+    let error = || Error::new(ErrorKind::InvalidInput, "Key cannot be encoded");
+    let alt = Modifiers { alt: true, ..Modifiers::default() };
+    let ctrl = Modifiers { ctrl: true, ..Modifiers::default() };
+
+    let (modifiers, key) = match key {
+        Key::Backspace => (Modifiers::default(), ModifiedKey::Backspace),
+        Key::Left => (Modifiers::default(), ModifiedKey::Left),
+        Key::Right => (Modifiers::default(), ModifiedKey::Right),
+        Key::Up => (Modifiers::default(), ModifiedKey::Up),
+        Key::Down => (Modifiers::default(), ModifiedKey::Down),
+        Key::Home => (Modifiers::default(), ModifiedKey::Home),
+        Key::End => (Modifiers::default(), ModifiedKey::End),
+        Key::PageUp => (Modifiers::default(), ModifiedKey::PageUp),
+        Key::PageDown => (Modifiers::default(), ModifiedKey::PageDown),
+        Key::Delete => (Modifiers::default(), ModifiedKey::Delete),
+        Key::Insert => (Modifiers::default(), ModifiedKey::Insert),
+        Key::F(n) => (Modifiers::default(), ModifiedKey::F(n)),
+        Key::Char(c) => (Modifiers::default(), ModifiedKey::Char(c)),
+        Key::Alt(c) => (alt, ModifiedKey::Char(c)),
+        Key::Ctrl(c) => (ctrl, ModifiedKey::Char(c)),
+        Key::Null if kind.is_none() => return w.write_all(b"\0"),
+        Key::Esc => (Modifiers::default(), ModifiedKey::Esc),
+        Key::KeypadEnter => (Modifiers::default(), ModifiedKey::KeypadEnter),
+        Key::Keypad(c) => (Modifiers::default(), ModifiedKey::Keypad(c)),
+        Key::Modified(modifiers, key) => (modifiers, key),
+        Key::Null | Key::__IsNotComplete => return Err(error()),
+    };
+
+    // Legacy encodings, which can neither carry the event type nor most modifiers.
+    if kind.is_none() {
+        let mut utf8 = [0; 4];
+        match (modifiers, key) {
+            (m, ModifiedKey::Char('\n')) if m == alt => return w.write_all(b"\x1B\r"),
+            (m, ModifiedKey::Char(c)) if m == alt && c != '[' && c != 'O' => {
+                w.write_all(b"\x1B")?;
+                return w.write_all(c.encode_utf8(&mut utf8).as_bytes());
+            }
+            (m, ModifiedKey::Char(c @ 'a'..='z')) if m == ctrl => {
+                return w.write_all(&[c as u8 - b'a' + 1]);
+            }
+            (m, ModifiedKey::Char(c @ '4'..='7')) if m == ctrl => {
+                return w.write_all(&[c as u8 - b'4' + 0x1C]);
+            }
+            (m, key) if m == Modifiers::default() => {
+                match key {
+                    ModifiedKey::F(n @ 1..=4) => return w.write_all(&[0x1B, b'O', b'O' + n]),
+                    ModifiedKey::KeypadEnter => return w.write_all(b"\x1BOM"),
+                    ModifiedKey::Keypad(c) => {
+                        let code = match c {
+                            '0'..='9' => c as u8 - b'0' + b'p',
+                            '*' => b'j',
+                            '+' => b'k',
+                            ',' => b'l',
+                            '-' => b'm',
+                            '.' => b'n',
+                            '/' => b'o',
+                            '=' => b'X',
+                            _ => return Err(error()),
+                        };
+                        return w.write_all(&[0x1B, b'O', code]);
+                    }
+                    ModifiedKey::Char('\n') => return w.write_all(b"\r"),
+                    ModifiedKey::Char(c) => return w.write_all(c.encode_utf8(&mut utf8).as_bytes()),
+                    ModifiedKey::Backspace => return w.write_all(b"\x7F"),
+                    ModifiedKey::Esc => return w.write_all(b"\x1B"),
+                    _ => (),
+                }
+            }
+            _ => (),
+        }
+    }
+
+    let params = match kind {
+        Some(kind) => format!("{}:{}", modifiers.to_param(), kind),
+        None if modifiers != Modifiers::default() => modifiers.to_param().to_string(),
+        None => String::new(),
+    };
+
+    if let Some(letter) = csi_letter(key) {
+        return if params.is_empty() {
+            write!(w, "\x1B[{}", letter)
+        } else {
+            write!(w, "\x1B[1;{}{}", params, letter)
+        };
+    }
+
+    let (code, last) = match (tilde_code(key), kitty_code(key)) {
+        (Some(code), _) => (code, '~'),
+        (None, Some(code)) => (code, 'u'),
+        (None, None) => return Err(error()),
+    };
+    if params.is_empty() {
+        write!(w, "\x1B[{}{}", code, last)
+    } else {
+        write!(w, "\x1B[{};{}{}", code, params, last)
+    }
+}
+
+/// The final byte of the xterm sequence `ESC [ 1 ; modifiers <final>` for `key`, if any.
+fn csi_letter(key: ModifiedKey) -> Option<char> {
+    Some(match key {
+        ModifiedKey::Up => 'A',
+        ModifiedKey::Down => 'B',
+        ModifiedKey::Right => 'C',
+        ModifiedKey::Left => 'D',
+        ModifiedKey::Home => 'H',
+        ModifiedKey::End => 'F',
+        ModifiedKey::F(n @ 1..=4) => (b'O' + n) as char,
+        _ => return None,
+    })
+}
+
+/// The number of the xterm sequence `ESC [ <number> ; modifiers ~` for `key`, if any.
+fn tilde_code(key: ModifiedKey) -> Option<u32> {
+    Some(match key {
+        ModifiedKey::Insert => 2,
+        ModifiedKey::Delete => 3,
+        ModifiedKey::PageUp => 5,
+        ModifiedKey::PageDown => 6,
+        ModifiedKey::F(5) => 15,
+        ModifiedKey::F(n @ 6..=10) => n as u32 + 11,
+        ModifiedKey::F(n @ 11..=14) => n as u32 + 12,
+        ModifiedKey::F(n @ 15..=16) => n as u32 + 13,
+        ModifiedKey::F(n @ 17..=20) => n as u32 + 14,
+        _ => return None,
+    })
+}
+
+/// The key code of the kitty sequence `ESC [ <code> ; modifiers u` for `key`, if any.
+fn kitty_code(key: ModifiedKey) -> Option<u32> {
+    Some(match key {
+        ModifiedKey::Char('\n') => 13,
+        ModifiedKey::Char(c) => c as u32,
+        ModifiedKey::Backspace => 127,
+        ModifiedKey::Esc => 27,
+        ModifiedKey::F(n @ 13..=24) => n as u32 - 13 + 57376,
+        ModifiedKey::KeypadEnter => 57414,
+        ModifiedKey::Keypad(c @ '0'..='9') => c as u32 - '0' as u32 + 57399,
+        ModifiedKey::Keypad('.') => 57409,
+        ModifiedKey::Keypad('/') => 57410,
+        ModifiedKey::Keypad('*') => 57411,
+        ModifiedKey::Keypad('-') => 57412,
+        ModifiedKey::Keypad('+') => 57413,
+        ModifiedKey::Keypad('=') => 57415,
+        _ => return None,
+    })
+}
+
+/// Write the sequence a terminal responds with for `report`.
+fn write_report<W: Write>(w: &mut W, report: &Report) -> io::Result<()> {
+    let join = |nums: &[u16]| nums.iter().map(|n| n.to_string()).collect::<Vec<_>>().join(";");
+    match *report {
+        Report::CursorPosition(x, y) => write!(w, "\x1B[{};{}R", y, x),
+        Report::PrimaryAttributes(ref attrs) => write!(w, "\x1B[?{}c", join(attrs)),
+        Report::SecondaryAttributes(ref attrs) => write!(w, "\x1B[>{}c", join(attrs)),
+        Report::Status(ok) => w.write_all(if ok { b"\x1B[0n" } else { b"\x1B[3n" }),
+        Report::PrivateMode(mode, set) => {
+            let setting = match set {
+                Some(true) => 1,
+                Some(false) => 2,
+                None => 0,
+            };
+            write!(w, "\x1B[?{};{}$y", mode, setting)
+        }
+        Report::PaletteColor(index, rgb) => {
+            write!(w, "\x1B]4;{};{}\x1B\\", index, color_spec(rgb))
+        }
+        Report::Background(rgb) => write!(w, "\x1B]11;{}\x1B\\", color_spec(rgb)),
+    }
+}
+
+/// The color in the `rgb:RRRR/GGGG/BBBB` form terminals report colors in.
+fn color_spec(rgb: Rgb) -> String {
+    let scale = |v: u8| u16::from(v) * 257;
+    format!("rgb:{:04x}/{:04x}/{:04x}", scale(rgb.0), scale(rgb.1), scale(rgb.2))
+}
+
+/// Write the SGR mouse report of `mouse` while holding `modifiers`.
+fn write_mouse<W: Write>(w: &mut W, modifiers: Modifiers, mouse: MouseEvent) -> io::Result<()> {
+    let bits = 4 * modifiers.shift as u16 + 8 * modifiers.alt as u16 + 16 * modifiers.ctrl as u16;
+    let (cb, x, y, last) = match mouse {
+        MouseEvent::Press(button, x, y) => {
+            let cb = match button {
+                MouseButton::Left => 0,
+                MouseButton::Middle => 1,
+                MouseButton::Right => 2,
+                MouseButton::WheelUp => 64,
+                MouseButton::WheelDown => 65,
+                MouseButton::WheelLeft => 66,
+                MouseButton::WheelRight => 67,
+            };
+            (cb, x, y, 'M')
+        }
+        MouseEvent::Release(x, y) => (3, x, y, 'm'),
+        MouseEvent::Hold(x, y) => (32, x, y, 'M'),
+        MouseEvent::Move(x, y) => (35, x, y, 'M'),
+        MouseEvent::DoubleClick(..) | MouseEvent::TripleClick(..) => return Ok(()),
+    };
+    write!(w, "\x1B[<{};{};{}{}", cb + bits, x, y, last)
+}
+
+/// Combine `key` with the (optional) xterm modifier parameter `param`.
+fn modified_key(key: ModifiedKey, param: Option<u32>) -> Key {
+    let modifiers = param.map_or_else(Modifiers::default, Modifiers::from_param);
+    let Modifiers { shift, alt, ctrl } = modifiers;
+
+    match key {
+        _ if modifiers == Modifiers::default() => key.into(),
+        // Legacy terminals prefix any character with ESC for Alt, including Enter and Tab.
+        ModifiedKey::Char(c) if (shift, alt, ctrl) == (false, true, false) => Key::Alt(c),
+        ModifiedKey::Char(c) if !c.is_control() => {
+            match (shift, alt, ctrl) {
+                (true, false, false) => Key::Char(c.to_uppercase().next().unwrap_or(c)),
+                (false, false, true) => Key::Ctrl(c),
+                _ => Key::Modified(modifiers, key),
+            }
+        }
+        _ => Key::Modified(modifiers, key),
+    }
+}
+
+/// Wrap `key` in an event of the kind given by the (optional) kitty event type parameter.
+///
+/// Returns None for unknown event types.
+fn key_event(key: Key, kind: Option<u32>) -> Option<Event> {
+    Some(match kind {
+        None | Some(1) => Event::Key(key),
+        Some(2) => Event::KeyRepeat(key),
+        Some(3) => Event::KeyRelease(key),
+        _ => return None,
+    })
+}
+
+/// How `sanitize_paste` treats control characters.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Sanitize {
+    /// Remove control characters, along with the rest of any escape sequence they start.
+    Strip,
+    /// Replace control characters with their caret notation (e.g. `^[` for ESC), as `cat -v`
+    /// does.
+    Escape,
+}
+
+/// Make pasted text safe to insert into an application or to print to the terminal.
+///
+/// Pasted text may hold anything, including escape sequences which would act on the terminal or
+/// even close the bracketed paste early in applications that echo it. This removes or escapes
+/// all control characters except tabs and newlines. Line breaks are normalized to `\n`.
+///
+/// # Example
+///
+/// ```rust
+/// use termion::event::{self, Event, Sanitize};
+/// use termion::input::TermRead;
+///
+/// let paste = b"\x1B[200~ls\x1B[2K -l\r\x1B[201~";
+/// let mut events = paste.events().translate(|event| match event {
+///     Event::Paste(text) => Some(Event::Paste(event::sanitize_paste(&text, Sanitize::Strip))),
+///     event => Some(event),
+/// });
+/// assert_eq!(events.next().unwrap().unwrap(), Event::Paste("ls -l\n".to_string()));
+///
+/// assert_eq!(event::sanitize_paste("a\x1B[31mb\x07\r\n", Sanitize::Escape), "a^[[31mb^G\n");
+/// ```
+pub fn sanitize_paste(text: &str, sanitize: Sanitize) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\t' | '\n' => result.push(c),
+            '\r' => {
+                if chars.peek() == Some(&'\n') {
+                    chars.next();
+                }
+                result.push('\n');
+            }
+            '\x1B' if sanitize == Sanitize::Strip => {
+                match chars.next() {
+                    // CSI: parameters and intermediates up to the final byte.
+                    Some('[') => {
+                        chars.by_ref().find(|c| ('@'..='~').contains(c));
+                    }
+                    // OSC, DCS and others: a string up to BEL or ST (`ESC \`).
+                    Some(']') | Some('P') | Some('X') | Some('^') | Some('_') => {
+                        while let Some(c) = chars.next() {
+                            if c == '\x07' || c == '\x1B' && chars.next_if_eq(&'\\').is_some() {
+                                break;
+                            }
+                        }
+                    }
+                    _ => (),
+                }
+            }
+            c if c.is_control() => {
+                if sanitize == Sanitize::Escape {
+                    let c = c as u32;
+                    if c >= 0x80 {
+                        result.push_str("M-");
+                    }
+                    result.push('^');
+                    result.push(match c & 0x7F {
+                        0x7F => '?',
+                        c => (c as u8 + b'@') as char,
+                    });
+                }
+            }
+            c => result.push(c),
+        }
+    }
+
+    result
+}
+
+/// Parse the first event in `buf`.
+///
+/// Returns the event together with the number of bytes it spans. Sequences which cannot be parsed
+/// are returned as `Event::Unsupported`.
+///
+/// If `buf` is empty or only holds the beginning of an event (such as a partial escape sequence),
+/// `(None, 0)` is returned, and parsing should be retried once more bytes are available. Note that
+/// this includes a lone ESC byte, since it may be the start of an escape sequence; if no more
+/// input is pending, it can be taken to mean `Key::Esc`.
+///
+/// # Example
+///
+/// ```rust
+/// use termion::event::{self, Event, Key};
+///
+/// let buf = b"a\x1B[A\x1B[";
+/// assert_eq!(event::parse(buf), (Some(Event::Key(Key::Char('a'))), 1));
+/// assert_eq!(event::parse(&buf[1..]), (Some(Event::Key(Key::Up)), 3));
+/// assert_eq!(event::parse(&buf[4..]), (None, 0));
+/// ```
+pub fn parse(buf: &[u8]) -> (Option<Event>, usize) {
+    parse_with(buf, Profile::Xterm)
+}
+
+/// Parse the first event in `buf`, sent by a terminal of the given profile.
+///
+/// See `parse` for details.
+pub fn parse_with(buf: &[u8], profile: Profile) -> (Option<Event>, usize) {
+    // OSC responses are told apart from Alt-] by the number following.
+    if buf.starts_with(b"\x1B]") && buf.get(2).is_some_and(u8::is_ascii_digit) {
+        return parse_osc(buf);
+    }
+
+    let (&item, rest) = match buf.split_first() {
+        Some(split) => split,
+        None => return (None, 0),
+    };
+
+    let mut iter = SliceBytes {
+        iter: rest.iter(),
+        exhausted: false,
+    };
+    let result = parse_event_with(item, &mut iter, profile);
+    let len = buf.len() - iter.iter.as_slice().len();
+
+    match result {
+        Ok(event) => (Some(event), len),
+        Err(_) if iter.exhausted => (None, 0),
+        Err(_) => (Some(Event::Unsupported(buf[..len].to_vec())), len),
+    }
+}
+
+/// Parse an OSC sequence, `ESC ] ... BEL` or `ESC ] ... ESC \`, at the start of `buf`.
+fn parse_osc(buf: &[u8]) -> (Option<Event>, usize) {
+    let mut i = 2;
+    let (content, len) = loop {
+        match (buf.get(i), buf.get(i + 1)) {
+            (Some(&b'\x07'), _) => break (i, i + 1),
+            (Some(&b'\x1B'), Some(&b'\\')) => break (i, i + 2),
+            // Another escape sequence aborts the string.
+            (Some(&b'\x1B'), Some(_)) => return (Some(Event::Unsupported(buf[..i].to_vec())), i),
+            (Some(&b'\x1B'), None) | (None, _) => return (None, 0),
+            _ => i += 1,
+        }
+    };
+
+    let report = str::from_utf8(&buf[2..content]).ok().and_then(|content| {
+        let mut params = content.splitn(3, ';');
+        match (params.next()?, params.next()?, params.next()) {
+            ("4", index, Some(spec)) => Some(Report::PaletteColor(index.parse().ok()?,
+                                                                  parse_color_spec(spec)?)),
+            ("11", spec, None) => Some(Report::Background(parse_color_spec(spec)?)),
+            _ => None,
+        }
+    });
+    match report {
+        Some(report) => (Some(Event::Report(report)), len),
+        None => (Some(Event::Unsupported(buf[..len].to_vec())), len),
+    }
+}
+
+/// Parse a color reported as `rgb:R/G/B`, with one to four hex digits per component.
+fn parse_color_spec(spec: &str) -> Option<Rgb> {
+    let mut components = spec.strip_prefix("rgb:")?.split('/').map(|hex| {
+        if hex.is_empty() || hex.len() > 4 {
+            return None;
+        }
+        let value = u32::from_str_radix(hex, 16).ok()?;
+        let max = (1 << (4 * hex.len())) - 1;
+        Some(((value * 255 + max / 2) / max) as u8)
+    });
+    let rgb = Rgb(components.next()??, components.next()??, components.next()??);
+    match components.next() {
+        None => Some(rgb),
+        Some(_) => None,
+    }
+}
+
+/// The dialect of the key sequences sent by a terminal.
+///
+/// Most terminal emulators follow xterm, which is the default. Some terminals send other sequences
+/// for certain keys, and a few of them mean different keys in xterm.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Default)]
+pub enum Profile {
+    /// xterm and compatible terminals.
+    #[default]
+    Xterm,
+    /// rxvt and urxvt.
+    ///
+    /// These report Shift and Ctrl with the arrows and special keys using their own sequences
+    /// (such as `ESC [ a` for Shift-Up, `ESC O a` for Ctrl-Up and `ESC [ 3 ^` for Ctrl-Delete),
+    /// and Shift-F3 through Shift-F10 with the sequences of F13 through F20 in xterm.
+    Rxvt,
+    /// The Linux virtual console.
+    ///
+    /// This reports F1 through F5 as `ESC [ [ A` through `ESC [ [ E`.
+    Linux,
+}
+
+/// An incremental event parser.
+///
+/// Input can be fed in chunks of any size as it arrives, and events are produced once they are
+/// complete. This allows parsing input over slow links, where escape sequences may be split
+/// between reads.
+///
+/// A lone ESC byte is ambiguous, as it may be the Esc key or the start of an escape sequence. By
+/// default, the parser waits for more input (or `flush`) to decide. With `with_esc_timeout`, it
+/// is instead reported as `Key::Esc` once the timeout has passed without completing a sequence.
+///
+/// # Example
+///
+/// ```rust
+/// use termion::event::{Event, EventParser, Key};
+///
+/// let mut parser = EventParser::new();
+/// parser.feed(b"\x1B[");
+/// assert_eq!(parser.next_event(), None);
+/// parser.feed(b"Db");
+/// assert_eq!(parser.next_event(), Some(Event::Key(Key::Left)));
+/// assert_eq!(parser.next_event(), Some(Event::Key(Key::Char('b'))));
+/// assert_eq!(parser.next_event(), None);
+/// ```
+#[derive(Debug, Default)]
+pub struct EventParser {
+    /// The input fed to the parser.
+    buf: Vec<u8>,
+    /// The position in `buf` of the first byte which has not been parsed yet.
+    pos: usize,
+    /// The number of bytes before `pos` which the last event was parsed from.
+    consumed: usize,
+    /// How long to wait for the rest of a sequence after an ESC byte.
+    esc_timeout: Option<Duration>,
+    /// When the pending ESC byte was found to be alone.
+    esc_received: Option<Instant>,
+    /// The dialect of the input.
+    profile: Profile,
+}
+
+impl EventParser {
+    /// Create a parser without any pending input.
+    pub fn new() -> EventParser {
+        EventParser::default()
+    }
+
+    /// Create a parser which reports a lone ESC byte as `Key::Esc` if no sequence is completed
+    /// within `timeout`.
+    pub fn with_esc_timeout(timeout: Duration) -> EventParser {
+        EventParser {
+            esc_timeout: Some(timeout),
+            ..EventParser::default()
+        }
+    }
+
+    /// The time to wait for the rest of a sequence after an ESC byte, if any.
+    pub fn esc_timeout(&self) -> Option<Duration> {
+        self.esc_timeout
+    }
+
+    /// The dialect of the sequences parsed.
+    pub fn profile(&self) -> Profile {
+        self.profile
+    }
+
+    /// Parse the sequences of the given dialect from now on.
+    pub fn set_profile(&mut self, profile: Profile) {
+        self.profile = profile;
+    }
+
+    /// Feed more input to the parser.
+    pub fn feed(&mut self, input: &[u8]) {
+        self.buf.drain(..self.pos - self.consumed);
+        self.pos = self.consumed;
+        self.buf.extend_from_slice(input);
+    }
+
+    /// Get the next complete event, if any.
+    pub fn next_event(&mut self) -> Option<Event> {
+        let timed_out = match (self.esc_timeout, self.esc_received) {
+            (Some(timeout), Some(received)) => received.elapsed() >= timeout,
+            _ => false,
+        };
+        if timed_out {
+            self.pos += 1;
+            self.consumed = 1;
+            self.esc_received = None;
+            return Some(Event::Key(Key::Esc));
+        }
+
+        let profile = self.profile;
+        let (event, len) = parse_with(self.pending(), profile);
+        self.pos += len;
+        self.consumed = len;
+        if len > 0 {
+            self.esc_received = None;
+        } else if self.pending() == b"\x1B" && self.esc_received.is_none() {
+            self.esc_received = Some(Instant::now());
+        }
+        event
+    }
+
+    /// The input which has not been parsed yet, because it does not make up a complete event.
+    pub fn pending(&self) -> &[u8] {
+        &self.buf[self.pos..]
+    }
+
+    /// The input the event last returned by `next_event` or `flush` was parsed from.
+    ///
+    /// This is empty if no event was returned.
+    pub fn consumed(&self) -> &[u8] {
+        &self.buf[self.pos - self.consumed..self.pos]
+    }
+
+    /// Get the next event, even if it is incomplete.
+    ///
+    /// This should be used once no more input is expected. A pending ESC byte is taken to mean
+    /// `Key::Esc`, while any other incomplete input is returned as `Event::Unsupported`.
+    pub fn flush(&mut self) -> Option<Event> {
+        if let Some(event) = self.next_event() {
+            return Some(event);
+        }
+
+        let pending = self.pending().to_vec();
+        match pending.first() {
+            None => None,
+            Some(&b'\x1B') => {
+                self.pos += 1;
+                self.consumed = 1;
+                self.esc_received = None;
+                Some(Event::Key(Key::Esc))
+            }
+            Some(_) => {
+                self.pos += pending.len();
+                self.consumed = pending.len();
+                Some(Event::Unsupported(pending))
+            }
+        }
+    }
+}
+
+/// Detects repeated clicks, which terminals do not report themselves.
+///
+/// Every event has to be passed to `track`, which returns a `MouseEvent::DoubleClick` or
+/// `MouseEvent::TripleClick` event when a button is pressed repeatedly at the same position, each
+/// press following the previous one within the interval.
+///
+/// # Example
+///
+/// ```rust
+/// use termion::event::{ClickDetector, Event, MouseButton, MouseEvent};
+///
+/// let mut clicks = ClickDetector::new();
+/// let press = Event::Mouse(MouseEvent::Press(MouseButton::Left, 3, 4));
+/// assert_eq!(clicks.track(&press), None);
+/// assert_eq!(clicks.track(&press),
+///            Some(Event::Mouse(MouseEvent::DoubleClick(MouseButton::Left, 3, 4))));
+/// ```
+#[derive(Debug)]
+pub struct ClickDetector {
+    /// The maximum time between two presses of a repeated click.
+    interval: Duration,
+    /// The last press, its time and the number of presses in a row.
+    last: Option<(Event, Instant, u8)>,
+}
+
+impl ClickDetector {
+    /// Create a detector with the default interval of 500 ms.
+    pub fn new() -> ClickDetector {
+        ClickDetector::with_interval(Duration::from_millis(500))
+    }
+
+    /// Create a detector which counts presses following each other within `interval`.
+    pub fn with_interval(interval: Duration) -> ClickDetector {
+        ClickDetector {
+            interval,
+            last: None,
+        }
+    }
+
+    /// Track `event`, returning a double or triple click event if it completes one.
+    pub fn track(&mut self, event: &Event) -> Option<Event> {
+        self.track_at(event, Instant::now())
+    }
+
+    fn track_at(&mut self, event: &Event, now: Instant) -> Option<Event> {
+        let (modifiers, mouse) = match *event {
+            Event::Mouse(mouse) => (None, mouse),
+            Event::ModifiedMouse(modifiers, mouse) => (Some(modifiers), mouse),
+            _ => return None,
+        };
+
+        let (button, x, y) = match mouse {
+            MouseEvent::Press(button @ MouseButton::Left, x, y) |
+            MouseEvent::Press(button @ MouseButton::Middle, x, y) |
+            MouseEvent::Press(button @ MouseButton::Right, x, y) => (button, x, y),
+            // Dragging does not make up a click.
+            MouseEvent::Hold(..) => {
+                self.last = None;
+                return None;
+            }
+            _ => return None,
+        };
+
+        let count = match self.last {
+            Some((ref last, time, count)) if last == event && now - time <= self.interval => {
+                count % 3 + 1
+            }
+            _ => 1,
+        };
+        self.last = Some((event.clone(), now, count));
+
+        let click = match count {
+            2 => MouseEvent::DoubleClick(button, x, y),
+            3 => MouseEvent::TripleClick(button, x, y),
+            _ => return None,
+        };
+        Some(match modifiers {
+            Some(modifiers) => Event::ModifiedMouse(modifiers, click),
+            None => Event::Mouse(click),
+        })
+    }
+}
+
+impl Default for ClickDetector {
+    fn default() -> ClickDetector {
+        ClickDetector::new()
+    }
+}
+
+/// An iterator over the bytes of a slice, remembering whether it ran out.
+struct SliceBytes<'a> {
+    iter: slice::Iter<'a, u8>,
+    exhausted: bool,
+}
+
+impl<'a> Iterator for SliceBytes<'a> {
+    type Item = Result<u8, Error>;
+
+    fn next(&mut self) -> Option<Result<u8, Error>> {
+        let next = self.iter.next().map(|&b| Ok(b));
+        self.exhausted = next.is_none();
+        next
+    }
+}
+
 /// Parse an Event from `item` and possibly subsequent bytes through `iter`.
 pub fn parse_event<I>(item: u8, iter: &mut I) -> Result<Event, Error>
     where I: Iterator<Item = Result<u8, Error>>
+{
+    parse_event_with(item, iter, Profile::Xterm)
+}
+
+/// Parse an Event of the given dialect from `item` and possibly subsequent bytes through `iter`.
+fn parse_event_with<I>(item: u8, iter: &mut I, profile: Profile) -> Result<Event, Error>
+    where I: Iterator<Item = Result<u8, Error>>
 {
     let error = Error::new(ErrorKind::Other, "Could not parse an event");
     match item {
@@ -109,14 +1173,32 @@ pub fn parse_event<I>(item: u8, iter: &mut I) -> Result<Event, Error>
                 Some(Ok(b'O')) => {
                     match iter.next() {
                         // F1-F4
-                        Some(Ok(val @ b'P'...b'S')) => Event::Key(Key::F(1 + val - b'P')),
+                        Some(Ok(val @ b'P'..=b'S')) => Event::Key(Key::F(1 + val - b'P')),
+                        // Application keypad mode.
+                        Some(Ok(b'M')) => Event::Key(Key::KeypadEnter),
+                        Some(Ok(val @ b'p'..=b'y')) => {
+                            Event::Key(Key::Keypad((b'0' + val - b'p') as char))
+                        }
+                        Some(Ok(b'j')) => Event::Key(Key::Keypad('*')),
+                        Some(Ok(b'k')) => Event::Key(Key::Keypad('+')),
+                        Some(Ok(b'l')) => Event::Key(Key::Keypad(',')),
+                        Some(Ok(b'm')) => Event::Key(Key::Keypad('-')),
+                        Some(Ok(b'n')) => Event::Key(Key::Keypad('.')),
+                        Some(Ok(b'o')) => Event::Key(Key::Keypad('/')),
+                        Some(Ok(b'X')) => Event::Key(Key::Keypad('=')),
+                        // rxvt Ctrl-arrows.
+                        Some(Ok(val @ b'a'..=b'd')) if profile == Profile::Rxvt => {
+                            let ctrl = Modifiers { ctrl: true, ..Modifiers::default() };
+                            Event::Key(Key::Modified(ctrl, rxvt_arrow(val)))
+                        }
                         _ => return Err(error),
                     }
                 }
                 Some(Ok(b'[')) => {
                     // This is a CSI sequence.
-                    parse_csi(iter).ok_or(error)?
+                    parse_csi(iter, profile).ok_or(error)?
                 }
+                Some(Ok(b'\r')) => Event::Key(Key::Alt('\n')),
                 Some(Ok(c)) => {
                     let ch = parse_utf8_char(c, iter);
                     Event::Key(Key::Alt(try!(ch)))
@@ -142,158 +1224,223 @@ pub fn parse_event<I>(item: u8, iter: &mut I) -> Result<Event, Error>
 /// Parses a CSI sequence, just after reading ^[
 ///
 /// Returns None if an unrecognized sequence is found.
-fn parse_csi<I>(iter: &mut I) -> Option<Event>
+fn parse_csi<I>(iter: &mut I, profile: Profile) -> Option<Event>
     where I: Iterator<Item = Result<u8, Error>>
 {
     Some(match iter.next() {
+        // rxvt Shift-arrows.
+        Some(Ok(c @ b'a'..=b'd')) if profile == Profile::Rxvt => {
+            let shift = Modifiers { shift: true, ..Modifiers::default() };
+            Event::Key(Key::Modified(shift, rxvt_arrow(c)))
+        }
+        // Linux console F1-F5.
+        Some(Ok(b'[')) if profile == Profile::Linux => {
+            match iter.next() {
+                Some(Ok(c @ b'A'..=b'E')) => Event::Key(Key::F(1 + c - b'A')),
+                _ => return None,
+            }
+        }
         Some(Ok(b'D')) => Event::Key(Key::Left),
         Some(Ok(b'C')) => Event::Key(Key::Right),
         Some(Ok(b'A')) => Event::Key(Key::Up),
         Some(Ok(b'B')) => Event::Key(Key::Down),
         Some(Ok(b'H')) => Event::Key(Key::Home),
         Some(Ok(b'F')) => Event::Key(Key::End),
+        Some(Ok(b'I')) => Event::FocusGained,
+        Some(Ok(b'O')) => Event::FocusLost,
         Some(Ok(b'M')) => {
             // X10 emulation mouse encoding: ESC [ CB Cx Cy (6 characters only).
-            let mut next = || iter.next().unwrap().unwrap();
+            let mut next = || iter.next().and_then(|b| b.ok());
 
-            let cb = next() as i8 - 32;
+            let cb = next()?.wrapping_sub(32);
             // (1, 1) are the coords for upper left.
-            let cx = next().saturating_sub(32) as u16;
-            let cy = next().saturating_sub(32) as u16;
-            Event::Mouse(match cb & 0b11 {
-                0 => {
-                    if cb & 0x40 != 0 {
-                        MouseEvent::Press(MouseButton::WheelUp, cx, cy)
-                    } else {
-                        MouseEvent::Press(MouseButton::Left, cx, cy)
-                    }
-                }
-                1 => {
-                    if cb & 0x40 != 0 {
-                        MouseEvent::Press(MouseButton::WheelDown, cx, cy)
-                    } else {
-                        MouseEvent::Press(MouseButton::Middle, cx, cy)
-                    }
-                }
-                2 => MouseEvent::Press(MouseButton::Right, cx, cy),
-                3 => MouseEvent::Release(cx, cy),
-                _ => return None,
-            })
+            let cx = next()?.saturating_sub(32) as u16;
+            let cy = next()?.saturating_sub(32) as u16;
+            parse_mouse_event(cb as u16, cx, cy, false)
         }
         Some(Ok(b'<')) => {
             // xterm mouse encoding:
             // ESC [ < Cb ; Cx ; Cy ; (M or m)
             let mut buf = Vec::new();
-            let mut c = iter.next().unwrap().unwrap();
-            while match c {
-                b'm' | b'M' => false,
-                _ => true,
-            } {
+            let mut c = iter.next()?.ok()?;
+            while c < 64 || c > 126 {
                 buf.push(c);
-                c = iter.next().unwrap().unwrap();
+                c = iter.next()?.ok()?;
             }
-            let str_buf = String::from_utf8(buf).unwrap();
-            let nums = &mut str_buf.split(';');
-
-            let cb = nums.next().unwrap().parse::<u16>().unwrap();
-            let cx = nums.next().unwrap().parse::<u16>().unwrap();
-            let cy = nums.next().unwrap().parse::<u16>().unwrap();
-
-            let event = match cb {
-                0...2 | 64...65 => {
-                    let button = match cb {
-                        0 => MouseButton::Left,
-                        1 => MouseButton::Middle,
-                        2 => MouseButton::Right,
-                        64 => MouseButton::WheelUp,
-                        65 => MouseButton::WheelDown,
-                        _ => unreachable!(),
-                    };
-                    match c {
-                        b'M' => MouseEvent::Press(button, cx, cy),
-                        b'm' => MouseEvent::Release(cx, cy),
-                        _ => return None,
-                    }
-                }
-                32 => MouseEvent::Hold(cx, cy),
+            let (cb, cx, cy) = parse_mouse_params(&buf)?;
+
+            match c {
+                b'M' if cb < 128 => parse_mouse_event(cb, cx, cy, false),
+                b'm' if cb < 128 => parse_mouse_event(cb, cx, cy, true),
                 _ => return None,
-            };
+            }
+        }
+        Some(Ok(c @ b'?')) | Some(Ok(c @ b'>')) => {
+            // Private reports: ESC [ ? Ps ; ... <final> or ESC [ > Ps ; ... <final>
+            let mut buf = Vec::new();
+            let mut last = iter.next()?.ok()?;
+            while !(64..=126).contains(&last) {
+                buf.push(last);
+                last = iter.next()?.ok()?;
+            }
+            // Mode reports have a `$` before the final byte.
+            let dollar = buf.last() == Some(&b'$');
+            if dollar {
+                buf.pop();
+            }
+            let nums = parse_numbers(&buf)?;
 
-            Event::Mouse(event)
+            Event::Report(match (c, last, dollar) {
+                (b'?', b'c', false) => Report::PrimaryAttributes(nums),
+                (b'>', b'c', false) => Report::SecondaryAttributes(nums),
+                // DECXCPR, possibly followed by the page number.
+                (b'?', b'R', false) if nums.len() >= 2 => {
+                    Report::CursorPosition(nums[1], nums[0])
+                }
+                // DECRPM, where 3 and 4 mean permanently set and reset.
+                (b'?', b'y', true) if nums.len() == 2 => {
+                    Report::PrivateMode(nums[0], match nums[1] {
+                        1 | 3 => Some(true),
+                        2 | 4 => Some(false),
+                        _ => None,
+                    })
+                }
+                _ => return None,
+            })
         }
         Some(Ok(c @ b'0'...b'9')) => {
             // Numbered escape code.
             let mut buf = Vec::new();
             buf.push(c);
-            let mut c = iter.next().unwrap().unwrap();
+            let mut c = iter.next()?.ok()?;
             // The final byte of a CSI sequence can be in the range 64-126, so
-            // let's keep reading anything else.
-            while c < 64 || c > 126 {
+            // let's keep reading anything else. rxvt ends shifted keys with `$` though.
+            let is_final = |c| (64..=126).contains(&c) || (profile == Profile::Rxvt && c == b'$');
+            while !is_final(c) {
                 buf.push(c);
-                c = iter.next().unwrap().unwrap();
+                c = iter.next()?.ok()?;
             }
 
             match c {
                 // rxvt mouse encoding:
                 // ESC [ Cb ; Cx ; Cy ; M
                 b'M' => {
-                    let str_buf = String::from_utf8(buf).unwrap();
-
-                    let nums: Vec<u16> = str_buf
-                        .split(';')
-                        .map(|n| n.parse().unwrap())
-                        .collect();
-
-                    let cb = nums[0];
-                    let cx = nums[1];
-                    let cy = nums[2];
-
-                    let event = match cb {
-                        32 => MouseEvent::Press(MouseButton::Left, cx, cy),
-                        33 => MouseEvent::Press(MouseButton::Middle, cx, cy),
-                        34 => MouseEvent::Press(MouseButton::Right, cx, cy),
-                        35 => MouseEvent::Release(cx, cy),
-                        64 => MouseEvent::Hold(cx, cy),
-                        96 | 97 => MouseEvent::Press(MouseButton::WheelUp, cx, cy),
+                    let (cb, cx, cy) = parse_mouse_params(&buf)?;
+
+                    match cb.checked_sub(32) {
+                        Some(cb) if cb < 128 => parse_mouse_event(cb, cx, cy, false),
+                        _ => return None,
+                    }
+                }
+                // Special key code, possibly followed by a modifier parameter (e.g. `ESC [ 3 ; 5 ~`
+                // is Ctrl+Delete). rxvt instead ends the sequence with `$` for Shift, `^` for Ctrl
+                // and `@` for both.
+                b'~' | b'$' | b'^' | b'@' if c == b'~' || profile == Profile::Rxvt => {
+                    let params = parse_key_params(&buf)?;
+
+                    let key = match param(&params, 0, 0)? {
+                        // xterm's modifyOtherKeys: ESC [ 27 ; modifiers ; code ~
+                        27 if c == b'~' => {
+                            let modifiers = param(&params, 1, 0);
+                            let key = match code_key(param(&params, 2, 0)?)? {
+                                // Report characters unshifted along with the modifiers, the
+                                // way kitty does.
+                                ModifiedKey::Char(c) if modifiers > Some(2) => {
+                                    ModifiedKey::Char(c.to_lowercase().next().unwrap_or(c))
+                                }
+                                key => key,
+                            };
+                            return Some(Event::Key(modified_key(key, modifiers)));
+                        }
+                        1 | 7 => ModifiedKey::Home,
+                        2 => ModifiedKey::Insert,
+                        3 => ModifiedKey::Delete,
+                        4 | 8 => ModifiedKey::End,
+                        5 => ModifiedKey::PageUp,
+                        6 => ModifiedKey::PageDown,
+                        v @ 11..=15 => ModifiedKey::F(v as u8 - 10),
+                        v @ 17..=21 => ModifiedKey::F(v as u8 - 11),
+                        v @ 23..=26 => ModifiedKey::F(v as u8 - 12),
+                        v @ 28..=29 => ModifiedKey::F(v as u8 - 13),
+                        v @ 31..=34 => ModifiedKey::F(v as u8 - 14),
+                        200 => return Some(Event::Paste(parse_paste(iter)?)),
                         _ => return None,
                     };
 
-                    Event::Mouse(event)
+                    let mut modifiers = param(&params, 1, 0)
+                        .map_or_else(Modifiers::default, Modifiers::from_param);
+                    modifiers.shift |= c == b'$' || c == b'@';
+                    modifiers.ctrl |= c == b'^' || c == b'@';
+                    let key = match key {
+                        ModifiedKey::F(n @ 13..=20) if profile == Profile::Rxvt => {
+                            modifiers.shift = true;
+                            ModifiedKey::F(n - 10)
+                        }
+                        key => key,
+                    };
+
+                    return key_event(modified_key(key, Some(modifiers.to_param())),
+                                     param(&params, 1, 1));
                 }
-                // Special key code.
-                b'~' => {
-                    let str_buf = String::from_utf8(buf).unwrap();
-
-                    // This CSI sequence can be a list of semicolon-separated
-                    // numbers.
-                    let nums: Vec<u8> = str_buf
-                        .split(';')
-                        .map(|n| n.parse().unwrap())
-                        .collect();
-
-                    if nums.is_empty() {
-                        return None;
+                // Cursor position report: ESC [ row ; column R
+                b'R' if !is_modified_f3(&buf) => {
+                    match parse_numbers(&buf)?[..] {
+                        [y, x] => Event::Report(Report::CursorPosition(x, y)),
+                        _ => return None,
                     }
-
-                    // TODO: handle multiple values for key modififiers (ex: values
-                    // [3, 2] means Shift+Delete)
-                    if nums.len() > 1 {
-                        return None;
+                }
+                // Window size report: ESC [ 8 ; rows ; columns t
+                b't' => {
+                    match parse_numbers(&buf)?[..] {
+                        [8, height, width] => Event::Resize(width, height),
+                        _ => return None,
                     }
-
-                    match nums[0] {
-                        1 | 7 => Event::Key(Key::Home),
-                        2 => Event::Key(Key::Insert),
-                        3 => Event::Key(Key::Delete),
-                        4 | 8 => Event::Key(Key::End),
-                        5 => Event::Key(Key::PageUp),
-                        6 => Event::Key(Key::PageDown),
-                        v @ 11...15 => Event::Key(Key::F(v - 10)),
-                        v @ 17...21 => Event::Key(Key::F(v - 11)),
-                        v @ 23...24 => Event::Key(Key::F(v - 12)),
+                }
+                // Operating status report.
+                b'n' => {
+                    match parse_numbers(&buf)?[..] {
+                        [0] => Event::Report(Report::Status(true)),
+                        [3] => Event::Report(Report::Status(false)),
                         _ => return None,
                     }
                 }
+                // Modified arrows, Home, End and F1-F4 (e.g. `ESC [ 1 ; 5 A` is Ctrl+Up).
+                b'A' | b'B' | b'C' | b'D' | b'H' | b'F' | b'P' | b'Q' | b'R' | b'S' => {
+                    let params = parse_key_params(&buf)?;
+
+                    let key = match c {
+                        b'A' => ModifiedKey::Up,
+                        b'B' => ModifiedKey::Down,
+                        b'C' => ModifiedKey::Right,
+                        b'D' => ModifiedKey::Left,
+                        b'H' => ModifiedKey::Home,
+                        b'F' => ModifiedKey::End,
+                        _ => ModifiedKey::F(1 + c - b'P'),
+                    };
+
+                    return key_event(modified_key(key, param(&params, 1, 0)),
+                                     param(&params, 1, 1));
+                }
+                // kitty keyboard protocol:
+                // ESC [ code[:shifted] ; modifiers[:event] ; text u
+                b'u' => {
+                    let params = parse_key_params(&buf)?;
+                    let modifiers = param(&params, 1, 0);
+
+                    let key = code_key(param(&params, 0, 0)?)?;
+
+                    // Prefer the reported shifted key over guessing it, if only Shift is held.
+                    let shifted = param(&params, 0, 1).and_then(::std::char::from_u32);
+                    let shift_only = Some(Modifiers { shift: true, ..Modifiers::default() });
+                    let key = match shifted {
+                        Some(c) if modifiers.map(Modifiers::from_param) == shift_only => {
+                            Key::Char(c)
+                        }
+                        _ => modified_key(key, modifiers),
+                    };
+
+                    return key_event(key, param(&params, 1, 1));
+                }
                 _ => return None,
             }
         }
@@ -302,27 +1449,181 @@ fn parse_csi<I>(iter: &mut I) -> Option<Event>
 
 }
 
+/// The key reported by the key code `code` of the kitty keyboard protocol or modifyOtherKeys.
+///
+/// This is a Unicode code point, except for functional keys, which use the private use area.
+fn code_key(code: u32) -> Option<ModifiedKey> {
+    Some(match code {
+        27 => ModifiedKey::Esc,
+        8 | 127 => ModifiedKey::Backspace,
+        13 => ModifiedKey::Char('\n'),
+        9 => ModifiedKey::Char('\t'),
+        v @ 57376..=57387 => ModifiedKey::F((v - 57376) as u8 + 13),
+        v @ 57399..=57408 => ModifiedKey::Keypad((b'0' + (v - 57399) as u8) as char),
+        57409 => ModifiedKey::Keypad('.'),
+        57410 => ModifiedKey::Keypad('/'),
+        57411 => ModifiedKey::Keypad('*'),
+        57412 => ModifiedKey::Keypad('-'),
+        57413 => ModifiedKey::Keypad('+'),
+        57414 => ModifiedKey::KeypadEnter,
+        57415 => ModifiedKey::Keypad('='),
+        0xE000..=0xF8FF => return None,
+        code => ModifiedKey::Char(::std::char::from_u32(code)?),
+    })
+}
+
+/// The arrow key of the rxvt sequences `ESC [ a` through `ESC [ d` and `ESC O a` through `ESC O d`.
+fn rxvt_arrow(c: u8) -> ModifiedKey {
+    match c {
+        b'a' => ModifiedKey::Up,
+        b'b' => ModifiedKey::Down,
+        b'c' => ModifiedKey::Right,
+        _ => ModifiedKey::Left,
+    }
+}
+
+/// Decode the button code `cb` of a mouse report (without any offset) at the given coordinates.
+///
+/// The low bits give the button (3 meaning a release), followed by bits for Shift, Alt and Ctrl,
+/// for motion and for the wheel. `release` tells that the event is a release regardless of the
+/// button, for encodings which report releases separately.
+///
+fn parse_mouse_event(cb: u16, cx: u16, cy: u16, release: bool) -> Event {
+    let modifiers = Modifiers {
+        shift: cb & 0b100 != 0,
+        alt: cb & 0b1000 != 0,
+        ctrl: cb & 0b10000 != 0,
+    };
+    let motion = cb & 0x20 != 0;
+    let wheel = cb & 0x40 != 0;
+
+    let event = match (wheel, cb & 0b11) {
+        (true, 0) => MouseEvent::Press(MouseButton::WheelUp, cx, cy),
+        (true, 1) => MouseEvent::Press(MouseButton::WheelDown, cx, cy),
+        (true, 2) => MouseEvent::Press(MouseButton::WheelLeft, cx, cy),
+        (true, _) => MouseEvent::Press(MouseButton::WheelRight, cx, cy),
+        (false, 3) if motion => MouseEvent::Move(cx, cy),
+        // A button is being dragged.
+        (false, _) if motion => MouseEvent::Hold(cx, cy),
+        (false, 3) => MouseEvent::Release(cx, cy),
+        (false, _) if release => MouseEvent::Release(cx, cy),
+        (false, 0) => MouseEvent::Press(MouseButton::Left, cx, cy),
+        (false, 1) => MouseEvent::Press(MouseButton::Middle, cx, cy),
+        (false, _) => MouseEvent::Press(MouseButton::Right, cx, cy),
+    };
+
+    if modifiers == Modifiers::default() {
+        Event::Mouse(event)
+    } else {
+        Event::ModifiedMouse(modifiers, event)
+    }
+}
+
+/// Parse the button and coordinates of an extended mouse sequence from the semicolon-separated
+/// parameters.
+///
+/// Returns None if there are less than three numbers.
+fn parse_mouse_params(buf: &[u8]) -> Option<(u16, u16, u16)> {
+    let nums = &mut str::from_utf8(buf).ok()?.split(';');
+    let mut next = || nums.next().and_then(|n| n.parse().ok());
+
+    Some((next()?, next()?, next()?))
+}
+
+/// Parse the semicolon-separated numbers of a report.
+fn parse_numbers(buf: &[u8]) -> Option<Vec<u16>> {
+    if buf.is_empty() {
+        return Some(Vec::new());
+    }
+    str::from_utf8(buf).ok()?.split(';').map(|n| n.parse().ok()).collect()
+}
+
+/// Whether the parameters of a sequence ending in `R` are those of a modified F3 key rather
+/// than a cursor position report.
+fn is_modified_f3(buf: &[u8]) -> bool {
+    match parse_numbers(buf).as_ref().map(|nums| &nums[..]) {
+        Some(&[1, m]) => (2..=16).contains(&m),
+        _ => false,
+    }
+}
+
+/// The parameters of a key sequence: semicolon-separated parameters, each holding
+/// colon-separated sub-parameters, where empty sub-parameters are `None`.
+type KeyParams = Vec<Vec<Option<u32>>>;
+
+/// Parse the parameters of a special key sequence.
+///
+/// Returns None if any sub-parameter is neither empty nor a number, or if there are more than
+/// three parameters.
+fn parse_key_params(buf: &[u8]) -> Option<KeyParams> {
+    let params = str::from_utf8(buf)
+        .ok()?
+        .split(';')
+        .map(|p| {
+            p.split(':')
+                .map(|n| if n.is_empty() { Some(None) } else { n.parse().ok().map(Some) })
+                .collect::<Option<Vec<_>>>()
+        })
+        .collect::<Option<KeyParams>>()?;
+
+    if params.len() > 3 {
+        return None;
+    }
+
+    Some(params)
+}
+
+/// Get the `j`th sub-parameter of the `i`th parameter, if present.
+fn param(params: &KeyParams, i: usize, j: usize) -> Option<u32> {
+    params.get(i).and_then(|p| p.get(j)).and_then(|&n| n)
+}
+
+/// Reads the content of a bracketed paste, just after reading ^[[200~
+///
+/// Returns None if the input ends before the closing ^[[201~ is found.
+fn parse_paste<I>(iter: &mut I) -> Option<String>
+    where I: Iterator<Item = Result<u8, Error>>
+{
+    const END: &[u8] = b"\x1B[201~";
+
+    let mut buf = Vec::new();
+    while !buf.ends_with(END) {
+        buf.push(iter.next()?.ok()?);
+    }
+    buf.truncate(buf.len() - END.len());
+
+    Some(String::from_utf8_lossy(&buf).into_owned())
+}
+
 /// Parse `c` as either a single byte ASCII char or a variable size UTF-8 char.
+///
+/// The length of the sequence is determined by its leading byte, so an invalid sequence consumes
+/// no more bytes than it claims to span.
 fn parse_utf8_char<I>(c: u8, iter: &mut I) -> Result<char, Error>
     where I: Iterator<Item = Result<u8, Error>>
 {
-    let error = Err(Error::new(ErrorKind::Other, "Input character is not valid UTF-8"));
-    if c.is_ascii() {
-        Ok(c as char)
-    } else {
-        let bytes = &mut Vec::new();
-        bytes.push(c);
+    let error = || Error::new(ErrorKind::Other, "Input character is not valid UTF-8");
+    let width = match c {
+        0x00..=0x7F => return Ok(c as char),
+        0xC2..=0xDF => 2,
+        0xE0..=0xEF => 3,
+        0xF0..=0xF4 => 4,
+        _ => return Err(error()),
+    };
 
-        loop {
-            bytes.push(iter.next().unwrap().unwrap());
-            if let Ok(st) = str::from_utf8(bytes) {
-                return Ok(st.chars().next().unwrap());
-            }
-            if bytes.len() >= 4 {
-                return error;
-            }
-        }
+    let mut bytes = [c, 0, 0, 0];
+    for byte in &mut bytes[1..width] {
+        *byte = match iter.next() {
+            Some(Ok(b @ 0x80..=0xBF)) => b,
+            Some(Err(e)) => return Err(e),
+            _ => return Err(error()),
+        };
     }
+
+    str::from_utf8(&bytes[..width])
+        .ok()
+        .and_then(|st| st.chars().next())
+        .ok_or_else(error)
 }
 
 #[cfg(test)]
@@ -336,3 +1637,326 @@ fn test_parse_utf8() {
         assert!(c == parse_utf8_char(b, bytes).unwrap());
     }
 }
+
+#[cfg(test)]
+#[test]
+fn test_parse_invalid_utf8() {
+    // A truncated sequence is an error rather than a panic.
+    let bytes = &mut "€".bytes().skip(1).take(1).map(Ok);
+    assert!(parse_utf8_char(0xE2, bytes).is_err());
+
+    // An invalid continuation byte ends the sequence.
+    let bytes = &mut b"\xA4ab".iter().cloned().map(Ok);
+    assert!(parse_utf8_char(0xE2, bytes).is_err());
+    assert_eq!(bytes.next().unwrap().unwrap(), b'b');
+
+    // A stray continuation byte does not consume the following input.
+    let bytes = &mut b"bc".iter().cloned().map(Ok);
+    assert!(parse_utf8_char(0xA4, bytes).is_err());
+    assert_eq!(bytes.next().unwrap().unwrap(), b'b');
+}
+
+#[cfg(test)]
+#[test]
+fn test_parse() {
+    let buf = b"\x1B[1;5Ab\x1B[\x00\xC3\xA9\x1B[200~ab";
+    assert_eq!(parse(buf),
+               (Some(Event::Key(Key::Modified(Modifiers { ctrl: true, ..Modifiers::default() },
+                                              ModifiedKey::Up))),
+                6));
+    assert_eq!(parse(&buf[6..]), (Some(Event::Key(Key::Char('b'))), 1));
+    assert_eq!(parse(&buf[7..]), (Some(Event::Unsupported(vec![0x1B, b'[', 0x00])), 3));
+    assert_eq!(parse(b"\x1B[12;34xy"), (Some(Event::Unsupported(b"\x1B[12;34x".to_vec())), 8));
+    assert_eq!(parse(&buf[10..]), (Some(Event::Key(Key::Char('é'))), 2));
+    // An unterminated paste is incomplete.
+    assert_eq!(parse(&buf[12..]), (None, 0));
+    assert_eq!(parse(b"\x1B"), (None, 0));
+    assert_eq!(parse(b"\xC3"), (None, 0));
+    assert_eq!(parse(b""), (None, 0));
+}
+
+#[cfg(test)]
+#[test]
+fn test_event_parser() {
+    let mut parser = EventParser::new();
+    for &b in b"\x1B[1;5A\xE2\x82\xAC\x1B[200~x\x1B[201" {
+        parser.feed(&[b]);
+    }
+    assert_eq!(parser.next_event(),
+               Some(Event::Key(Key::Modified(Modifiers { ctrl: true, ..Modifiers::default() },
+                                             ModifiedKey::Up))));
+    assert_eq!(parser.consumed(), b"\x1B[1;5A");
+    assert_eq!(parser.next_event(), Some(Event::Key(Key::Char('€'))));
+    assert_eq!(parser.consumed(), "€".as_bytes());
+    assert_eq!(parser.next_event(), None);
+    assert_eq!(parser.consumed(), b"");
+    assert_eq!(parser.pending(), b"\x1B[200~x\x1B[201");
+
+    parser.feed(b"~\x1B");
+    assert_eq!(parser.next_event(), Some(Event::Paste("x".to_string())));
+    assert_eq!(parser.next_event(), None);
+    assert_eq!(parser.flush(), Some(Event::Key(Key::Esc)));
+
+    parser.feed(b"\xE2\x82");
+    assert_eq!(parser.flush(), Some(Event::Unsupported(vec![0xE2, 0x82])));
+    assert_eq!(parser.consumed(), b"\xE2\x82");
+    assert_eq!(parser.flush(), None);
+}
+
+#[cfg(test)]
+#[test]
+fn test_esc_timeout() {
+    use std::thread;
+
+    let mut parser = EventParser::with_esc_timeout(Duration::from_secs(60));
+    parser.feed(b"\x1B");
+    assert_eq!(parser.next_event(), None);
+    parser.feed(b"[A");
+    assert_eq!(parser.next_event(), Some(Event::Key(Key::Up)));
+
+    let mut parser = EventParser::with_esc_timeout(Duration::from_millis(1));
+    parser.feed(b"\x1B");
+    assert_eq!(parser.next_event(), None);
+    thread::sleep(Duration::from_millis(5));
+    parser.feed(b"[A");
+    assert_eq!(parser.next_event(), Some(Event::Key(Key::Esc)));
+    assert_eq!(parser.next_event(), Some(Event::Key(Key::Char('['))));
+    assert_eq!(parser.next_event(), Some(Event::Key(Key::Char('A'))));
+}
+
+#[cfg(test)]
+#[test]
+fn test_click_detector() {
+    let mut clicks = ClickDetector::with_interval(Duration::from_millis(100));
+    let start = Instant::now();
+    let at = |ms| start + Duration::from_millis(ms);
+    let press = Event::Mouse(MouseEvent::Press(MouseButton::Left, 3, 4));
+    let release = Event::Mouse(MouseEvent::Release(3, 4));
+
+    assert_eq!(clicks.track_at(&press, at(0)), None);
+    assert_eq!(clicks.track_at(&release, at(10)), None);
+    assert_eq!(clicks.track_at(&press, at(90)),
+               Some(Event::Mouse(MouseEvent::DoubleClick(MouseButton::Left, 3, 4))));
+    assert_eq!(clicks.track_at(&press, at(180)),
+               Some(Event::Mouse(MouseEvent::TripleClick(MouseButton::Left, 3, 4))));
+    // A fourth press starts over.
+    assert_eq!(clicks.track_at(&press, at(200)), None);
+    // Too slow.
+    assert_eq!(clicks.track_at(&press, at(400)), None);
+    // Elsewhere.
+    let other = Event::Mouse(MouseEvent::Press(MouseButton::Left, 4, 4));
+    assert_eq!(clicks.track_at(&other, at(410)), None);
+    // Dragging in between.
+    assert_eq!(clicks.track_at(&Event::Mouse(MouseEvent::Hold(4, 4)), at(420)), None);
+    assert_eq!(clicks.track_at(&other, at(430)), None);
+
+    let ctrl = Modifiers { ctrl: true, ..Modifiers::default() };
+    let press = Event::ModifiedMouse(ctrl, MouseEvent::Press(MouseButton::Right, 3, 4));
+    assert_eq!(clicks.track_at(&press, at(500)), None);
+    assert_eq!(clicks.track_at(&press, at(510)),
+               Some(Event::ModifiedMouse(ctrl, MouseEvent::DoubleClick(MouseButton::Right, 3, 4))));
+}
+
+#[cfg(test)]
+#[test]
+fn test_write_events() {
+    let ctrl_shift = Modifiers { shift: true, ctrl: true, ..Modifiers::default() };
+    let alt = Modifiers { alt: true, ..Modifiers::default() };
+    let events = [Event::Key(Key::Char('a')),
+                  Event::Key(Key::Char('\n')),
+                  Event::Key(Key::Char('\t')),
+                  Event::Key(Key::Char('ö')),
+                  Event::Key(Key::Alt('x')),
+                  Event::Key(Key::Alt('[')),
+                  Event::Key(Key::Ctrl('c')),
+                  Event::Key(Key::Ctrl('5')),
+                  Event::Key(Key::Ctrl(' ')),
+                  Event::Key(Key::Null),
+                  Event::Key(Key::Esc),
+                  Event::Key(Key::Backspace),
+                  Event::Key(Key::Up),
+                  Event::Key(Key::End),
+                  Event::Key(Key::PageDown),
+                  Event::Key(Key::F(1)),
+                  Event::Key(Key::F(12)),
+                  Event::Key(Key::F(20)),
+                  Event::Key(Key::F(24)),
+                  Event::Key(Key::KeypadEnter),
+                  Event::Key(Key::Keypad('7')),
+                  Event::Key(Key::Keypad('=')),
+                  Event::Key(Key::Modified(ctrl_shift, ModifiedKey::Left)),
+                  Event::Key(Key::Modified(alt, ModifiedKey::F(3))),
+                  Event::Key(Key::Modified(alt, ModifiedKey::Delete)),
+                  Event::Key(Key::Modified(ctrl_shift, ModifiedKey::Char('a'))),
+                  Event::Key(Key::Modified(ctrl_shift, ModifiedKey::Keypad('+'))),
+                  Event::KeyRepeat(Key::Char('a')),
+                  Event::KeyRelease(Key::Ctrl('a')),
+                  Event::KeyRelease(Key::Up),
+                  Event::KeyRelease(Key::F(9)),
+                  Event::Mouse(MouseEvent::Press(MouseButton::Right, 300, 2)),
+                  Event::Mouse(MouseEvent::Press(MouseButton::WheelLeft, 1, 2)),
+                  Event::Mouse(MouseEvent::Release(5, 6)),
+                  Event::Mouse(MouseEvent::Hold(5, 6)),
+                  Event::Mouse(MouseEvent::Move(7, 8)),
+                  Event::ModifiedMouse(alt, MouseEvent::Press(MouseButton::Left, 1, 1)),
+                  Event::Paste("some\ntext".to_string()),
+                  Event::FocusGained,
+                  Event::FocusLost,
+                  Event::Report(Report::CursorPosition(80, 24)),
+                  Event::Report(Report::PrimaryAttributes(vec![62, 22])),
+                  Event::Report(Report::SecondaryAttributes(vec![41, 353, 0])),
+                  Event::Resize(80, 24),
+                  Event::Report(Report::Status(false)),
+                  Event::Report(Report::PrivateMode(25, Some(false))),
+                  Event::Report(Report::PaletteColor(4, Rgb(0, 0, 238))),
+                  Event::Report(Report::Background(Rgb(255, 255, 255))),
+                  Event::Unsupported(b"\x1B[5x".to_vec())];
+
+    for event in events.iter() {
+        let mut parser = EventParser::new();
+        parser.feed(&event.to_bytes().unwrap());
+        assert_eq!(parser.flush(), Some(event.clone()));
+        assert_eq!(parser.pending(), b"");
+    }
+
+    assert_eq!(Event::Key(Key::Modified(ctrl_shift, ModifiedKey::Left)).to_bytes().unwrap(),
+               b"\x1B[1;6D");
+    assert_eq!(Event::KeyRelease(Key::Char('a')).to_bytes().unwrap(), b"\x1B[97;1:3u");
+    assert_eq!(Event::Mouse(MouseEvent::DoubleClick(MouseButton::Left, 1, 1)).to_bytes().unwrap(),
+               b"");
+    assert!(Key::F(30).write_to(&mut Vec::new()).is_err());
+    assert!(Event::KeyRelease(Key::Null).to_bytes().is_err());
+}
+
+#[cfg(test)]
+#[test]
+fn test_key_from_str() {
+    let ctrl_alt = Modifiers { alt: true, ctrl: true, ..Modifiers::default() };
+    let shift = Modifiers { shift: true, ..Modifiers::default() };
+
+    assert_eq!("q".parse(), Ok(Key::Char('q')));
+    assert_eq!("Q".parse(), Ok(Key::Char('Q')));
+    assert_eq!("-".parse(), Ok(Key::Char('-')));
+    assert_eq!("ctrl-x".parse(), Ok(Key::Ctrl('x')));
+    assert_eq!("Ctrl+X".parse(), Ok(Key::Ctrl('x')));
+    assert_eq!("C-x".parse(), Ok(Key::Ctrl('x')));
+    assert_eq!("alt--".parse(), Ok(Key::Alt('-')));
+    assert_eq!("ö".parse(), Ok(Key::Char('ö')));
+    assert_eq!("alt-ö".parse(), Ok(Key::Alt('ö')));
+    assert_eq!("ctrl-+".parse(), Ok(Key::Ctrl('+')));
+    assert_eq!("shift-a".parse(), Ok(Key::Char('A')));
+    assert_eq!("alt-enter".parse(), Ok(Key::Alt('\n')));
+    assert_eq!("space".parse(), Ok(Key::Char(' ')));
+    assert_eq!("ESC".parse(), Ok(Key::Esc));
+    assert_eq!("null".parse(), Ok(Key::Null));
+    assert_eq!("F5".parse(), Ok(Key::F(5)));
+    assert_eq!("pgdn".parse(), Ok(Key::PageDown));
+    assert_eq!("shift-tab".parse(), Ok(Key::Modified(shift, ModifiedKey::Char('\t'))));
+    assert_eq!("ctrl-alt-delete".parse(), Ok(Key::Modified(ctrl_alt, ModifiedKey::Delete)));
+    assert_eq!("shift-f24".parse(), Ok(Key::Modified(shift, ModifiedKey::F(24))));
+
+    for s in &["", "ctrl-", "f0", "f25", "fx", "hyper-x", "ctrl-null", "left-x", "enterr"] {
+        assert_eq!(s.parse::<Key>(), Err(ParseKeyError(s.to_string())));
+    }
+
+    // Descriptions match what the parser reports.
+    assert_eq!(parse(b"\x1B\r"), (Some(Event::Key(Key::Alt('\n'))), 2));
+}
+
+#[cfg(test)]
+#[test]
+fn test_profiles() {
+    let shift = Modifiers { shift: true, ..Modifiers::default() };
+    let ctrl = Modifiers { ctrl: true, ..Modifiers::default() };
+    let ctrl_shift = Modifiers { shift: true, ctrl: true, ..Modifiers::default() };
+    let key = |buf: &[u8], profile| match parse_with(buf, profile) {
+        (Some(Event::Key(key)), len) if len == buf.len() => key,
+        other => panic!("{:?} parsed as {:?}", buf, other),
+    };
+
+    assert_eq!(key(b"\x1B[a", Profile::Rxvt), Key::Modified(shift, ModifiedKey::Up));
+    assert_eq!(key(b"\x1B[d", Profile::Rxvt), Key::Modified(shift, ModifiedKey::Left));
+    assert_eq!(key(b"\x1BOc", Profile::Rxvt), Key::Modified(ctrl, ModifiedKey::Right));
+    assert_eq!(key(b"\x1BOb", Profile::Rxvt), Key::Modified(ctrl, ModifiedKey::Down));
+    assert_eq!(key(b"\x1B[7~", Profile::Rxvt), Key::Home);
+    assert_eq!(key(b"\x1B[8^", Profile::Rxvt), Key::Modified(ctrl, ModifiedKey::End));
+    assert_eq!(key(b"\x1B[2$", Profile::Rxvt), Key::Modified(shift, ModifiedKey::Insert));
+    assert_eq!(key(b"\x1B[3@", Profile::Rxvt), Key::Modified(ctrl_shift, ModifiedKey::Delete));
+    assert_eq!(key(b"\x1B[11^", Profile::Rxvt), Key::Modified(ctrl, ModifiedKey::F(1)));
+    assert_eq!(key(b"\x1B[25~", Profile::Rxvt), Key::Modified(shift, ModifiedKey::F(3)));
+    assert_eq!(key(b"\x1B[34^", Profile::Rxvt), Key::Modified(ctrl_shift, ModifiedKey::F(10)));
+    assert_eq!(key(b"\x1B[25~", Profile::Xterm), Key::F(13));
+    assert_eq!(parse_with(b"\x1B[a", Profile::Xterm).0,
+               Some(Event::Unsupported(b"\x1B[a".to_vec())));
+    assert_eq!(parse_with(b"\x1B[2$", Profile::Xterm), (None, 0));
+
+    assert_eq!(key(b"\x1B[[A", Profile::Linux), Key::F(1));
+    assert_eq!(key(b"\x1B[[E", Profile::Linux), Key::F(5));
+    assert_eq!(key(b"\x1B[17~", Profile::Linux), Key::F(6));
+    assert_eq!(key(b"\x1B[1~", Profile::Linux), Key::Home);
+    assert_eq!(key(b"\x1B[4~", Profile::Linux), Key::End);
+    assert_eq!(parse_with(b"\x1B[[A", Profile::Xterm).0,
+               Some(Event::Unsupported(b"\x1B[[".to_vec())));
+
+    let mut parser = EventParser::new();
+    parser.set_profile(Profile::Linux);
+    parser.feed(b"\x1B[[");
+    assert_eq!(parser.next_event(), None);
+    parser.feed(b"B");
+    assert_eq!(parser.next_event(), Some(Event::Key(Key::F(2))));
+}
+
+#[cfg(test)]
+#[test]
+fn test_reports() {
+    let report = |buf: &[u8]| match parse(buf) {
+        (Some(Event::Report(report)), len) if len == buf.len() => report,
+        other => panic!("{:?} parsed as {:?}", buf, other),
+    };
+
+    assert_eq!(report(b"\x1B[24;80R"), Report::CursorPosition(80, 24));
+    assert_eq!(report(b"\x1B[1;1R"), Report::CursorPosition(1, 1));
+    assert_eq!(report(b"\x1B[1;17R"), Report::CursorPosition(17, 1));
+    assert_eq!(report(b"\x1B[?1;5R"), Report::CursorPosition(5, 1));
+    assert_eq!(report(b"\x1B[?3;4;1R"), Report::CursorPosition(4, 3));
+    assert_eq!(report(b"\x1B[?62;1;22c"), Report::PrimaryAttributes(vec![62, 1, 22]));
+    assert_eq!(report(b"\x1B[?6c"), Report::PrimaryAttributes(vec![6]));
+    assert_eq!(report(b"\x1B[>41;353;0c"), Report::SecondaryAttributes(vec![41, 353, 0]));
+    assert_eq!(report(b"\x1B[0n"), Report::Status(true));
+    assert_eq!(report(b"\x1B[3n"), Report::Status(false));
+    assert_eq!(report(b"\x1B[?25;1$y"), Report::PrivateMode(25, Some(true)));
+    assert_eq!(report(b"\x1B[?12;4$y"), Report::PrivateMode(12, Some(false)));
+    assert_eq!(report(b"\x1B[?9999;0$y"), Report::PrivateMode(9999, None));
+    assert_eq!(report(b"\x1B]4;1;rgb:cdcd/0000/0000\x07"),
+               Report::PaletteColor(1, Rgb(205, 0, 0)));
+    assert_eq!(report(b"\x1B]4;255;rgb:f/80/eee\x1B\\"),
+               Report::PaletteColor(255, Rgb(255, 128, 238)));
+    assert_eq!(report(b"\x1B]11;rgb:1e1e/1e1e/2e2e\x1B\\"), Report::Background(Rgb(30, 30, 46)));
+    assert_eq!(parse(b"\x1B]4;1;rgb:0/0/0"), (None, 0));
+    assert_eq!(parse(b"\x1B]4;1;?\x07"),
+               (Some(Event::Unsupported(b"\x1B]4;1;?\x07".to_vec())), 8));
+    assert_eq!(parse(b"\x1B]4;1\x1B[A"), (Some(Event::Unsupported(b"\x1B]4;1".to_vec())), 5));
+    assert_eq!(parse(b"\x1B]a"), (Some(Event::Key(Key::Alt(']'))), 2));
+
+    let ctrl = Modifiers { ctrl: true, ..Modifiers::default() };
+    assert_eq!(parse(b"\x1B[1;5R"),
+               (Some(Event::Key(Key::Modified(ctrl, ModifiedKey::F(3)))), 6));
+    assert_eq!(parse(b"\x1B[1;2;3R"),
+               (Some(Event::Unsupported(b"\x1B[1;2;3R".to_vec())), 8));
+    assert_eq!(parse(b"\x1B[?1;;2R"),
+               (Some(Event::Unsupported(b"\x1B[?1;;2R".to_vec())), 8));
+    assert_eq!(parse(b"\x1B[?62;1"), (None, 0));
+
+    assert_eq!(parse(b"\x1B[8;24;80t"), (Some(Event::Resize(80, 24)), 10));
+}
+
+#[cfg(test)]
+#[test]
+fn test_sanitize_paste() {
+    let text = "a\tb\r\nc\rd\x1B[1;31me\x1B]0;title\x07f\x1B]8;;url\x1B\\g\x1Bh\u{9B}i\x7F\x00j";
+    assert_eq!(sanitize_paste(text, Sanitize::Strip), "a\tb\nc\ndefgij");
+    assert_eq!(sanitize_paste(text, Sanitize::Escape),
+               "a\tb\nc\nd^[[1;31me^[]0;title^Gf^[]8;;url^[\\g^[hM-^[i^?^@j");
+    assert_eq!(sanitize_paste("ümlaut", Sanitize::Strip), "ümlaut");
+    assert_eq!(sanitize_paste("trailing\x1B[", Sanitize::Strip), "trailing");
+}
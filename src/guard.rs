@@ -0,0 +1,86 @@
+//! The writers enabling a terminal mode for as long as they are alive.
+
+use std::fmt;
+use std::io::{self, Write};
+use std::ops;
+
+/// A writer which enables a terminal mode on creation and disables it again when dropped.
+pub struct ModeGuard<W: Write> {
+    term: W,
+    /// The sequence disabling the mode.
+    exit: String,
+}
+
+impl<W: Write> ModeGuard<W> {
+    /// Write `enter` to `term` and flush it, panicking if that fails. `exit` is written when this
+    /// is dropped.
+    pub fn new<E: fmt::Display, X: fmt::Display>(mut term: W, enter: E, exit: X) -> ModeGuard<W> {
+        write!(term, "{}", enter).unwrap();
+        term.flush().unwrap();
+
+        ModeGuard {
+            term,
+            exit: exit.to_string(),
+        }
+    }
+}
+
+impl<W: Write> Drop for ModeGuard<W> {
+    fn drop(&mut self) {
+        let _ = self.term.write_all(self.exit.as_bytes());
+        let _ = self.term.flush();
+    }
+}
+
+impl<W: Write> ops::Deref for ModeGuard<W> {
+    type Target = W;
+
+    fn deref(&self) -> &W {
+        &self.term
+    }
+}
+
+impl<W: Write> ops::DerefMut for ModeGuard<W> {
+    fn deref_mut(&mut self) -> &mut W {
+        &mut self.term
+    }
+}
+
+impl<W: Write> Write for ModeGuard<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.term.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.term.flush()
+    }
+}
+
+/// Implement `Deref`, `DerefMut` and `Write` for a type wrapping a `ModeGuard` as `guard`.
+macro_rules! derive_mode_guard {
+    ($name:ident) => {
+        impl<W: ::std::io::Write> ::std::ops::Deref for $name<W> {
+            type Target = W;
+
+            fn deref(&self) -> &W {
+                &self.guard
+            }
+        }
+
+        impl<W: ::std::io::Write> ::std::ops::DerefMut for $name<W> {
+            fn deref_mut(&mut self) -> &mut W {
+                &mut self.guard
+            }
+        }
+
+        impl<W: ::std::io::Write> ::std::io::Write for $name<W> {
+            fn write(&mut self, buf: &[u8]) -> ::std::io::Result<usize> {
+                self.guard.write(buf)
+            }
+
+            fn flush(&mut self) -> ::std::io::Result<()> {
+                self.guard.flush()
+            }
+        }
+    };
+}
@@ -1,10 +1,25 @@
 //! User input.
 
+use std::collections::HashSet;
+use std::fmt;
 use std::io::{self, Read, Write};
-use std::ops;
+use std::mem;
+use std::str;
+use std::thread;
+use std::time::{Duration, Instant};
 
-use event::{self, Event, Key};
+use clear;
+use color::Rgb;
+use cursor;
+use event::{Event, EventParser, Key, ModifiedKey, Modifiers, MouseEvent, Profile, Report};
+use guard::ModeGuard;
 use raw::IntoRawMode;
+use signal::{self, Signal};
+use size::terminal_size;
+use tty;
+
+#[cfg(windows)]
+pub use console::{ConsoleEvents, console_events};
 
 /// An iterator over input keys.
 pub struct Keys<R> {
@@ -17,7 +32,7 @@ impl<R: Read> Iterator for Keys<R> {
     fn next(&mut self) -> Option<Result<Key, io::Error>> {
         loop {
             match self.iter.next() {
-                Some(Ok(Event::Key(k))) => return Some(Ok(k)),
+                Some(Ok(Event::Key(k))) | Some(Ok(Event::KeyRepeat(k))) => return Some(Ok(k)),
                 Some(Ok(_)) => continue,
                 e @ Some(Err(_)) => e,
                 None => return None,
@@ -29,84 +44,634 @@ impl<R: Read> Iterator for Keys<R> {
 /// An iterator over input events.
 pub struct Events<R> {
     source: R,
-    leftover: Option<u8>,
+    parser: EventParser,
+    /// When the last input was read.
+    received: Option<Instant>,
+    /// Events pushed back by `unread`, the next one last.
+    unread: Vec<Event>,
+    /// The translation applied to events before they are returned.
+    translate: Option<Box<dyn FnMut(Event) -> Option<Event>>>,
+    /// Whether to coalesce bursts of mouse motion.
+    coalesce_motion: bool,
+    /// An event parsed while coalescing mouse motion, to be returned next.
+    lookahead: Option<Event>,
+    /// The input consumed since the last event was returned, if recorded for `with_raw`.
+    raw: Option<Vec<u8>>,
+    /// The input `lookahead` was parsed from, if recorded.
+    lookahead_raw: Vec<u8>,
+    /// How many resizes had been received when the last one was reported.
+    resizes: usize,
+    /// The size last reported for a resize signal, which an `AsyncReader` also inserts into its
+    /// input, so it is dropped there.
+    echoed_resize: Option<(u16, u16)>,
+    /// How many of each of `SIGNAL_EVENTS` had been received when the last one was reported.
+    signals: [usize; 3],
+}
+
+/// The signals reported by `Events` before reading, along with their events.
+const SIGNAL_EVENTS: [(Signal, Event); 3] = [
+    (Signal::Suspend, Event::Suspend),
+    (Signal::Resume, Event::Resume),
+    (Signal::Interrupt, Event::Interrupt),
+];
+
+impl<R> Events<R> {
+    /// Wait up to `timeout` for the rest of an escape sequence after an ESC byte.
+    ///
+    /// By default, an ESC byte which ends a read is taken to be the Esc key, since escape
+    /// sequences are normally sent all at once. This can misread Alt-modified keys and escape
+    /// sequences which are split by slow links (such as SSH), in which case a timeout should be
+    /// used instead.
+    ///
+    /// When a read returns no input, it is retried until the timeout expires. Note that if the
+    /// reader blocks, the Esc key is only reported once more input arrives.
+    pub fn esc_timeout(self, timeout: Duration) -> Events<R> {
+        let mut parser = EventParser::with_esc_timeout(timeout);
+        parser.set_profile(self.parser.profile());
+        parser.feed(self.parser.pending());
+
+        Events {
+            source: self.source,
+            parser,
+            received: self.received,
+            unread: self.unread,
+            translate: self.translate,
+            coalesce_motion: self.coalesce_motion,
+            lookahead: self.lookahead,
+            raw: self.raw,
+            lookahead_raw: self.lookahead_raw,
+            resizes: self.resizes,
+            echoed_resize: self.echoed_resize,
+            signals: self.signals,
+        }
+    }
+
+    /// Report each event together with the time its input was received.
+    ///
+    /// The time is taken when the read completing the event returns, so when reading from an
+    /// `AsyncReader`, it is the time the input was polled.
+    pub fn timed(self) -> TimedEvents<R> {
+        TimedEvents { events: self }
+    }
+
+    /// Report each event together with the input it was parsed from.
+    ///
+    /// Every byte read is reported exactly once, so the input can be forwarded unmodified (e.g. to
+    /// a child PTY) while inspecting it. The bytes of events merged by `coalesce_motion` or dropped
+    /// by `translate` are reported with the next event returned, while events pushed back by
+    /// `unread` come without any bytes.
+    pub fn with_raw(mut self) -> RawEvents<R> {
+        self.raw = Some(Vec::new());
+        RawEvents { events: self }
+    }
+
+    /// Wait up to `timeout` for each event, yielding `None` if none arrives in time.
+    ///
+    /// This is meant for non-blocking readers such as `AsyncReader`, which are polled until the
+    /// timeout expires, so a loop can keep rendering at a fixed rate while waiting for input. A
+    /// blocking reader still blocks until input arrives.
+    pub fn with_timeout(self, timeout: Duration) -> TimeoutEvents<R> {
+        TimeoutEvents {
+            events: self,
+            timeout,
+        }
+    }
+
+    /// Push `event` back, so it is returned by the next call to `next`.
+    ///
+    /// This allows looking ahead, e.g. to check whether a key is followed by another key it forms
+    /// a chord with. Events pushed back are returned in the reverse order they were pushed in.
+    pub fn unread(&mut self, event: Event) {
+        self.unread.push(event);
+    }
+
+    /// Pass every event through `translate` before returning it.
+    ///
+    /// The closure may return a different event, e.g. to remap keys, or `None` to drop the event.
+    /// If a translation was installed before, `translate` is applied to its results. Events pushed
+    /// back by `unread` are not translated again.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use termion::event::{Event, Key};
+    /// use termion::input::TermRead;
+    ///
+    /// let mut events = b"ab".events().translate(|event| match event {
+    ///     Event::Key(Key::Char('a')) => None,
+    ///     Event::Key(Key::Char(c)) => Some(Event::Key(Key::Char(c.to_ascii_uppercase()))),
+    ///     event => Some(event),
+    /// });
+    /// assert_eq!(events.next().unwrap().unwrap(), Event::Key(Key::Char('B')));
+    /// assert!(events.next().is_none());
+    /// ```
+    pub fn translate<F>(mut self, mut translate: F) -> Events<R>
+        where F: FnMut(Event) -> Option<Event> + 'static
+    {
+        self.translate = Some(match self.translate.take() {
+            Some(mut first) => Box::new(move |event| first(event).and_then(&mut translate)),
+            None => Box::new(translate),
+        });
+        self
+    }
+
+    /// Coalesce bursts of mouse motion.
+    ///
+    /// Consecutive `MouseEvent::Move` or `MouseEvent::Hold` events (with the same modifiers) which
+    /// have already been read are reported as a single event at the latest position. This keeps
+    /// applications which are slow to handle the events from falling behind the mouse. Input
+    /// which has not been read yet is not waited for.
+    pub fn coalesce_motion(mut self) -> Events<R> {
+        self.coalesce_motion = true;
+        self
+    }
+
+    /// Parse the key sequences of the given terminal dialect.
+    ///
+    /// By default, xterm sequences are parsed.
+    pub fn profile(mut self, profile: Profile) -> Events<R> {
+        self.parser.set_profile(profile);
+        self
+    }
 }
 
 impl<R: Read> Iterator for Events<R> {
     type Item = Result<Event, io::Error>;
 
     fn next(&mut self) -> Option<Result<Event, io::Error>> {
-        let mut source = &mut self.source;
-
-        if let Some(c) = self.leftover {
-            // we have a leftover byte, use it
-            self.leftover = None;
-            return Some(parse_event(c, &mut source.bytes()));
-        }
-
-        // Here we read two bytes at a time. We need to distinguish between single ESC key presses,
-        // and escape sequences (which start with ESC or a x1B byte). The idea is that if this is
-        // an escape sequence, we will read multiple bytes (the first byte being ESC) but if this
-        // is a single ESC keypress, we will only read a single byte.
-        let mut buf = [0u8; 2];
-        let res = match source.read(&mut buf) {
-            Ok(0) => return None,
-            Ok(1) => {
-                match buf[0] {
-                    b'\x1B' => Ok(Event::Key(Key::Esc)),
-                    c => parse_event(c, &mut source.bytes()),
+        if let Some(event) = self.unread.pop() {
+            return Some(Ok(event));
+        }
+
+        loop {
+            let event = match self.read_event()? {
+                Ok(event) => event,
+                Err(e) => return Some(Err(e)),
+            };
+            let event = match self.translate {
+                Some(ref mut translate) => translate(event),
+                None => Some(event),
+            };
+            if let Some(event) = event {
+                return Some(Ok(event));
+            }
+        }
+    }
+}
+
+/// The time to wait for the terminal to respond to a query.
+const QUERY_TIMEOUT: Duration = Duration::from_secs(1);
+
+impl<R: Read> Events<R> {
+    /// Ask the terminal for the cursor position ((1,1)-based) on `writer`, and wait for its
+    /// response among the input events.
+    ///
+    /// The events read before the response are returned by `next` afterwards as usual, so the
+    /// position can be queried in the middle of an event loop without losing input, and without
+    /// the response turning up in the loop. See `cursor::position` for the requirements on the
+    /// terminal and the reader.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use std::io::stdout;
+    /// use termion::async_stdin;
+    /// use termion::input::TermRead;
+    /// use termion::raw::IntoRawMode;
+    ///
+    /// let mut stdout = stdout().into_raw_mode().unwrap();
+    /// let mut events = async_stdin().events();
+    /// let (x, y) = events.cursor_position(&mut stdout).unwrap();
+    /// ```
+    pub fn cursor_position<W: Write>(&mut self, writer: &mut W) -> io::Result<(u16, u16)> {
+        self.query(writer, csi!("6n"), "the cursor position", |events, event| match *event {
+            // The sequence is parsed here, since xterm's response in the first row can also be a
+            // modified F3 key.
+            Event::Report(Report::CursorPosition(..)) |
+            Event::Key(Key::Modified(_, ModifiedKey::F(3))) => {
+                parse_position(events.parser.consumed())
+            }
+            _ => None,
+        })
+    }
+
+    /// Ask the terminal for its primary device attributes (DA1) on `writer`, and wait for its
+    /// response among the input events, like `cursor_position`.
+    ///
+    /// These are the terminal's conformance level followed by the features it supports, see
+    /// `Report::PrimaryAttributes`. Practically all terminals respond to this.
+    pub fn primary_attributes<W: Write>(&mut self, writer: &mut W) -> io::Result<Vec<u16>> {
+        self.query(writer, csi!("c"), "its attributes", |_, event| match *event {
+            Event::Report(Report::PrimaryAttributes(ref attributes)) => Some(attributes.clone()),
+            _ => None,
+        })
+    }
+
+    /// Ask the terminal whether the private `mode` is set (DECRQM) on `writer`, and wait for its
+    /// response among the input events, like `cursor_position`.
+    ///
+    /// Returns `None` if the terminal doesn't recognize the mode. Terminals not supporting the
+    /// query at all don't respond, which gives an error of kind `TimedOut` with a non-blocking
+    /// reader.
+    pub fn private_mode<W: Write>(&mut self, writer: &mut W, mode: u16)
+                                  -> io::Result<Option<bool>> {
+        let request = format!(csi!("?{}$p"), mode);
+        self.query(writer, &request, "the mode", |_, event| match *event {
+            Event::Report(Report::PrivateMode(reported, set)) if reported == mode => Some(set),
+            _ => None,
+        })
+    }
+
+    /// Ask the terminal for the color of a palette entry (OSC 4) on `writer`, and wait for its
+    /// response among the input events, like `cursor_position`.
+    ///
+    /// Terminals not supporting the query don't respond, which gives an error of kind `TimedOut`
+    /// with a non-blocking reader.
+    pub fn palette_color<W: Write>(&mut self, writer: &mut W, index: u8) -> io::Result<Rgb> {
+        let request = format!("\x1B]4;{};?\x1B\\", index);
+        self.query(writer, &request, "the color", |_, event| match *event {
+            Event::Report(Report::PaletteColor(reported, rgb)) if reported == index => Some(rgb),
+            _ => None,
+        })
+    }
+
+    /// Ask the terminal for its default background color (OSC 11) on `writer`, and wait for its
+    /// response among the input events, like `palette_color`.
+    ///
+    /// See `color::query_theme` for telling dark and light backgrounds apart.
+    pub fn background_color<W: Write>(&mut self, writer: &mut W) -> io::Result<Rgb> {
+        self.query(writer, "\x1B]11;?\x1B\\", "its background", |_, event| match *event {
+            Event::Report(Report::Background(rgb)) => Some(rgb),
+            _ => None,
+        })
+    }
+
+    /// Write `request` and wait for the event `response` picks the answer from, holding back the
+    /// events read before it. `what` names the answer for the timeout error.
+    fn query<W, T, F>(&mut self, writer: &mut W, request: &str, what: &str, mut response: F)
+                      -> io::Result<T>
+        where W: Write,
+              F: FnMut(&Events<R>, &Event) -> Option<T>
+    {
+        writer.write_all(request.as_bytes())?;
+        writer.flush()?;
+
+        let deadline = Instant::now() + QUERY_TIMEOUT;
+        let mut held = Vec::new();
+        let result = loop {
+            match self.read_event() {
+                Some(Ok(event)) => {
+                    match response(self, &event) {
+                        Some(answer) => break Ok(answer),
+                        None => held.push(event),
+                    }
                 }
+                Some(Err(err)) => break Err(err),
+                None if Instant::now() >= deadline => {
+                    let msg = format!("The terminal did not report {}.", what);
+                    break Err(io::Error::new(io::ErrorKind::TimedOut, msg));
+                }
+                None => thread::sleep(Duration::from_millis(1)),
             }
-            Ok(2) => {
-                let mut option_iter = &mut Some(buf[1]).into_iter();
-                let result = {
-                    let mut iter = option_iter.map(|c| Ok(c)).chain(source.bytes());
-                    parse_event(buf[0], &mut iter)
-                };
-                // If the option_iter wasn't consumed, keep the byte for later.
-                self.leftover = option_iter.next();
-                result
+        };
+
+        // The events held back come after those already pushed back.
+        let held: Vec<Event> = match self.translate {
+            Some(ref mut translate) => held.into_iter().filter_map(&mut **translate).collect(),
+            None => held,
+        };
+        self.unread.splice(0..0, held.into_iter().rev());
+
+        result
+    }
+
+    /// Read the next event from the source.
+    fn read_event(&mut self) -> Option<Result<Event, io::Error>> {
+        if let Some(event) = self.lookahead.take() {
+            if let Some(ref mut raw) = self.raw {
+                raw.append(&mut self.lookahead_raw);
             }
-            Ok(_) => unreachable!(),
-            Err(e) => Err(e),
+            if !self.echoed(&event) {
+                return Some(Ok(self.coalesce(event)));
+            }
+        }
+
+        loop {
+            if let Some(event) = self.parser.next_event() {
+                self.record();
+                if self.echoed(&event) {
+                    continue;
+                }
+                return Some(Ok(self.coalesce(event)));
+            }
+
+            // Signals received while not reading (e.g. while drawing) are reported here.
+            if let Some(event) = self.signal().or_else(|| self.resize()) {
+                return Some(Ok(event));
+            }
+
+            // We need to distinguish between single ESC key presses, and escape sequences (which
+            // start with ESC or a x1B byte). Without a timeout, the idea is that an escape
+            // sequence is sent all at once, so if a read ends with an ESC byte, it is a single
+            // ESC keypress. Any other incomplete event is waiting for more input.
+            let waiting_for_esc = self.parser.pending() == b"\x1B";
+            if waiting_for_esc && self.parser.esc_timeout().is_none() {
+                return self.flush_pending().map(Ok);
+            }
+
+            // Read as much as is available, so bursts of mouse motion can be coalesced.
+            let mut buf = [0u8; 1024];
+            match self.source.read(&mut buf) {
+                // The parser reports the Esc key once the timeout expires.
+                Ok(0) if waiting_for_esc => thread::sleep(Duration::from_millis(1)),
+                // No input is available yet (e.g. from an `AsyncReader`), or the source ended. An
+                // incomplete sequence is held back until the rest arrives, or `flush_pending`.
+                Ok(0) => return None,
+                Ok(n) => {
+                    self.received = Some(Instant::now());
+                    self.parser.feed(&buf[..n]);
+                }
+                // Reads interrupted by a reported signal report it at the top of the loop.
+                Err(ref e) if e.kind() == io::ErrorKind::Interrupted && self.signaled() => {}
+                Err(e) => return Some(Err(e)),
+            }
+        }
+    }
+
+    /// The event of a signal received since it was last reported, if any.
+    fn signal(&mut self) -> Option<Event> {
+        for (seen, &(signal, ref event)) in self.signals.iter_mut().zip(SIGNAL_EVENTS.iter()) {
+            let received = signal::received(signal);
+            if received != *seen {
+                *seen = received;
+                return Some(event.clone());
+            }
+        }
+        None
+    }
+
+    /// Whether any signal has been received since it was last reported.
+    fn signaled(&self) -> bool {
+        signal::received(Signal::Resize) != self.resizes ||
+        self.signals.iter().zip(SIGNAL_EVENTS.iter())
+            .any(|(&seen, &(signal, _))| signal::received(signal) != seen)
+    }
+
+    /// The event of the terminal's size, if it was resized since the last resize was reported.
+    fn resize(&mut self) -> Option<Event> {
+        let resizes = signal::received(Signal::Resize);
+        if resizes == self.resizes {
+            return None;
+        }
+        self.resizes = resizes;
+
+        let (width, height) = terminal_size().ok()?;
+        self.echoed_resize = Some((width, height));
+        Some(Event::Resize(width, height))
+    }
+
+    /// Whether `event` is the resize an `AsyncReader` inserted into its input for a signal which
+    /// has already been reported. Resizes read before their signal is seen report it instead.
+    fn echoed(&mut self, event: &Event) -> bool {
+        match *event {
+            Event::Resize(width, height) => {
+                self.resizes = signal::received(Signal::Resize);
+                self.echoed_resize.take() == Some((width, height))
+            }
+            _ => false,
+        }
+    }
+
+    /// Replace the mouse motion `event` by the latest of the parsed events of the same kind
+    /// following it, if coalescing motion.
+    fn coalesce(&mut self, mut event: Event) -> Event {
+        let kind = match motion(&event) {
+            Some(kind) if self.coalesce_motion => kind,
+            _ => return event,
         };
 
-        Some(res)
+        while let Some(next) = self.parser.next_event() {
+            if motion(&next) != Some(kind) {
+                if self.raw.is_some() {
+                    self.lookahead_raw = self.parser.consumed().to_vec();
+                }
+                self.lookahead = Some(next);
+                break;
+            }
+            self.record();
+            event = next;
+        }
+        event
+    }
+
+    /// Get the event of the incomplete input held back, if any, e.g. once the source ended.
+    ///
+    /// Reads returning no input (as an `AsyncReader` does while waiting) leave incomplete
+    /// sequences in the parser, so they are completed by later reads. This reports them anyway,
+    /// the same way `EventParser::flush` does.
+    pub fn flush_pending(&mut self) -> Option<Event> {
+        let event = self.parser.flush();
+        self.record();
+        event
+    }
+
+    /// Record the input of the event the parser returned last, if recording.
+    fn record(&mut self) {
+        if let Some(ref mut raw) = self.raw {
+            raw.extend_from_slice(self.parser.consumed());
+        }
     }
 }
 
-fn parse_event<I>(item: u8, iter: &mut I) -> Result<Event, io::Error>
-    where I: Iterator<Item = Result<u8, io::Error>>
-{
-    let mut buf = vec![item];
-    let result = {
-        let mut iter = iter.inspect(|byte| if let &Ok(byte) = byte {
-            buf.push(byte);
-        });
-        event::parse_event(item, &mut iter)
+/// Parse a cursor position report, `ESC [ row ; column R`.
+fn parse_position(seq: &[u8]) -> Option<(u16, u16)> {
+    if !seq.starts_with(b"\x1B[") || !seq.ends_with(b"R") {
+        return None;
+    }
+
+    let mut params = str::from_utf8(&seq[2..seq.len() - 1]).ok()?.split(';');
+    let row = params.next()?.parse().ok()?;
+    let column = params.next()?.parse().ok()?;
+    if params.next().is_some() {
+        return None;
+    }
+    Some((column, row))
+}
+
+/// The modifiers of a mouse motion event, and whether a button is held.
+fn motion(event: &Event) -> Option<(Modifiers, bool)> {
+    let (modifiers, mouse) = match *event {
+        Event::Mouse(mouse) => (Modifiers::default(), mouse),
+        Event::ModifiedMouse(modifiers, mouse) => (modifiers, mouse),
+        _ => return None,
     };
-    result.or(Ok(Event::Unsupported(buf)))
+    match mouse {
+        MouseEvent::Move(..) => Some((modifiers, false)),
+        MouseEvent::Hold(..) => Some((modifiers, true)),
+        _ => None,
+    }
+}
+
+/// An iterator over input events and the time they were received, see `Events::timed`.
+pub struct TimedEvents<R> {
+    events: Events<R>,
+}
+
+impl<R: Read> Iterator for TimedEvents<R> {
+    type Item = Result<(Event, Instant), io::Error>;
+
+    fn next(&mut self) -> Option<Result<(Event, Instant), io::Error>> {
+        Some(match self.events.next()? {
+            Ok(event) => Ok((event, self.events.received.unwrap_or_else(Instant::now))),
+            Err(e) => Err(e),
+        })
+    }
+}
+
+/// An iterator over input events and the input they were parsed from, see `Events::with_raw`.
+pub struct RawEvents<R> {
+    events: Events<R>,
+}
+
+impl<R: Read> Iterator for RawEvents<R> {
+    type Item = Result<(Event, Vec<u8>), io::Error>;
+
+    fn next(&mut self) -> Option<Result<(Event, Vec<u8>), io::Error>> {
+        Some(match self.events.next()? {
+            Ok(event) => {
+                let raw = self.events.raw.as_mut().map(mem::take).unwrap_or_default();
+                Ok((event, raw))
+            }
+            Err(e) => Err(e),
+        })
+    }
 }
 
+/// An iterator over input events which gives up waiting after a timeout, see
+/// `Events::with_timeout`.
+pub struct TimeoutEvents<R> {
+    events: Events<R>,
+    timeout: Duration,
+}
+
+impl<R: Read> Iterator for TimeoutEvents<R> {
+    type Item = Result<Option<Event>, io::Error>;
+
+    fn next(&mut self) -> Option<Result<Option<Event>, io::Error>> {
+        let deadline = Instant::now() + self.timeout;
+        loop {
+            match self.events.next() {
+                Some(Ok(event)) => return Some(Ok(Some(event))),
+                Some(Err(e)) => return Some(Err(e)),
+                None => {
+                    let now = Instant::now();
+                    if now >= deadline {
+                        return Some(Ok(None));
+                    }
+                    thread::sleep((deadline - now).min(Duration::from_millis(1)));
+                }
+            }
+        }
+    }
+}
+
+/// How long to wait for the rest of an escape sequence in `poll_event`.
+const SEQUENCE_TIMEOUT: u64 = 50;
+
+/// Wait up to `timeout` for an input event from the terminal, without a background thread.
+///
+/// Returns `None` if no event arrives in time. This waits for input on the TTY device with
+/// `poll(2)` (or `WaitForSingleObject` on Windows), and then reads it byte by byte, so no input
+/// beyond the event is consumed. An ESC byte which is not followed by more input within a short
+/// time is reported as `Key::Esc`.
+///
+/// The terminal should be in raw mode, as input is otherwise only available once a line is
+/// complete.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use std::io::{stdout, Write};
+/// use std::time::Duration;
+/// use termion::input::poll_event;
+/// use termion::raw::IntoRawMode;
+///
+/// let mut stdout = stdout().into_raw_mode().unwrap();
+/// loop {
+///     match poll_event(Duration::from_millis(100)).unwrap() {
+///         Some(event) => write!(stdout, "{:?}\r\n", event).unwrap(),
+///         None => write!(stdout, "tick\r\n").unwrap(),
+///     }
+/// }
+/// ```
+#[cfg(not(any(windows, target_os = "redox")))]
+pub fn poll_event(timeout: Duration) -> io::Result<Option<Event>> {
+    let tty = tty::get_tty()?;
+    read_event_timeout(|timeout| tty::read_byte_timeout(&tty, timeout), timeout)
+}
+
+/// Wait up to `timeout` for an input event from the console, without a background thread.
+///
+/// Returns `None` if no event arrives in time. The console has to be in raw mode.
+#[cfg(windows)]
+pub fn poll_event(timeout: Duration) -> io::Result<Option<Event>> {
+    read_event_timeout(tty::read_byte_timeout, timeout)
+}
+
+/// Read an event through `read_byte`, waiting up to `timeout` for its first byte.
+#[cfg(not(target_os = "redox"))]
+fn read_event_timeout<F>(mut read_byte: F, timeout: Duration) -> io::Result<Option<Event>>
+    where F: FnMut(Duration) -> io::Result<Option<u8>>
+{
+    let mut parser = EventParser::new();
+    match read_byte(timeout)? {
+        Some(byte) => parser.feed(&[byte]),
+        None => return Ok(None),
+    }
+
+    loop {
+        if let Some(event) = parser.next_event() {
+            return Ok(Some(event));
+        }
+        match read_byte(Duration::from_millis(SEQUENCE_TIMEOUT))? {
+            Some(byte) => parser.feed(&[byte]),
+            // Report an incomplete sequence at once, as the parser does not outlive this call.
+            None if parser.pending() == b"\x1B" => return Ok(Some(Event::Key(Key::Esc))),
+            None => return Ok(Some(Event::Unsupported(parser.pending().to_vec()))),
+        }
+    }
+}
 
 /// Extension to `Read` trait.
 pub trait TermRead {
     /// An iterator over input events.
     fn events(self) -> Events<Self> where Self: Sized;
 
+    /// An iterator over input events, which yields `None` when no event arrives within `timeout`.
+    ///
+    /// See `Events::with_timeout`.
+    fn events_with_timeout(self, timeout: Duration) -> TimeoutEvents<Self> where Self: Sized;
+
+    /// An iterator over input events and the exact input each was parsed from.
+    ///
+    /// See `Events::with_raw`.
+    fn events_and_raw(self) -> RawEvents<Self> where Self: Sized;
+
     /// An iterator over key inputs.
     fn keys(self) -> Keys<Self> where Self: Sized;
 
     /// Read a line.
     ///
     /// EOT and ETX will abort the prompt, returning `None`. Newline or carriage return will
-    /// complete the input.
+    /// complete the input. Backspace (or Ctrl-H) deletes the last character, and Ctrl-U the whole
+    /// line, so this also works in raw mode, where the terminal does not handle these keys.
+    /// Other keys which do not produce text, such as the arrow keys, are ignored.
     fn read_line(&mut self) -> io::Result<Option<String>>;
 
     /// Read a password.
     ///
-    /// EOT and ETX will abort the prompt, returning `None`. Newline or carriage return will
-    /// complete the input.
+    /// The terminal is switched to raw mode while reading, so the password is not echoed. The
+    /// input is handled as by `read_line`.
     fn read_passwd<W: Write>(&mut self, writer: &mut W) -> io::Result<Option<String>> {
         let _raw = try!(writer.into_raw_mode());
         self.read_line()
@@ -117,34 +682,538 @@ impl<R: Read> TermRead for R {
     fn events(self) -> Events<Self> {
         Events {
             source: self,
-            leftover: None,
+            parser: EventParser::new(),
+            received: None,
+            unread: Vec::new(),
+            translate: None,
+            coalesce_motion: false,
+            lookahead: None,
+            raw: None,
+            lookahead_raw: Vec::new(),
+            resizes: signal::received(Signal::Resize),
+            echoed_resize: None,
+            signals: [
+                signal::received(Signal::Suspend),
+                signal::received(Signal::Resume),
+                signal::received(Signal::Interrupt),
+            ],
         }
     }
+    fn events_with_timeout(self, timeout: Duration) -> TimeoutEvents<Self> {
+        self.events().with_timeout(timeout)
+    }
+    fn events_and_raw(self) -> RawEvents<Self> {
+        self.events().with_raw()
+    }
     fn keys(self) -> Keys<Self> {
         Keys { iter: self.events() }
     }
 
     fn read_line(&mut self) -> io::Result<Option<String>> {
-        let mut buf = Vec::with_capacity(30);
-
-        for c in self.bytes() {
-            match c {
-                Err(e) => return Err(e),
-                Ok(0) | Ok(3) | Ok(4) => return Ok(None),
-                Ok(0x7f) => {
-                    buf.pop();
+        let mut line = String::with_capacity(30);
+        let mut parser = EventParser::new();
+        let mut byte = [0];
+
+        // Read byte by byte, so no input after the line is consumed.
+        while self.read(&mut byte)? == 1 {
+            parser.feed(&byte);
+
+            while let Some(event) = parser.next_event() {
+                // A stray ESC makes an Alt key of the next one, which is handled as the key alone
+                // (e.g. Enter still completes the line).
+                let event = match event {
+                    Event::Key(Key::Alt(c)) => {
+                        let mut buf = [0; 4];
+                        ::event::parse(c.encode_utf8(&mut buf).as_bytes()).0.unwrap_or(event)
+                    }
+                    event => event,
+                };
+                match event {
+                    Event::Key(Key::Char('\n')) => return Ok(Some(line)),
+                    Event::Key(Key::Null) |
+                    Event::Key(Key::Ctrl('c')) |
+                    Event::Key(Key::Ctrl('d')) => return Ok(None),
+                    Event::Key(Key::Backspace) | Event::Key(Key::Ctrl('h')) => {
+                        line.pop();
+                    }
+                    Event::Key(Key::Ctrl('u')) => line.clear(),
+                    Event::Key(Key::Char(c)) => line.push(c),
+                    // Escape sequences, such as arrow keys, are ignored.
+                    _ => (),
                 }
-                Ok(b'\n') | Ok(b'\r') => break,
-                Ok(c) => buf.push(c),
             }
         }
 
-        let string = try!(String::from_utf8(buf)
-            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e)));
-        Ok(Some(string))
+        Ok(Some(line))
+    }
+}
+
+/// A minimal line editor, reading a line from key events.
+///
+/// The terminal has to be in raw mode. The line is edited with the following keys:
+///
+/// - Left, Right, Home and End (or Ctrl-B, Ctrl-F, Ctrl-A and Ctrl-E) move the cursor, while
+///   Ctrl-Left and Ctrl-Right (or Alt-B and Alt-F) move it by words.
+/// - Backspace and Delete delete a character, Ctrl-W and Alt-D a word, and Ctrl-U and Ctrl-K
+///   everything before and after the cursor.
+/// - Up and Down (or Ctrl-P and Ctrl-N) go through the history.
+/// - Enter completes the line, while Ctrl-C aborts it, as does Ctrl-D on an empty line.
+///
+/// Lines longer than the terminal is wide are not supported.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use std::io::{stdin, stdout};
+/// use termion::input::{LineEditor, TermRead};
+/// use termion::raw::IntoRawMode;
+///
+/// let mut stdout = stdout().into_raw_mode().unwrap();
+/// let mut keys = stdin().keys();
+/// let mut editor = LineEditor::new();
+/// editor.set_prompt("> ");
+///
+/// while let Some(line) = editor.read_line(&mut keys, &mut stdout).unwrap() {
+///     if line == "quit" {
+///         break;
+///     }
+/// }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct LineEditor {
+    /// The prompt written before the line.
+    prompt: String,
+    /// The lines entered before, the latest last.
+    history: Vec<String>,
+}
+
+impl LineEditor {
+    /// Create a line editor without a prompt or history.
+    pub fn new() -> LineEditor {
+        LineEditor::default()
+    }
+
+    /// The prompt written before the line.
+    pub fn prompt(&self) -> &str {
+        &self.prompt
+    }
+
+    /// Set the prompt written before the line.
+    ///
+    /// This may contain styles and colors, but no cursor movement or newlines.
+    pub fn set_prompt(&mut self, prompt: &str) {
+        self.prompt = prompt.to_string();
+    }
+
+    /// The lines in the history, the latest last.
+    pub fn history(&self) -> &[String] {
+        &self.history
+    }
+
+    /// Add a line to the history, unless it is empty or the same as the latest line.
+    ///
+    /// Lines completed by `read_line` are added automatically.
+    pub fn add_history(&mut self, line: &str) {
+        if !line.is_empty() && self.history.last().map(|l| &**l) != Some(line) {
+            self.history.push(line.to_string());
+        }
+    }
+
+    /// Read a line from `keys`, displaying it on `out`.
+    ///
+    /// Returns `None` if the line is aborted, or if there are no more keys.
+    pub fn read_line<I, W>(&mut self, keys: &mut I, out: &mut W) -> io::Result<Option<String>>
+        where I: Iterator<Item = Result<Key, io::Error>>,
+              W: Write
+    {
+        let ctrl = Modifiers { ctrl: true, ..Modifiers::default() };
+        let mut line: Vec<char> = Vec::new();
+        let mut pos = 0;
+        // The position in the history, which is its length while editing a new line.
+        let mut index = self.history.len();
+        let mut new_line = Vec::new();
+
+        self.redraw(out, &line, pos)?;
+        loop {
+            let key = match keys.next() {
+                Some(key) => key?,
+                None => return Ok(None),
+            };
+
+            match key {
+                Key::Char('\n') => {
+                    write!(out, "\r\n")?;
+                    out.flush()?;
+                    let line: String = line.into_iter().collect();
+                    self.add_history(&line);
+                    return Ok(Some(line));
+                }
+                Key::Ctrl('c') | Key::Ctrl('d') if key == Key::Ctrl('c') || line.is_empty() => {
+                    write!(out, "\r\n")?;
+                    out.flush()?;
+                    return Ok(None);
+                }
+                Key::Char(c) => {
+                    line.insert(pos, c);
+                    pos += 1;
+                }
+                Key::Backspace | Key::Ctrl('h') if pos > 0 => {
+                    pos -= 1;
+                    line.remove(pos);
+                }
+                Key::Delete | Key::Ctrl('d') if pos < line.len() => {
+                    line.remove(pos);
+                }
+                Key::Left | Key::Ctrl('b') => pos = pos.saturating_sub(1),
+                Key::Right | Key::Ctrl('f') => pos = (pos + 1).min(line.len()),
+                Key::Home | Key::Ctrl('a') => pos = 0,
+                Key::End | Key::Ctrl('e') => pos = line.len(),
+                Key::Alt('b') => pos = word_start(&line, pos),
+                Key::Alt('f') => pos = word_end(&line, pos),
+                Key::Modified(m, ModifiedKey::Left) if m == ctrl => pos = word_start(&line, pos),
+                Key::Modified(m, ModifiedKey::Right) if m == ctrl => pos = word_end(&line, pos),
+                Key::Ctrl('w') => {
+                    let start = word_start(&line, pos);
+                    line.drain(start..pos);
+                    pos = start;
+                }
+                Key::Alt('d') => {
+                    let end = word_end(&line, pos);
+                    line.drain(pos..end);
+                }
+                Key::Ctrl('u') => {
+                    line.drain(..pos);
+                    pos = 0;
+                }
+                Key::Ctrl('k') => line.truncate(pos),
+                Key::Up | Key::Ctrl('p') if index > 0 => {
+                    if index == self.history.len() {
+                        new_line = line;
+                    }
+                    index -= 1;
+                    line = self.history[index].chars().collect();
+                    pos = line.len();
+                }
+                Key::Down | Key::Ctrl('n') if index < self.history.len() => {
+                    index += 1;
+                    line = match self.history.get(index) {
+                        Some(l) => l.chars().collect(),
+                        None => new_line.clone(),
+                    };
+                    pos = line.len();
+                }
+                _ => continue,
+            }
+
+            self.redraw(out, &line, pos)?;
+        }
+    }
+
+    /// Write the prompt and `line`, with the cursor at `pos`.
+    fn redraw<W: Write>(&self, out: &mut W, line: &[char], pos: usize) -> io::Result<()> {
+        let text: String = line.iter().collect();
+        write!(out, "\r{}{}{}", self.prompt, text, clear::UntilNewline)?;
+        if pos < line.len() {
+            write!(out, "{}", cursor::Left((line.len() - pos) as u16))?;
+        }
+        out.flush()
+    }
+}
+
+/// The start of the word before `pos` in `line`.
+fn word_start(line: &[char], mut pos: usize) -> usize {
+    while pos > 0 && line[pos - 1].is_whitespace() {
+        pos -= 1;
+    }
+    while pos > 0 && !line[pos - 1].is_whitespace() {
+        pos -= 1;
+    }
+    pos
+}
+
+/// The end of the word after `pos` in `line`.
+fn word_end(line: &[char], mut pos: usize) -> usize {
+    while pos < line.len() && line[pos].is_whitespace() {
+        pos += 1;
+    }
+    while pos < line.len() && !line[pos].is_whitespace() {
+        pos += 1;
+    }
+    pos
+}
+
+/// The result of feeding a key to `KeyChords`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ChordMatch<T> {
+    /// The keys so far make up the binding with this action.
+    Matched(T),
+    /// The keys so far are the beginning of a binding, and more are needed.
+    Pending,
+    /// The keys so far do not make up or start any binding.
+    ///
+    /// These are all the keys fed since the last match, including the current one, so the
+    /// application can handle them on their own.
+    Unmatched(Vec<Key>),
+    /// The pending chord timed out before the current key, so its keys (the first field) are
+    /// given up on. The current key starts a new chord, the result of which is the second field.
+    Expired(Vec<Key>, Box<ChordMatch<T>>),
+}
+
+/// A matcher for multi-key chords, such as Ctrl-X Ctrl-S.
+///
+/// Keys are fed one at a time, and matched against the registered bindings. Bindings which are
+/// the beginning of another binding shadow it.
+///
+/// # Example
+///
+/// ```rust
+/// use termion::event::Key;
+/// use termion::input::{ChordMatch, KeyChords};
+///
+/// let mut chords = KeyChords::new();
+/// chords.bind(&[Key::Ctrl('x'), Key::Ctrl('s')], "save");
+/// chords.bind(&[Key::Ctrl('x'), Key::Ctrl('c')], "quit");
+///
+/// assert_eq!(chords.feed(Key::Ctrl('x')), ChordMatch::Pending);
+/// assert_eq!(chords.feed(Key::Ctrl('s')), ChordMatch::Matched("save"));
+/// assert_eq!(chords.feed(Key::Ctrl('x')), ChordMatch::Pending);
+/// assert_eq!(chords.feed(Key::Char('a')),
+///            ChordMatch::Unmatched(vec![Key::Ctrl('x'), Key::Char('a')]));
+/// ```
+#[derive(Debug, Clone)]
+pub struct KeyChords<T> {
+    /// The registered key sequences and their actions.
+    bindings: Vec<(Vec<Key>, T)>,
+    /// The keys fed since the last match.
+    pending: Vec<Key>,
+    /// How long to wait for the next key of a chord.
+    timeout: Option<Duration>,
+    /// When the last key was fed.
+    last: Option<Instant>,
+}
+
+impl<T: Clone> KeyChords<T> {
+    /// Create a matcher without any bindings, which waits for the next key of a chord forever.
+    pub fn new() -> KeyChords<T> {
+        KeyChords {
+            bindings: Vec::new(),
+            pending: Vec::new(),
+            timeout: None,
+            last: None,
+        }
+    }
+
+    /// Create a matcher without any bindings, which gives up on a chord if its next key is not
+    /// fed within `timeout`, see `expire`.
+    pub fn with_timeout(timeout: Duration) -> KeyChords<T> {
+        KeyChords {
+            timeout: Some(timeout),
+            ..KeyChords::new()
+        }
+    }
+
+    /// Bind the key sequence `keys` to `action`, replacing any previous binding of them.
+    pub fn bind(&mut self, keys: &[Key], action: T) {
+        self.bindings.retain(|(k, _)| &k[..] != keys);
+        self.bindings.push((keys.to_vec(), action));
+    }
+
+    /// The keys fed since the last match, which start a binding.
+    pub fn pending(&self) -> &[Key] {
+        &self.pending
+    }
+
+    /// Feed the next key.
+    pub fn feed(&mut self, key: Key) -> ChordMatch<T> {
+        self.feed_at(key, Instant::now())
+    }
+
+    /// Give up on the pending chord if the timeout passed since its last key.
+    ///
+    /// Returns the keys of the chord, if any. Feeding a key does this first, too (see
+    /// `ChordMatch::Expired`), so this is only needed to give up without waiting for a key, e.g.
+    /// periodically if input can be polled without blocking.
+    pub fn expire(&mut self) -> Option<Vec<Key>> {
+        self.expire_at(Instant::now())
+    }
+
+    fn feed_at(&mut self, key: Key, now: Instant) -> ChordMatch<T> {
+        match self.expire_at(now) {
+            Some(expired) => ChordMatch::Expired(expired, Box::new(self.feed_fresh(key, now))),
+            None => self.feed_fresh(key, now),
+        }
+    }
+
+    /// Feed `key` to the pending chord, which has not timed out.
+    fn feed_fresh(&mut self, key: Key, now: Instant) -> ChordMatch<T> {
+        self.pending.push(key);
+        self.last = Some(now);
+
+        let pending = &self.pending;
+        if let Some((_, action)) = self.bindings.iter().find(|(k, _)| k == pending) {
+            let action = action.clone();
+            self.pending.clear();
+            return ChordMatch::Matched(action);
+        }
+
+        if self.bindings.iter().any(|(k, _)| k.starts_with(pending)) {
+            ChordMatch::Pending
+        } else {
+            ChordMatch::Unmatched(self.pending.drain(..).collect())
+        }
+    }
+
+    fn expire_at(&mut self, now: Instant) -> Option<Vec<Key>> {
+        match (self.timeout, self.last) {
+            (Some(timeout), Some(last)) if !self.pending.is_empty() && now - last >= timeout => {
+                Some(self.pending.drain(..).collect())
+            }
+            _ => None,
+        }
+    }
+}
+
+impl<T: Clone> Default for KeyChords<T> {
+    fn default() -> KeyChords<T> {
+        KeyChords::new()
+    }
+}
+
+/// The keys currently held down, for polling input the way games do.
+///
+/// This relies on key release events, so the kitty keyboard protocol has to be enabled with
+/// `EnableKittyKeyboard::REPORT_EVENT_TYPES`. Every event has to be passed to `track`.
+///
+/// Keys are tracked without their modifiers, and letters in lowercase, so a key released while
+/// holding other modifiers than it was pressed with is still released. Losing focus releases all
+/// keys, since releases aren't reported while the terminal is unfocused.
+///
+/// # Example
+///
+/// ```rust
+/// use termion::event::{Event, Key};
+/// use termion::input::KeyState;
+///
+/// let mut keys = KeyState::new();
+/// keys.track(&Event::Key(Key::Char('w')));
+/// keys.track(&Event::Key(Key::Ctrl('d')));
+/// assert!(keys.is_pressed(Key::Char('w')));
+/// assert!(keys.is_pressed(Key::Char('d')));
+///
+/// keys.track(&Event::KeyRelease(Key::Char('W')));
+/// assert!(!keys.is_pressed(Key::Char('w')));
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct KeyState {
+    held: HashSet<Key>,
+}
+
+impl KeyState {
+    /// Create a tracker with no keys held.
+    pub fn new() -> KeyState {
+        KeyState::default()
+    }
+
+    /// Update the held keys according to `event`.
+    pub fn track(&mut self, event: &Event) {
+        match *event {
+            Event::Key(key) | Event::KeyRepeat(key) => {
+                self.held.insert(unmodified(key));
+            }
+            Event::KeyRelease(key) => {
+                self.held.remove(&unmodified(key));
+            }
+            Event::FocusLost => self.held.clear(),
+            _ => {}
+        }
+    }
+
+    /// Whether `key` is held down, ignoring its modifiers.
+    pub fn is_pressed(&self, key: Key) -> bool {
+        self.held.contains(&unmodified(key))
+    }
+
+    /// Release all keys, e.g. after the terminal switched the keyboard protocol.
+    pub fn clear(&mut self) {
+        self.held.clear();
+    }
+}
+
+/// The key `key` without its modifiers, with letters in lowercase.
+fn unmodified(key: Key) -> Key {
+    match key {
+        Key::Char(c) | Key::Alt(c) | Key::Ctrl(c) |
+        Key::Modified(_, ModifiedKey::Char(c)) => Key::Char(c.to_lowercase().next().unwrap_or(c)),
+        Key::Modified(_, key) => key.into(),
+        key => key,
+    }
+}
+
+derive_csi_sequence!("Enable bracketed paste mode, reporting pasted text as `Event::Paste`.",
+                     EnableBracketedPaste,
+                     "?2004h");
+derive_csi_sequence!("Disable bracketed paste mode.", DisableBracketedPaste, "?2004l");
+derive_csi_sequence!("Enable focus reporting, reporting `Event::FocusGained` and \
+                      `Event::FocusLost`.",
+                     EnableFocusReporting,
+                     "?1004h");
+derive_csi_sequence!("Disable focus reporting.", DisableFocusReporting, "?1004l");
+
+/// Switch the keypad to application mode, reporting its keys as `Key::Keypad` and
+/// `Key::KeypadEnter`.
+#[derive(Copy, Clone)]
+pub struct EnableApplicationKeypad;
+
+impl fmt::Display for EnableApplicationKeypad {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "\x1B=")
+    }
+}
+
+/// Switch the keypad back to numeric mode.
+#[derive(Copy, Clone)]
+pub struct DisableApplicationKeypad;
+
+impl fmt::Display for DisableApplicationKeypad {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "\x1B>")
+    }
+}
+
+/// Enable the kitty keyboard protocol with the given enhancement flags.
+///
+/// The flags are a combination of the `EnableKittyKeyboard::*` constants. The previous keyboard
+/// mode is restored by `DisableKittyKeyboard`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct EnableKittyKeyboard(pub u8);
+
+impl EnableKittyKeyboard {
+    /// Report keys which are ambiguous in legacy mode (such as Esc) with unambiguous sequences.
+    pub const DISAMBIGUATE: u8 = 0b1;
+    /// Report key repeat and key release events (`Event::KeyRepeat` and `Event::KeyRelease`).
+    pub const REPORT_EVENT_TYPES: u8 = 0b10;
+    /// Report the shifted version of keys as alternates.
+    pub const REPORT_ALTERNATE_KEYS: u8 = 0b100;
+    /// Report all keys, including text keys, as escape sequences.
+    pub const REPORT_ALL_KEYS: u8 = 0b1000;
+}
+
+impl fmt::Display for EnableKittyKeyboard {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, csi!(">{}u"), self.0)
     }
 }
 
+derive_csi_sequence!("Disable the kitty keyboard protocol, restoring the previous keyboard mode.",
+                     DisableKittyKeyboard,
+                     "<u");
+
+derive_csi_sequence!("Enable xterm's modifyOtherKeys mode, reporting keys such as Ctrl-Shift-P or \
+                      Ctrl-, as `Key::Modified` or `Key::Ctrl` instead of plain control bytes.",
+                     EnableModifyOtherKeys,
+                     ">4;2m");
+derive_csi_sequence!("Disable xterm's modifyOtherKeys mode.", DisableModifyOtherKeys, ">4;0m");
+
 /// A sequence of escape codes to enable terminal mouse support.
 const ENTER_MOUSE_SEQUENCE: &'static str = csi!("?1000h\x1b[?1002h\x1b[?1015h\x1b[?1006h");
 
@@ -153,54 +1222,53 @@ const EXIT_MOUSE_SEQUENCE: &'static str = csi!("?1006l\x1b[?1015l\x1b[?1002l\x1b
 
 /// A terminal with added mouse support.
 ///
+/// Mouse reporting is enabled on creation and disabled again when this is dropped, so the terminal
+/// is left as it was found.
+///
+/// This enables the SGR and urxvt extended encodings, so coordinates beyond column 223 are
+/// reported correctly by terminals supporting either of them.
+///
 /// This can be obtained through the `From` implementations.
 pub struct MouseTerminal<W: Write> {
-    term: W,
+    guard: ModeGuard<W>,
 }
 
 impl<W: Write> From<W> for MouseTerminal<W> {
-    fn from(mut from: W) -> MouseTerminal<W> {
-        from.write_all(ENTER_MOUSE_SEQUENCE.as_bytes()).unwrap();
-
-        MouseTerminal { term: from }
+    fn from(from: W) -> MouseTerminal<W> {
+        MouseTerminal { guard: ModeGuard::new(from, ENTER_MOUSE_SEQUENCE, EXIT_MOUSE_SEQUENCE) }
     }
 }
 
-impl<W: Write> Drop for MouseTerminal<W> {
-    fn drop(&mut self) {
-        self.term.write_all(EXIT_MOUSE_SEQUENCE.as_bytes()).unwrap();
-    }
-}
+derive_mode_guard!(MouseTerminal);
 
-impl<W: Write> ops::Deref for MouseTerminal<W> {
-    type Target = W;
+/// A sequence of escape codes to enable terminal mouse support, including any-motion tracking.
+const ENTER_MOUSE_MOTION_SEQUENCE: &str = csi!("?1000h\x1b[?1003h\x1b[?1015h\x1b[?1006h");
 
-    fn deref(&self) -> &W {
-        &self.term
-    }
-}
+/// A sequence of escape codes to disable terminal mouse support, including any-motion tracking.
+const EXIT_MOUSE_MOTION_SEQUENCE: &str = csi!("?1006l\x1b[?1015l\x1b[?1003l\x1b[?1000l");
 
-impl<W: Write> ops::DerefMut for MouseTerminal<W> {
-    fn deref_mut(&mut self) -> &mut W {
-        &mut self.term
-    }
+/// A terminal with added mouse support, which also reports mouse motion while no button is held
+/// (as `MouseEvent::Move`).
+///
+/// This can be obtained through the `From` implementations.
+pub struct MouseMotionTerminal<W: Write> {
+    guard: ModeGuard<W>,
 }
 
-impl<W: Write> Write for MouseTerminal<W> {
-    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
-        self.term.write(buf)
-    }
-
-    fn flush(&mut self) -> io::Result<()> {
-        self.term.flush()
+impl<W: Write> From<W> for MouseMotionTerminal<W> {
+    fn from(from: W) -> MouseMotionTerminal<W> {
+        let guard = ModeGuard::new(from, ENTER_MOUSE_MOTION_SEQUENCE, EXIT_MOUSE_MOTION_SEQUENCE);
+        MouseMotionTerminal { guard }
     }
 }
 
+derive_mode_guard!(MouseMotionTerminal);
+
 #[cfg(test)]
 mod test {
     use super::*;
     use std::io;
-    use event::{Key, Event, MouseEvent, MouseButton};
+    use event::{Key, Event, ModifiedKey, Modifiers, MouseEvent, MouseButton};
 
     #[test]
     fn test_keys() {
@@ -240,6 +1308,217 @@ mod test {
         assert!(i.next().is_none());
     }
 
+    #[test]
+    fn test_mouse_drag() {
+        let mut i = b"\x1B[M\x20\x22\x24\x1B[M\x40\x23\x24\x1B[M\x23\x23\x24\
+                    \x1B[<2;2;4M\x1B[<34;3;4M\x1B[<2;3;4m"
+            .events();
+
+        assert_eq!(i.next().unwrap().unwrap(),
+                   Event::Mouse(MouseEvent::Press(MouseButton::Left, 2, 4)));
+        assert_eq!(i.next().unwrap().unwrap(),
+                   Event::Mouse(MouseEvent::Hold(3, 4)));
+        assert_eq!(i.next().unwrap().unwrap(),
+                   Event::Mouse(MouseEvent::Release(3, 4)));
+        assert_eq!(i.next().unwrap().unwrap(),
+                   Event::Mouse(MouseEvent::Press(MouseButton::Right, 2, 4)));
+        assert_eq!(i.next().unwrap().unwrap(),
+                   Event::Mouse(MouseEvent::Hold(3, 4)));
+        assert_eq!(i.next().unwrap().unwrap(),
+                   Event::Mouse(MouseEvent::Release(3, 4)));
+        assert!(i.next().is_none());
+    }
+
+    #[test]
+    fn test_mouse_move() {
+        let mut i = b"\x1B[M\x43\x22\x24\x1B[<35;3;4M\x1B[67;4;4M".events();
+
+        assert_eq!(i.next().unwrap().unwrap(), Event::Mouse(MouseEvent::Move(2, 4)));
+        assert_eq!(i.next().unwrap().unwrap(), Event::Mouse(MouseEvent::Move(3, 4)));
+        assert_eq!(i.next().unwrap().unwrap(), Event::Mouse(MouseEvent::Move(4, 4)));
+        assert!(i.next().is_none());
+    }
+
+    /// A writer recording its output, which can be made to fail.
+    #[derive(Default)]
+    struct Recorder {
+        out: Vec<u8>,
+        flushed: usize,
+        broken: bool,
+    }
+
+    impl Write for Recorder {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            if self.broken {
+                return Err(io::Error::new(io::ErrorKind::BrokenPipe, "broken"));
+            }
+            self.out.write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            self.flushed = self.out.len();
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_mouse_terminal() {
+        let mut rec = Recorder::default();
+        {
+            let mut term = MouseTerminal::from(&mut rec);
+            assert_eq!(term.flushed, ENTER_MOUSE_SEQUENCE.len());
+            term.write_all(b"x").unwrap();
+        }
+
+        let expected = [ENTER_MOUSE_SEQUENCE, "x", EXIT_MOUSE_SEQUENCE].concat();
+        assert_eq!(&rec.out[..], expected.as_bytes());
+        assert_eq!(rec.flushed, expected.len());
+
+        // Failing to restore the terminal does not panic.
+        let mut term = MouseTerminal::from(Recorder::default());
+        term.broken = true;
+    }
+
+    #[test]
+    fn test_mouse_motion_terminal() {
+        let mut out = Vec::new();
+        {
+            let mut term = MouseMotionTerminal::from(&mut out);
+            term.write_all(b"x").unwrap();
+        }
+
+        let expected = [ENTER_MOUSE_MOTION_SEQUENCE, "x", EXIT_MOUSE_MOTION_SEQUENCE].concat();
+        assert_eq!(&out[..], expected.as_bytes());
+    }
+
+    #[test]
+    fn test_mouse_large_coordinates() {
+        let mut i = b"\x1B[<0;300;4M\x1B[<0;1000;500m\x1B[32;300;400M\x1B[M\x20\xFF\x21".events();
+
+        assert_eq!(i.next().unwrap().unwrap(),
+                   Event::Mouse(MouseEvent::Press(MouseButton::Left, 300, 4)));
+        assert_eq!(i.next().unwrap().unwrap(),
+                   Event::Mouse(MouseEvent::Release(1000, 500)));
+        assert_eq!(i.next().unwrap().unwrap(),
+                   Event::Mouse(MouseEvent::Press(MouseButton::Left, 300, 400)));
+        assert_eq!(i.next().unwrap().unwrap(),
+                   Event::Mouse(MouseEvent::Press(MouseButton::Left, 223, 1)));
+        assert!(i.next().is_none());
+    }
+
+    #[test]
+    fn test_mouse_wheel() {
+        let mut i = b"\x1B[M\x60\x22\x24\x1B[M\x61\x22\x24\x1B[M\x62\x22\x24\x1B[M\x63\x22\x24\
+                    \x1B[<64;2;4M\x1B[<65;2;4M\x1B[<66;2;4M\x1B[<67;2;4M\
+                    \x1B[96;2;4M\x1B[97;2;4M\x1B[98;2;4M\x1B[99;2;4M"
+            .events();
+
+        for _ in 0..3 {
+            for &button in &[MouseButton::WheelUp,
+                             MouseButton::WheelDown,
+                             MouseButton::WheelLeft,
+                             MouseButton::WheelRight] {
+                assert_eq!(i.next().unwrap().unwrap(),
+                           Event::Mouse(MouseEvent::Press(button, 2, 4)));
+            }
+        }
+        assert!(i.next().is_none());
+    }
+
+    #[test]
+    fn test_mouse_modifiers() {
+        use event::Modifiers;
+
+        let ctrl = Modifiers { ctrl: true, ..Modifiers::default() };
+        let shift = Modifiers { shift: true, ..Modifiers::default() };
+        let alt_shift = Modifiers { alt: true, shift: true, ..Modifiers::default() };
+
+        let mut i = b"\x1B[M\x30\x22\x24\x1B[<16;2;4M\x1B[<36;3;4M\x1B[<12;3;4m\x1B[40;2;4M"
+            .events();
+        assert_eq!(i.next().unwrap().unwrap(),
+                   Event::ModifiedMouse(ctrl, MouseEvent::Press(MouseButton::Left, 2, 4)));
+        assert_eq!(i.next().unwrap().unwrap(),
+                   Event::ModifiedMouse(ctrl, MouseEvent::Press(MouseButton::Left, 2, 4)));
+        assert_eq!(i.next().unwrap().unwrap(),
+                   Event::ModifiedMouse(shift, MouseEvent::Hold(3, 4)));
+        assert_eq!(i.next().unwrap().unwrap(),
+                   Event::ModifiedMouse(alt_shift, MouseEvent::Release(3, 4)));
+        assert_eq!(i.next().unwrap().unwrap(),
+                   Event::ModifiedMouse(Modifiers { alt: true, ..Modifiers::default() },
+                                        MouseEvent::Press(MouseButton::Left, 2, 4)));
+        assert!(i.next().is_none());
+    }
+
+    #[test]
+    fn test_paste() {
+        let mut i = b"a\x1B[200~p\x1B[Dst\xC3\xA9\n\x1B[201~b".events();
+
+        assert_eq!(i.next().unwrap().unwrap(), Event::Key(Key::Char('a')));
+        assert_eq!(i.next().unwrap().unwrap(),
+                   Event::Paste("p\x1B[Dst\u{e9}\n".to_string()));
+        assert_eq!(i.next().unwrap().unwrap(), Event::Key(Key::Char('b')));
+        assert!(i.next().is_none());
+    }
+
+    #[test]
+    fn test_focus() {
+        let mut i = b"\x1B[Oa\x1B[I".events();
+
+        assert_eq!(i.next().unwrap().unwrap(), Event::FocusLost);
+        assert_eq!(i.next().unwrap().unwrap(), Event::Key(Key::Char('a')));
+        assert_eq!(i.next().unwrap().unwrap(), Event::FocusGained);
+        assert!(i.next().is_none());
+    }
+
+    /// A reader returning its input in the given chunks.
+    struct Chunks(Vec<&'static [u8]>);
+
+    impl Read for Chunks {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            if self.0.is_empty() {
+                return Ok(0);
+            }
+            let chunk = self.0.remove(0);
+            buf[..chunk.len()].copy_from_slice(chunk);
+            Ok(chunk.len())
+        }
+    }
+
+    #[test]
+    fn test_split_reads() {
+        let mut st = Chunks(vec![b"a\x1B[1;", b"5Bb\xC3", b"\xA9\x1B", b"\x1B[", b"D"]).keys();
+
+        assert_eq!(st.next().unwrap().unwrap(), Key::Char('a'));
+        assert_eq!(st.next().unwrap().unwrap(),
+                   Key::Modified(::event::Modifiers { ctrl: true, ..Default::default() },
+                                 ::event::ModifiedKey::Down));
+        assert_eq!(st.next().unwrap().unwrap(), Key::Char('b'));
+        assert_eq!(st.next().unwrap().unwrap(), Key::Char('é'));
+        assert_eq!(st.next().unwrap().unwrap(), Key::Esc);
+        assert_eq!(st.next().unwrap().unwrap(), Key::Left);
+        assert!(st.next().is_none());
+    }
+
+    #[test]
+    fn test_esc_timeout() {
+        use std::time::Duration;
+
+        let mut st = Chunks(vec![b"\x1B", b"[A\x1B", b"b"]).keys();
+        assert_eq!(st.next().unwrap().unwrap(), Key::Esc);
+        assert_eq!(st.next().unwrap().unwrap(), Key::Char('['));
+        assert_eq!(st.next().unwrap().unwrap(), Key::Char('A'));
+
+        let mut st = Chunks(vec![b"\x1B", b"[A\x1B", b"b"])
+            .events()
+            .esc_timeout(Duration::from_secs(60));
+        assert_eq!(st.next().unwrap().unwrap(), Event::Key(Key::Up));
+        assert_eq!(st.next().unwrap().unwrap(), Event::Key(Key::Alt('b')));
+
+        let mut st = b"\x1B".events().esc_timeout(Duration::from_millis(1));
+        assert_eq!(st.next().unwrap().unwrap(), Event::Key(Key::Esc));
+        assert!(st.next().is_none());
+    }
+
     #[test]
     fn test_function_keys() {
         let mut st = b"\x1BOP\x1BOQ\x1BOR\x1BOS".keys();
@@ -255,6 +1534,32 @@ mod test {
         }
     }
 
+    #[test]
+    fn test_extended_function_keys() {
+        let mut st = b"\x1B[25~\x1B[26~\x1B[28~\x1B[29~\x1B[31~\x1B[32~\x1B[33~\x1B[34~".keys();
+        for i in 13..21 {
+            assert_eq!(st.next().unwrap().unwrap(), Key::F(i));
+        }
+
+        let mut st = b"\x1B[57383u\x1B[57387u".keys();
+        assert_eq!(st.next().unwrap().unwrap(), Key::F(20));
+        assert_eq!(st.next().unwrap().unwrap(), Key::F(24));
+        assert!(st.next().is_none());
+    }
+
+    #[test]
+    fn test_keypad_keys() {
+        let mut st = b"\x1BOM\x1BOp\x1BOy\x1BOk\x1BOn\x1B[57414u\x1B[57401u".keys();
+        assert_eq!(st.next().unwrap().unwrap(), Key::KeypadEnter);
+        assert_eq!(st.next().unwrap().unwrap(), Key::Keypad('0'));
+        assert_eq!(st.next().unwrap().unwrap(), Key::Keypad('9'));
+        assert_eq!(st.next().unwrap().unwrap(), Key::Keypad('+'));
+        assert_eq!(st.next().unwrap().unwrap(), Key::Keypad('.'));
+        assert_eq!(st.next().unwrap().unwrap(), Key::KeypadEnter);
+        assert_eq!(st.next().unwrap().unwrap(), Key::Keypad('2'));
+        assert!(st.next().is_none());
+    }
+
     #[test]
     fn test_special_keys() {
         let mut st = b"\x1B[2~\x1B[H\x1B[7~\x1B[5~\x1B[3~\x1B[F\x1B[8~\x1B[6~".keys();
@@ -269,6 +1574,332 @@ mod test {
         assert!(st.next().is_none());
     }
 
+    #[test]
+    fn test_modified_keys() {
+        use event::{Modifiers, ModifiedKey};
+
+        let ctrl = Modifiers { ctrl: true, ..Modifiers::default() };
+        let shift = Modifiers { shift: true, ..Modifiers::default() };
+        let alt_shift = Modifiers { alt: true, shift: true, ..Modifiers::default() };
+
+        let mut st = b"\x1B[1;5A\x1B[1;2D\x1B[1;4H\x1B[3;5~\x1B[6;2~\x1B[1;5Q\x1B[15;4~\x1B[1;1C"
+            .keys();
+        assert_eq!(st.next().unwrap().unwrap(), Key::Modified(ctrl, ModifiedKey::Up));
+        assert_eq!(st.next().unwrap().unwrap(), Key::Modified(shift, ModifiedKey::Left));
+        assert_eq!(st.next().unwrap().unwrap(), Key::Modified(alt_shift, ModifiedKey::Home));
+        assert_eq!(st.next().unwrap().unwrap(), Key::Modified(ctrl, ModifiedKey::Delete));
+        assert_eq!(st.next().unwrap().unwrap(), Key::Modified(shift, ModifiedKey::PageDown));
+        assert_eq!(st.next().unwrap().unwrap(), Key::Modified(ctrl, ModifiedKey::F(2)));
+        assert_eq!(st.next().unwrap().unwrap(), Key::Modified(alt_shift, ModifiedKey::F(5)));
+        assert_eq!(st.next().unwrap().unwrap(), Key::Right);
+        assert!(st.next().is_none());
+    }
+
+    #[test]
+    fn test_kitty_keys() {
+        use event::{Modifiers, ModifiedKey};
+
+        let ctrl_alt = Modifiers { ctrl: true, alt: true, ..Modifiers::default() };
+        let ctrl = Modifiers { ctrl: true, ..Modifiers::default() };
+
+        let mut i = b"\x1B[27u\x1B[97;5u\x1B[97;7u\x1B[49:33;2u\x1B[97;1:2u\x1B[97;1:3u\
+                      \x1B[1;5:3A\x1B[13;5u\x1B[57358u"
+            .events();
+        assert_eq!(i.next().unwrap().unwrap(), Event::Key(Key::Esc));
+        assert_eq!(i.next().unwrap().unwrap(), Event::Key(Key::Ctrl('a')));
+        assert_eq!(i.next().unwrap().unwrap(),
+                   Event::Key(Key::Modified(ctrl_alt, ModifiedKey::Char('a'))));
+        assert_eq!(i.next().unwrap().unwrap(), Event::Key(Key::Char('!')));
+        assert_eq!(i.next().unwrap().unwrap(), Event::KeyRepeat(Key::Char('a')));
+        assert_eq!(i.next().unwrap().unwrap(), Event::KeyRelease(Key::Char('a')));
+        assert_eq!(i.next().unwrap().unwrap(),
+                   Event::KeyRelease(Key::Modified(ctrl, ModifiedKey::Up)));
+        assert_eq!(i.next().unwrap().unwrap(),
+                   Event::Key(Key::Modified(ctrl, ModifiedKey::Char('\n'))));
+        assert_eq!(i.next().unwrap().unwrap(),
+                   Event::Unsupported(b"\x1B[57358u".to_vec()));
+        assert!(i.next().is_none());
+    }
+
+    #[test]
+    fn test_modify_other_keys() {
+        use event::{Modifiers, ModifiedKey};
+
+        let ctrl_shift = Modifiers { ctrl: true, shift: true, ..Modifiers::default() };
+        let ctrl = Modifiers { ctrl: true, ..Modifiers::default() };
+
+        let mut i = b"\x1B[27;6;80~\x1B[27;5;44~\x1B[27;2;65~\x1B[27;3;13~\x1B[27;5;9~"
+            .events();
+        assert_eq!(i.next().unwrap().unwrap(),
+                   Event::Key(Key::Modified(ctrl_shift, ModifiedKey::Char('p'))));
+        assert_eq!(i.next().unwrap().unwrap(), Event::Key(Key::Ctrl(',')));
+        assert_eq!(i.next().unwrap().unwrap(), Event::Key(Key::Char('A')));
+        assert_eq!(i.next().unwrap().unwrap(), Event::Key(Key::Alt('\n')));
+        assert_eq!(i.next().unwrap().unwrap(),
+                   Event::Key(Key::Modified(ctrl, ModifiedKey::Char('\t'))));
+        assert!(i.next().is_none());
+
+        assert_eq!(format!("{}{}", EnableModifyOtherKeys, DisableModifyOtherKeys),
+                   "\x1B[>4;2m\x1B[>4;0m");
+    }
+
+    #[test]
+    fn test_resize_between_reads() {
+        let size = match terminal_size() {
+            Ok(size) => size,
+            // The size can't be reported without a terminal.
+            Err(_) => return,
+        };
+
+        let mut i = b"a\x1B[8;24;80t".events();
+        // As if a resize was received while not reading.
+        i.resizes = i.resizes.wrapping_sub(1);
+        assert_eq!(i.next().unwrap().unwrap(), Event::Resize(size.0, size.1));
+        assert_eq!(i.next().unwrap().unwrap(), Event::Key(Key::Char('a')));
+        // A resize which is not the one just reported is passed on.
+        if size != (80, 24) {
+            assert_eq!(i.next().unwrap().unwrap(), Event::Resize(80, 24));
+        }
+        assert!(i.next().is_none());
+
+        // The resize inserted into an `AsyncReader`'s input is only reported once.
+        let mut echo = Vec::new();
+        Event::Resize(size.0, size.1).write_to(&mut echo).unwrap();
+        let mut i = (&echo[..]).events();
+        i.resizes = i.resizes.wrapping_sub(1);
+        assert_eq!(i.next().unwrap().unwrap(), Event::Resize(size.0, size.1));
+        assert!(i.next().is_none());
+    }
+
+    #[test]
+    fn test_empty_read_mid_sequence() {
+        let mut i = Chunks(vec![b"\x1B[1;5", b"", b"A", b"\xC3"]).events();
+        assert!(i.next().is_none());
+        let ctrl = Modifiers { ctrl: true, ..Modifiers::default() };
+        assert_eq!(i.next().unwrap().unwrap(), Event::Key(Key::Modified(ctrl, ModifiedKey::Up)));
+        assert!(i.next().is_none());
+        assert!(i.next().is_none());
+        assert_eq!(i.flush_pending(), Some(Event::Unsupported(b"\xC3".to_vec())));
+        assert!(i.flush_pending().is_none());
+    }
+
+    #[test]
+    fn test_timed_events() {
+        use std::time::Instant;
+
+        let before = Instant::now();
+        let mut i = Chunks(vec![b"a\x1B[", b"A"]).events().timed();
+        let (event, first) = i.next().unwrap().unwrap();
+        assert_eq!(event, Event::Key(Key::Char('a')));
+        let (event, second) = i.next().unwrap().unwrap();
+        assert_eq!(event, Event::Key(Key::Up));
+        assert!(i.next().is_none());
+        assert!(before <= first && first <= second && second <= Instant::now());
+    }
+
+    #[test]
+    fn test_unread() {
+        let mut i = b"ab".events();
+        let a = i.next().unwrap().unwrap();
+        let b = i.next().unwrap().unwrap();
+        i.unread(b.clone());
+        i.unread(a.clone());
+        assert_eq!(i.next().unwrap().unwrap(), a);
+        i.unread(Event::FocusGained);
+        assert_eq!(i.next().unwrap().unwrap(), Event::FocusGained);
+        assert_eq!(i.next().unwrap().unwrap(), b);
+        assert!(i.next().is_none());
+    }
+
+    #[test]
+    fn test_events_and_raw() {
+        let mut i = b"a\x1B[<35;1;1M\x1B[<35;2;1M\x1B[Ab\xE2c".events()
+            .coalesce_motion()
+            .translate(|event| match event {
+                Event::Key(Key::Up) => None,
+                event => Some(event),
+            })
+            .with_raw();
+        let mut next = || i.next().unwrap().unwrap();
+        assert_eq!(next(), (Event::Key(Key::Char('a')), b"a".to_vec()));
+        assert_eq!(next(),
+                   (Event::Mouse(MouseEvent::Move(2, 1)), b"\x1B[<35;1;1M\x1B[<35;2;1M".to_vec()));
+        // The bytes of the dropped Up key come along with the next event.
+        assert_eq!(next(), (Event::Key(Key::Char('b')), b"\x1B[Ab".to_vec()));
+        assert_eq!(next(), (Event::Unsupported(b"\xE2c".to_vec()), b"\xE2c".to_vec()));
+        assert!(i.next().is_none());
+    }
+
+    #[test]
+    fn test_cursor_position() {
+        let mut out = Vec::new();
+        let mut events = b"ab\x1B[1;5Rc".events();
+        events.unread(Event::Key(Key::Esc));
+        assert_eq!(events.cursor_position(&mut out).unwrap(), (5, 1));
+        assert_eq!(out, b"\x1B[6n");
+
+        let rest: Vec<_> = events.map(Result::unwrap).collect();
+        assert_eq!(rest,
+                   [Event::Key(Key::Esc),
+                    Event::Key(Key::Char('a')),
+                    Event::Key(Key::Char('b')),
+                    Event::Key(Key::Char('c'))]);
+
+        let mut events = b"a".events().translate(|_| Some(Event::Key(Key::Char('x'))));
+        let err = events.cursor_position(&mut out).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::TimedOut);
+        assert_eq!(events.next().unwrap().unwrap(), Event::Key(Key::Char('x')));
+    }
+
+    #[test]
+    fn test_primary_attributes() {
+        let mut out = Vec::new();
+        let mut events = b"\x1B[?62;22c".events();
+        assert_eq!(events.primary_attributes(&mut out).unwrap(), [62, 22]);
+        assert_eq!(out, b"\x1B[c");
+    }
+
+    #[test]
+    fn test_private_mode() {
+        let mut out = Vec::new();
+        let mut events = b"a\x1B[?12;1$y\x1B[?25;2$yb".events();
+        assert_eq!(events.private_mode(&mut out, 25).unwrap(), Some(false));
+        assert_eq!(out, b"\x1B[?25$p");
+
+        let rest: Vec<_> = events.map(Result::unwrap).collect();
+        assert_eq!(rest,
+                   [Event::Key(Key::Char('a')),
+                    Event::Report(Report::PrivateMode(12, Some(true))),
+                    Event::Key(Key::Char('b'))]);
+    }
+
+    #[test]
+    fn test_color_queries() {
+        let mut out = Vec::new();
+        let mut events = b"a\x1B]4;9;rgb:ffff/0000/0000\x07".events();
+        assert_eq!(events.palette_color(&mut out, 9).unwrap(), Rgb(255, 0, 0));
+        assert_eq!(out, b"\x1B]4;9;?\x1B\\");
+        assert_eq!(events.next().unwrap().unwrap(), Event::Key(Key::Char('a')));
+
+        let mut out = Vec::new();
+        let mut events = b"\x1B]11;rgb:0000/2b2b/3636\x1B\\".events();
+        assert_eq!(events.background_color(&mut out).unwrap(), Rgb(0, 43, 54));
+        assert_eq!(out, b"\x1B]11;?\x1B\\");
+    }
+
+    #[test]
+    fn test_events_with_timeout() {
+        let mut i = b"a".events_with_timeout(Duration::from_millis(5));
+        assert_eq!(i.next().unwrap().unwrap(), Some(Event::Key(Key::Char('a'))));
+        let start = Instant::now();
+        assert_eq!(i.next().unwrap().unwrap(), None);
+        assert!(start.elapsed() >= Duration::from_millis(5));
+    }
+
+    #[test]
+    fn test_key_state() {
+        let ctrl = Modifiers { ctrl: true, ..Modifiers::default() };
+        let mut keys = KeyState::new();
+        keys.track(&Event::Key(Key::Left));
+        keys.track(&Event::Key(Key::Char('A')));
+        keys.track(&Event::KeyRepeat(Key::Char('a')));
+        assert!(keys.is_pressed(Key::Left));
+        assert!(keys.is_pressed(Key::Char('a')));
+        assert!(keys.is_pressed(Key::Alt('A')));
+        assert!(!keys.is_pressed(Key::Right));
+
+        keys.track(&Event::KeyRelease(Key::Modified(ctrl, ModifiedKey::Left)));
+        assert!(!keys.is_pressed(Key::Left));
+        assert!(keys.is_pressed(Key::Char('a')));
+
+        keys.track(&Event::FocusLost);
+        assert!(!keys.is_pressed(Key::Char('a')));
+    }
+
+    #[test]
+    fn test_signal_events() {
+        let mut i = b"a".events();
+        // Pretend a suspend and an interrupt were received since the iterator was created.
+        i.signals[0] = i.signals[0].wrapping_sub(1);
+        i.signals[2] = i.signals[2].wrapping_sub(1);
+        assert_eq!(i.next().unwrap().unwrap(), Event::Suspend);
+        assert_eq!(i.next().unwrap().unwrap(), Event::Interrupt);
+        assert_eq!(i.next().unwrap().unwrap(), Event::Key(Key::Char('a')));
+        assert!(i.next().is_none());
+    }
+
+    #[test]
+    fn test_translate() {
+        use event::{MouseButton, MouseEvent};
+
+        let mut last = None;
+        let mut i = b"\x1B[<35;1;1M\x1B[<35;1;1M\x1B[<35;2;1M\x1B[<0;2;1Mx\x1B"
+            .events()
+            .translate(|event| match event {
+                Event::Key(Key::Char('x')) => Some(Event::Key(Key::Ctrl('z'))),
+                event => Some(event),
+            })
+            .translate(|event| match event {
+                Event::Key(Key::Ctrl('z')) => Some(Event::Key(Key::Esc)),
+                event => Some(event),
+            })
+            .translate(move |event| {
+                // Drop repeated moves to the same position.
+                if let Event::Mouse(MouseEvent::Move(..)) = event {
+                    if last == Some(event.clone()) {
+                        return None;
+                    }
+                }
+                last = Some(event.clone());
+                Some(event)
+            });
+        assert_eq!(i.next().unwrap().unwrap(), Event::Mouse(MouseEvent::Move(1, 1)));
+        assert_eq!(i.next().unwrap().unwrap(), Event::Mouse(MouseEvent::Move(2, 1)));
+        assert_eq!(i.next().unwrap().unwrap(),
+                   Event::Mouse(MouseEvent::Press(MouseButton::Left, 2, 1)));
+        assert_eq!(i.next().unwrap().unwrap(), Event::Key(Key::Esc));
+        i.unread(Event::Key(Key::Char('x')));
+        assert_eq!(i.next().unwrap().unwrap(), Event::Key(Key::Char('x')));
+        assert_eq!(i.next().unwrap().unwrap(), Event::Key(Key::Esc));
+        assert!(i.next().is_none());
+    }
+
+    #[test]
+    fn test_coalesce_motion() {
+        use event::MouseButton;
+
+        let input = b"\x1B[<35;1;1M\x1B[<35;2;1M\x1B[<35;3;1M\x1B[<0;3;1M\x1B[<32;4;1M\
+                      \x1B[<32;5;1M\x1B[<48;6;1M\x1B[<48;7;1M\x1B[<3;7;1m";
+        let mut i = input.events().coalesce_motion();
+        assert_eq!(i.next().unwrap().unwrap(), Event::Mouse(MouseEvent::Move(3, 1)));
+        assert_eq!(i.next().unwrap().unwrap(),
+                   Event::Mouse(MouseEvent::Press(MouseButton::Left, 3, 1)));
+        assert_eq!(i.next().unwrap().unwrap(), Event::Mouse(MouseEvent::Hold(5, 1)));
+        let ctrl = Modifiers { ctrl: true, ..Modifiers::default() };
+        assert_eq!(i.next().unwrap().unwrap(),
+                   Event::ModifiedMouse(ctrl, MouseEvent::Hold(7, 1)));
+        assert_eq!(i.next().unwrap().unwrap(), Event::Mouse(MouseEvent::Release(7, 1)));
+        assert!(i.next().is_none());
+
+        // Input which has not been read yet is not waited for.
+        let mut i = Chunks(vec![b"\x1B[<35;1;1M", b"\x1B[<35;2;1M"]).events().coalesce_motion();
+        assert_eq!(i.next().unwrap().unwrap(), Event::Mouse(MouseEvent::Move(1, 1)));
+        assert_eq!(i.next().unwrap().unwrap(), Event::Mouse(MouseEvent::Move(2, 1)));
+        assert!(i.next().is_none());
+
+        assert_eq!(input.events().count(), 9);
+    }
+
+    #[test]
+    fn test_utf8_keys() {
+        let mut st = "é€\u{1F600}\x1Bü".as_bytes().keys();
+        assert_eq!(st.next().unwrap().unwrap(), Key::Char('é'));
+        assert_eq!(st.next().unwrap().unwrap(), Key::Char('€'));
+        assert_eq!(st.next().unwrap().unwrap(), Key::Char('\u{1F600}'));
+        assert_eq!(st.next().unwrap().unwrap(), Key::Alt('ü'));
+        assert!(st.next().is_none());
+    }
+
     #[test]
     fn test_esc_key() {
         let mut st = b"\x1B".keys();
@@ -310,6 +1941,127 @@ mod test {
                    Some("this is the secnd tes"));
     }
 
+    #[test]
+    fn test_line_editing() {
+        line_match("wrong\x15right", Some("right"));
+        line_match("ab\x08c\r", Some("ac"));
+        line_match("héé\x7f\x7fllo", Some("hllo"));
+        line_match("he\x1B[D\x1B[Cy", Some("hey"));
+        line_match("ab\x1B\rcd\n", Some("ab"));
+        line_match("ab\x1Bc\x1B\x03", None);
+        // Nothing after the line is consumed.
+        let mut input = &b"ab\x1B\rcd"[..];
+        assert_eq!(input.read_line().unwrap().unwrap(), "ab");
+        assert_eq!(input, b"cd");
+        line_match("abort\x03", None);
+        line_match("abort\x04", None);
+        line_match("", Some(""));
+    }
+
+    fn edit(editor: &mut LineEditor, input: &str) -> (Option<String>, String) {
+        let mut keys = input.as_bytes().keys();
+        let mut out = Vec::new();
+        let line = editor.read_line(&mut keys, &mut out).unwrap();
+        (line, String::from_utf8(out).unwrap())
+    }
+
+    #[test]
+    fn test_line_editor() {
+        let mut editor = LineEditor::new();
+        editor.set_prompt("> ");
+
+        let (line, out) = edit(&mut editor, "ab\x1B[Dc\r");
+        assert_eq!(line.unwrap(), "acb");
+        assert_eq!(out,
+                   "\r> \x1B[K\r> a\x1B[K\r> ab\x1B[K\r> ab\x1B[K\x1B[1D\r> acb\x1B[K\x1B[1D\
+                    \r\n");
+
+        // Moving and deleting words.
+        assert_eq!(edit(&mut editor, "one two three\x17\x17four\r").0.unwrap(), "one four");
+        assert_eq!(edit(&mut editor, "one two\x1Bb\x1Bb\x1Bdx\x1B[1;5Cy\r").0.unwrap(),
+                   "x twoy");
+        assert_eq!(edit(&mut editor, "abc\x02\x02\x0B\x01\x1B[3~\x05d\r").0.unwrap(), "d");
+        assert_eq!(edit(&mut editor, "abc\x02\x15\x7Fx\r").0.unwrap(), "xc");
+
+        // Going through the history keeps the new line.
+        assert_eq!(editor.history(), ["acb", "one four", "x twoy", "d", "xc"]);
+        assert_eq!(edit(&mut editor, "new\x1B[A\x1B[A\x1B[B\x1B[B!\r").0.unwrap(), "new!");
+        assert_eq!(edit(&mut editor, "\x10\x10\x10\x10\x10\x10\r").0.unwrap(), "acb");
+        assert_eq!(edit(&mut editor, "\x1B[A\r").0.unwrap(), "acb");
+        assert_eq!(editor.history().len(), 7);
+
+        // Aborting.
+        assert_eq!(edit(&mut editor, "abc\x03").0, None);
+        assert_eq!(edit(&mut editor, "abc\x04\x01\x04\x04\x04\x04").0, None);
+        assert_eq!(edit(&mut editor, "abc").0, None);
+        assert_eq!(edit(&mut editor, "\r").0.unwrap(), "");
+        assert_eq!(editor.history().len(), 7);
+    }
+
+    #[test]
+    fn test_key_chords() {
+        use std::time::{Duration, Instant};
+
+        let mut chords = KeyChords::with_timeout(Duration::from_millis(100));
+        chords.bind(&[Key::Ctrl('x'), Key::Ctrl('s')], 1);
+        chords.bind(&[Key::Ctrl('x'), Key::Char('4'), Key::Char('f')], 2);
+        chords.bind(&[Key::F(1)], 3);
+        chords.bind(&[Key::Ctrl('x'), Key::Ctrl('s')], 4);
+
+        let start = Instant::now();
+        let at = |ms| start + Duration::from_millis(ms);
+        assert_eq!(chords.feed_at(Key::F(1), at(0)), ChordMatch::Matched(3));
+        assert_eq!(chords.feed_at(Key::Ctrl('x'), at(0)), ChordMatch::Pending);
+        assert_eq!(chords.feed_at(Key::Ctrl('s'), at(0)), ChordMatch::Matched(4));
+        assert_eq!(chords.feed_at(Key::Ctrl('x'), at(0)), ChordMatch::Pending);
+        assert_eq!(chords.feed_at(Key::Char('4'), at(50)), ChordMatch::Pending);
+        assert_eq!(chords.pending(), [Key::Ctrl('x'), Key::Char('4')]);
+        assert_eq!(chords.expire_at(at(100)), None);
+        assert_eq!(chords.feed_at(Key::Char('f'), at(120)), ChordMatch::Matched(2));
+        assert_eq!(chords.feed_at(Key::Char('q'), at(120)),
+                   ChordMatch::Unmatched(vec![Key::Char('q')]));
+
+        assert_eq!(chords.feed_at(Key::Ctrl('x'), at(200)), ChordMatch::Pending);
+        assert_eq!(chords.expire_at(at(300)), Some(vec![Key::Ctrl('x')]));
+        assert_eq!(chords.expire_at(at(400)), None);
+        assert_eq!(chords.feed_at(Key::Ctrl('s'), at(400)),
+                   ChordMatch::Unmatched(vec![Key::Ctrl('s')]));
+
+        // Feeding a key after the timeout gives up on the chord without calling `expire`.
+        assert_eq!(chords.feed_at(Key::Ctrl('x'), at(500)), ChordMatch::Pending);
+        assert_eq!(chords.feed_at(Key::Ctrl('s'), at(600)),
+                   ChordMatch::Expired(vec![Key::Ctrl('x')],
+                                       Box::new(ChordMatch::Unmatched(vec![Key::Ctrl('s')]))));
+        assert_eq!(chords.feed_at(Key::Ctrl('x'), at(700)), ChordMatch::Pending);
+        assert_eq!(chords.feed_at(Key::Ctrl('x'), at(800)),
+                   ChordMatch::Expired(vec![Key::Ctrl('x')], Box::new(ChordMatch::Pending)));
+        assert_eq!(chords.feed_at(Key::Ctrl('s'), at(850)), ChordMatch::Matched(4));
+    }
+
+    #[test]
+    fn test_read_event_timeout() {
+        use std::time::Duration;
+
+        let mut input = vec![Some(0x1B), Some(b'['), Some(b'A'), Some(0x1B), None, Some(0x1B),
+                             Some(b'['), None, None]
+            .into_iter();
+        let mut timeouts = Vec::new();
+        let mut read = |timeout| {
+            timeouts.push(timeout);
+            Ok(input.next().unwrap())
+        };
+        let timeout = Duration::from_secs(1);
+        assert_eq!(read_event_timeout(&mut read, timeout).unwrap(), Some(Event::Key(Key::Up)));
+        assert_eq!(read_event_timeout(&mut read, timeout).unwrap(), Some(Event::Key(Key::Esc)));
+        assert_eq!(read_event_timeout(&mut read, timeout).unwrap(),
+                   Some(Event::Unsupported(b"\x1B[".to_vec())));
+        assert_eq!(read_event_timeout(&mut read, timeout).unwrap(), None);
+
+        let short = Duration::from_millis(SEQUENCE_TIMEOUT);
+        assert_eq!(timeouts,
+                   [timeout, short, short, timeout, short, timeout, short, short, timeout]);
+    }
+
     #[test]
     fn test_end() {
         line_match("abc\nhttps://www.youtube.com/watch?v=dQw4w9WgXcQ",
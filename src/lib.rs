@@ -8,6 +8,11 @@
 //!
 //! Supports Redox, Mac OS X, and Linux (or, in general, ANSI terminals).
 //!
+//! The types restoring the terminal's state when dropped (like `screen::AlternateScreen` and
+//! `cursor::HideCursor`) panic if they can't change it on creation, but ignore the errors of
+//! restoring it, since panicking in a destructor may abort the process before the rest of the
+//! state (e.g. raw mode) is restored.
+//!
 //! For more information refer to the [README](https://github.com/ticki/termion).
 #![warn(missing_docs)]
 
@@ -19,11 +24,23 @@ extern crate winapi;
 #[cfg(windows)]
 extern crate kernel32;
 
+#[cfg(feature = "futures")]
+extern crate futures_core;
+#[cfg(feature = "mio")]
+extern crate mio;
+#[cfg(feature = "tokio")]
+extern crate tokio;
+
 #[cfg(not(any(target_os = "redox", windows)))]
 mod termios;
 
 mod async;
-pub use async::{AsyncReader, async_stdin};
+pub use async::{AsyncReader, Overflow, async_stdin, async_stdin_until, async_tty};
+#[cfg(feature = "futures")]
+pub use async::EventStream;
+
+mod signal;
+pub use signal::{Signal, report_signal, suspend};
 
 
 #[cfg(not(windows))]
@@ -42,15 +59,29 @@ mod tty;
 #[path = "tty_windows.rs"]
 mod tty;
 
-pub use tty::{is_tty, get_tty};
+pub use tty::{is_tty, get_tty, get_tty_nonblocking};
+#[cfg(all(feature = "tokio", not(any(windows, target_os = "redox"))))]
+pub use tty::AsyncTty;
+#[cfg(all(feature = "mio", not(any(windows, target_os = "redox"))))]
+pub use tty::TtySource;
+
+#[cfg(windows)]
+#[path = "console_windows.rs"]
+mod console;
 
 #[macro_use]
 mod macros;
+#[macro_use]
+mod guard;
+pub mod ansi;
+pub mod buffer;
 pub mod clear;
 pub mod color;
 pub mod cursor;
+pub mod edit;
 pub mod event;
 pub mod input;
+pub mod mouse;
 
 #[cfg(not(windows))]
 pub mod raw;
@@ -59,6 +90,7 @@ pub mod raw;
 #[path = "raw_windows.rs"]
 pub mod raw;
 
+pub mod record;
 pub mod screen;
 pub mod scroll;
 pub mod style;
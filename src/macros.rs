@@ -15,5 +15,13 @@ macro_rules! derive_csi_sequence {
                 write!(f, csi!($value))
             }
         }
+
+        impl $name {
+            /// The sequence as bytes, for writing it without formatting.
+            #[inline]
+            pub fn as_bytes(&self) -> &'static [u8] {
+                csi!($value).as_bytes()
+            }
+        }
     };
 }
@@ -0,0 +1,56 @@
+//! Mouse reporting modes.
+//!
+//! These sequences switch the terminal's mouse reporting on and off. The reported events are
+//! parsed into `event::Event::Mouse`. For a terminal which enables a sensible set of modes on
+//! creation and disables them on drop, see `input::MouseTerminal`.
+//!
+//! By default, mouse reports use the X10 encoding, which cannot represent coordinates beyond 223.
+//! The SGR and urxvt encodings lift this limit. When several encodings are enabled, the terminal
+//! uses the one it prefers among those it supports, falling back to X10 if it supports none.
+//!
+//! # Example
+//!
+//! ```rust
+//! use termion::mouse;
+//!
+//! fn main() {
+//!     print!("{}{}", mouse::EnableNormal, mouse::EnableSgr);
+//!     // ...
+//!     print!("{}{}", mouse::DisableSgr, mouse::DisableNormal);
+//! }
+//! ```
+
+use std::fmt;
+
+derive_csi_sequence!("Enable X10 compatibility mouse reporting (button presses only).",
+                     EnableX10,
+                     "?9h");
+derive_csi_sequence!("Disable X10 compatibility mouse reporting.", DisableX10, "?9l");
+derive_csi_sequence!("Enable normal mouse reporting (button presses and releases).",
+                     EnableNormal,
+                     "?1000h");
+derive_csi_sequence!("Disable normal mouse reporting.", DisableNormal, "?1000l");
+derive_csi_sequence!("Enable reporting of mouse motion while a button is held (dragging).",
+                     EnableButtonMotion,
+                     "?1002h");
+derive_csi_sequence!("Disable reporting of mouse motion while a button is held.",
+                     DisableButtonMotion,
+                     "?1002l");
+derive_csi_sequence!("Enable reporting of all mouse motion, even while no button is held.",
+                     EnableAnyMotion,
+                     "?1003h");
+derive_csi_sequence!("Disable reporting of mouse motion while no button is held.",
+                     DisableAnyMotion,
+                     "?1003l");
+derive_csi_sequence!("Enable the urxvt (1015) extended encoding of mouse reports.",
+                     EnableUrxvt,
+                     "?1015h");
+derive_csi_sequence!("Disable the urxvt (1015) extended encoding of mouse reports.",
+                     DisableUrxvt,
+                     "?1015l");
+derive_csi_sequence!("Enable the SGR (1006) extended encoding of mouse reports.",
+                     EnableSgr,
+                     "?1006h");
+derive_csi_sequence!("Disable the SGR (1006) extended encoding of mouse reports.",
+                     DisableSgr,
+                     "?1006l");
@@ -24,6 +24,8 @@
 
 use std::io::{self, Write};
 use std::ops;
+#[cfg(not(target_os = "redox"))]
+use std::time::Duration;
 
 /// A terminal restorer, which keeps the previous state of the terminal, and restores it, when
 /// dropped.
@@ -59,6 +61,83 @@ impl<W: Write> Drop for RawTerminal<W> {
     }
 }
 
+#[cfg(not(target_os = "redox"))]
+impl<W: Write> RawTerminal<W> {
+    /// Enable or disable software flow control (`IXON` and `IXOFF`).
+    ///
+    /// Raw mode disables it, so Ctrl-S and Ctrl-Q are read as `Key::Ctrl('s')` and
+    /// `Key::Ctrl('q')`. With flow control, they instead stop and resume the output, which makes
+    /// the program appear to hang until Ctrl-Q is typed.
+    pub fn set_flow_control(&mut self, enabled: bool) -> io::Result<()> {
+        use libc::{IXOFF, IXON};
+
+        update_terminal_attr(|ios| if enabled {
+            ios.c_iflag |= IXON | IXOFF;
+        } else {
+            ios.c_iflag &= !(IXON | IXOFF);
+        })
+    }
+
+    /// Enable or disable the signal keys (`ISIG`).
+    ///
+    /// Raw mode disables them, so Ctrl-C and Ctrl-Z are read as `Key::Ctrl('c')` and
+    /// `Key::Ctrl('z')`. With the signal keys, they instead send `SIGINT` and `SIGTSTP` to the
+    /// process (which can be reported as events with `report_signal`), and Ctrl-\\ sends `SIGQUIT`.
+    pub fn set_signals(&mut self, enabled: bool) -> io::Result<()> {
+        use libc::ISIG;
+
+        update_terminal_attr(|ios| if enabled {
+            ios.c_lflag |= ISIG;
+        } else {
+            ios.c_lflag &= !ISIG;
+        })
+    }
+
+    /// Set when reads from the terminal return (`VMIN` and `VTIME`).
+    ///
+    /// A read waits for at least `min` bytes, giving up `time` after the last byte received (or
+    /// after the read started, if `min` is zero). The time is rounded up to tenths of a second,
+    /// and zero waits indefinitely for `min` bytes. For instance, a `min` of zero and a `time` of
+    /// 100 ms makes reads return what arrived within 100 ms, if anything.
+    ///
+    /// Raw mode waits for one byte without a timeout. Times beyond 25.5 seconds give an error of
+    /// kind `InvalidInput`.
+    pub fn set_read_timeout(&mut self, min: u8, time: Duration) -> io::Result<()> {
+        use libc::{VMIN, VTIME};
+
+        let tenths = time.as_secs()
+            .saturating_mul(10)
+            .saturating_add(u64::from(time.subsec_nanos().div_ceil(100_000_000)));
+        if tenths > 255 {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput,
+                                      "The read timeout is limited to 25.5 seconds."));
+        }
+
+        update_terminal_attr(|ios| {
+            ios.c_cc[VMIN] = min;
+            ios.c_cc[VTIME] = tenths as u8;
+        })
+    }
+}
+
+/// Change the current terminal attributes with `f`.
+#[cfg(not(target_os = "redox"))]
+fn update_terminal_attr<F: FnOnce(&mut Termios)>(f: F) -> io::Result<()> {
+    use termios::{get_terminal_attr, set_terminal_attr};
+
+    let (mut ios, exit) = get_terminal_attr();
+    if exit != 0 {
+        return Err(io::Error::other("Unable to get Termios attribute."));
+    }
+
+    f(&mut ios);
+
+    if set_terminal_attr(&mut ios as *mut _) != 0 {
+        return Err(io::Error::other("Unable to set Termios attribute."));
+    }
+    Ok(())
+}
+
 impl<W: Write> ops::Deref for RawTerminal<W> {
     type Target = W;
 
@@ -142,5 +221,27 @@ mod test {
         let mut out = stdout().into_raw_mode().unwrap();
 
         out.write_all(b"this is a test, muahhahahah").unwrap();
+
+        // Changing the attributes is tested here, so it doesn't race with other raw mode tests.
+        #[cfg(not(target_os = "redox"))]
+        {
+            use libc::{ISIG, IXON, VMIN, VTIME};
+            use termios::get_terminal_attr;
+
+            out.set_flow_control(true).unwrap();
+            assert_eq!(get_terminal_attr().0.c_iflag & IXON, IXON);
+            out.set_flow_control(false).unwrap();
+            assert_eq!(get_terminal_attr().0.c_iflag & IXON, 0);
+
+            out.set_signals(true).unwrap();
+            assert_eq!(get_terminal_attr().0.c_lflag & ISIG, ISIG);
+            out.set_signals(false).unwrap();
+            assert_eq!(get_terminal_attr().0.c_lflag & ISIG, 0);
+
+            out.set_read_timeout(0, Duration::from_millis(150)).unwrap();
+            let ios = get_terminal_attr().0;
+            assert_eq!((ios.c_cc[VMIN], ios.c_cc[VTIME]), (0, 2));
+            assert!(out.set_read_timeout(0, Duration::from_secs(26)).is_err());
+        }
     }
 }
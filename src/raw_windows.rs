@@ -24,6 +24,7 @@
 
 use std::io::{self, Write};
 use std::ops;
+use std::time::Duration;
 
 use winapi;
 use winapi::wincon::*;
@@ -80,6 +81,37 @@ impl<W: Write> Write for RawTerminal<W> {
     }
 }
 
+impl<W: Write> RawTerminal<W> {
+    /// Enable or disable software flow control.
+    ///
+    /// The Windows console has no flow control, so Ctrl-S and Ctrl-Q are always read as keys, and
+    /// this does nothing.
+    pub fn set_flow_control(&mut self, _enabled: bool) -> io::Result<()> {
+        Ok(())
+    }
+
+    /// Set when reads from the terminal return.
+    ///
+    /// The Windows console has no equivalent for this, so it gives an error.
+    pub fn set_read_timeout(&mut self, _min: u8, _time: Duration) -> io::Result<()> {
+        Err(io::Error::new(io::ErrorKind::Other, "Read timeouts are not supported on Windows."))
+    }
+
+    /// Enable or disable the system's handling of Ctrl-C (`ENABLE_PROCESSED_INPUT`).
+    ///
+    /// Raw mode disables it, so Ctrl-C is read as `Key::Ctrl('c')`. When enabled, it instead
+    /// interrupts the process.
+    pub fn set_signals(&mut self, enabled: bool) -> io::Result<()> {
+        let handle = get_std_handle(winapi::STD_INPUT_HANDLE)?;
+        let mode = get_console_mode(handle)?;
+        set_console_mode(handle, if enabled {
+            mode | ENABLE_PROCESSED_INPUT
+        } else {
+            mode & !ENABLE_PROCESSED_INPUT
+        })
+    }
+}
+
 /// Types which can be converted into "raw mode".
 ///
 /// # Why is this type defined on writers and not readers?
@@ -0,0 +1,195 @@
+//! Recording input and replaying it.
+//!
+//! A recording is a text file with a line for every read, holding the time of the read in
+//! microseconds since the recording started, and the bytes read in hexadecimal:
+//!
+//! ```text
+//! 0 61
+//! 1520133 1b5b41
+//! ```
+//!
+//! Replaying it feeds the same bytes to the parser at the same pace, which is useful for
+//! reproducing bug reports and for testing applications deterministically.
+//!
+//! # Example
+//!
+//! ```rust
+//! use std::f64;
+//! use termion::event::{Event, Key};
+//! use termion::input::TermRead;
+//! use termion::record::{Recorder, Replayer};
+//!
+//! let mut recorder = Recorder::new(&b"q"[..], Vec::new());
+//! (&mut recorder).keys().next();
+//!
+//! let recording = recorder.into_inner().1;
+//! let mut events = Replayer::new(&recording[..]).speed(f64::INFINITY).events();
+//! assert_eq!(events.next().unwrap().unwrap(), Event::Key(Key::Char('q')));
+//! ```
+
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// A reader which records the input read from another reader, see the module documentation.
+pub struct Recorder<R, W> {
+    source: R,
+    log: W,
+    start: Instant,
+}
+
+impl<R: Read, W: Write> Recorder<R, W> {
+    /// Record the input read from `source` to `log`, timed from now on.
+    pub fn new(source: R, log: W) -> Recorder<R, W> {
+        Recorder {
+            source,
+            log,
+            start: Instant::now(),
+        }
+    }
+
+    /// Get back the source and the log.
+    pub fn into_inner(self) -> (R, W) {
+        (self.source, self.log)
+    }
+}
+
+impl<R: Read, W: Write> Read for Recorder<R, W> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.source.read(buf)?;
+        if n > 0 {
+            let elapsed = self.start.elapsed();
+            let micros = elapsed.as_secs() * 1_000_000 + u64::from(elapsed.subsec_micros());
+            let hex: String = buf[..n].iter().map(|b| format!("{:02x}", b)).collect();
+            writeln!(self.log, "{} {}", micros, hex)?;
+            self.log.flush()?;
+        }
+        Ok(n)
+    }
+}
+
+/// A reader replaying a recording, see the module documentation.
+///
+/// The input of each recorded read is returned once the time it was recorded at has passed since
+/// the first read from the replayer.
+pub struct Replayer<R> {
+    recording: BufReader<R>,
+    speed: f64,
+    start: Option<Instant>,
+    /// The input of the current line not read yet.
+    pending: Vec<u8>,
+}
+
+impl<R: Read> Replayer<R> {
+    /// Replay `recording` at its original pace.
+    pub fn new(recording: R) -> Replayer<R> {
+        Replayer {
+            recording: BufReader::new(recording),
+            speed: 1.0,
+            start: None,
+            pending: Vec::new(),
+        }
+    }
+
+    /// Replay `speed` times as fast as recorded, or without any delay if `speed` is infinite.
+    ///
+    /// # Panics
+    ///
+    /// This panics if `speed` isn't positive.
+    pub fn speed(mut self, speed: f64) -> Replayer<R> {
+        assert!(speed > 0.0, "The speed must be positive.");
+        self.speed = speed;
+        self
+    }
+
+    /// Read the next line of the recording into `pending`, waiting until it is due. Returns
+    /// `false` at the end of the recording.
+    fn next_line(&mut self) -> io::Result<bool> {
+        let mut line = String::new();
+        if self.recording.read_line(&mut line)? == 0 {
+            return Ok(false);
+        }
+
+        let invalid = || io::Error::new(io::ErrorKind::InvalidData, "Invalid recording line");
+        let mut fields = line.split_whitespace();
+        let micros: u64 = fields.next().and_then(|t| t.parse().ok()).ok_or_else(invalid)?;
+        let hex = fields.next().ok_or_else(invalid)?;
+        if hex.len() % 2 != 0 || !hex.is_ascii() {
+            return Err(invalid());
+        }
+        self.pending = (0..hex.len()).step_by(2)
+            .map(|i| u8::from_str_radix(&hex[i..i + 2], 16))
+            .rev()
+            .collect::<Result<_, _>>()
+            .map_err(|_| invalid())?;
+
+        let start = *self.start.get_or_insert_with(Instant::now);
+        let due = start + Duration::from_micros(micros).div_f64(self.speed);
+        let now = Instant::now();
+        if due > now {
+            thread::sleep(due - now);
+        }
+        Ok(true)
+    }
+}
+
+impl<R: Read> Read for Replayer<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.pending.is_empty() {
+            if !self.next_line()? {
+                return Ok(0);
+            }
+        }
+
+        // The pending input is stored in reverse, so it can be popped off.
+        let mut n = 0;
+        while n < buf.len() {
+            match self.pending.pop() {
+                Some(b) => buf[n] = b,
+                None => break,
+            }
+            n += 1;
+        }
+        Ok(n)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::f64;
+    use event::{Event, Key};
+    use input::TermRead;
+
+    #[test]
+    fn test_record_replay() {
+        let source = (&b"a"[..]).chain(&b"\x1B[A"[..]);
+        let mut recorder = Recorder::new(source, Vec::new());
+        let mut input = Vec::new();
+        recorder.read_to_end(&mut input).unwrap();
+        assert_eq!(input, b"a\x1B[A");
+
+        let recording = String::from_utf8(recorder.into_inner().1).unwrap();
+        let lines: Vec<_> = recording.lines().map(|l| l.split(' ').nth(1).unwrap()).collect();
+        assert_eq!(lines, ["61", "1b5b41"]);
+
+        let events: Vec<_> = Replayer::new(recording.as_bytes())
+            .speed(f64::INFINITY)
+            .events()
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(events, [Event::Key(Key::Char('a')), Event::Key(Key::Up)]);
+
+        let start = Instant::now();
+        let mut replayer = Replayer::new(&b"0 61\n20000 62\n"[..]);
+        let mut buf = [0; 2];
+        assert_eq!(replayer.read(&mut buf).unwrap(), 1);
+        assert_eq!(replayer.read(&mut buf).unwrap(), 1);
+        assert_eq!(&buf[..1], b"b");
+        assert!(start.elapsed() >= Duration::from_millis(20));
+        assert_eq!(replayer.read(&mut buf).unwrap(), 0);
+
+        let mut replayer = Replayer::new(&b"0 6\n"[..]);
+        assert_eq!(replayer.read(&mut buf).unwrap_err().kind(), io::ErrorKind::InvalidData);
+    }
+}
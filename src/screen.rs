@@ -1,4 +1,4 @@
-//! Managing switching between main and alternate screen buffers.
+//! Managing switching between main and alternate screen buffers, and synchronized updates.
 //!
 //! Note that this implementation uses xterm's new escape sequences for screen switching and thus
 //! only works for xterm compatible terminals (which should be most terminals nowadays).
@@ -19,10 +19,11 @@
 //! }
 //! ```
 
-use std::io::{self, Write};
-use std::ops;
+use std::io::Write;
 use std::fmt;
 
+use guard::ModeGuard;
+
 /// Switch to the main screen buffer of the terminal.
 pub struct ToMainScreen;
 
@@ -45,49 +46,89 @@ impl fmt::Display for ToAlternateScreen {
 /// to an alternate screen.
 ///
 /// This is achieved by switching the terminal to the alternate screen on creation and
-/// automatically switching it back to the original screen on drop.
+/// automatically switching it back to the original screen on drop, so the main screen is restored
+/// on every way out of the scope, including early returns with `?` and panics.
 pub struct AlternateScreen<W: Write> {
-    /// The output target.
-    output: W,
+    guard: ModeGuard<W>,
 }
 
 impl<W: Write> AlternateScreen<W> {
     /// Create an alternate screen wrapper struct for the provided output and switch the terminal
     /// to the alternate screen.
-    pub fn from(mut output: W) -> Self {
-        write!(output, "{}", ToAlternateScreen).expect("switch to alternate screen");
-        AlternateScreen {
-            output: output,
-        }
+    pub fn from(output: W) -> Self {
+        AlternateScreen { guard: ModeGuard::new(output, ToAlternateScreen, ToMainScreen) }
     }
 }
 
-impl<W: Write> Drop for AlternateScreen<W> {
-    fn drop(&mut self) {
-        write!(self, "{}", ToMainScreen).expect("switch to main screen");
-    }
-}
+derive_mode_guard!(AlternateScreen);
 
-impl<W: Write> ops::Deref for AlternateScreen<W> {
-    type Target = W;
+derive_csi_sequence!("Begin a synchronized update (DEC mode 2026), so the terminal holds back the \
+                      output until the update ends, instead of showing a frame drawn halfway.",
+                     BeginSynchronizedUpdate,
+                     "?2026h");
+derive_csi_sequence!("End a synchronized update, showing the output since it began.",
+                     EndSynchronizedUpdate,
+                     "?2026l");
 
-    fn deref(&self) -> &W {
-        &self.output
-    }
+/// A guard drawing a frame in a synchronized update, so the terminal shows it all at once rather
+/// than tearing or flickering during large repaints.
+///
+/// The update begins on creation and ends on drop, flushing the output both times. Terminals not
+/// supporting synchronized updates ignore the sequences.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use std::io::{Write, stdout};
+/// use termion::{clear, cursor};
+/// use termion::screen::SyncUpdate;
+///
+/// let stdout = stdout();
+/// let mut stdout = stdout.lock();
+/// {
+///     let mut frame = SyncUpdate::from(&mut stdout);
+///     write!(frame, "{}{}Frame", clear::All, cursor::Goto(1, 1)).unwrap();
+/// }
+/// ```
+pub struct SyncUpdate<W: Write> {
+    guard: ModeGuard<W>,
 }
 
-impl<W: Write> ops::DerefMut for AlternateScreen<W> {
-    fn deref_mut(&mut self) -> &mut W {
-        &mut self.output
+impl<W: Write> SyncUpdate<W> {
+    /// Begin a synchronized update of the provided output.
+    pub fn from(output: W) -> Self {
+        SyncUpdate { guard: ModeGuard::new(output, BeginSynchronizedUpdate, EndSynchronizedUpdate) }
     }
 }
 
-impl<W: Write> Write for AlternateScreen<W> {
-    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
-        self.output.write(buf)
+derive_mode_guard!(SyncUpdate);
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::io;
+
+    #[test]
+    fn test_alternate_screen() {
+        fn draw(out: &mut Vec<u8>) -> io::Result<()> {
+            let mut screen = AlternateScreen::from(out);
+            write!(screen, "x")?;
+            Err(io::Error::other("early return"))
+        }
+
+        let mut out = Vec::new();
+        assert!(draw(&mut out).is_err());
+        assert_eq!(out, b"\x1B[?1049hx\x1B[?1049l");
     }
 
-    fn flush(&mut self) -> io::Result<()> {
-        self.output.flush()
+    #[test]
+    fn test_sync_update() {
+        let mut out = Vec::new();
+        {
+            let mut frame = SyncUpdate::from(&mut out);
+            write!(frame, "x").unwrap();
+            assert_eq!(frame.len(), 9);
+        }
+        assert_eq!(out, b"\x1B[?2026hx\x1B[?2026l");
     }
 }
@@ -1,6 +1,29 @@
 //! Scrolling.
+//!
+//! # Example
+//!
+//! Keeping a header and a status bar in place while the rows between them scroll:
+//!
+//! ```rust,no_run
+//! use std::io::{Write, stdout};
+//! use termion::cursor::Goto;
+//! use termion::scroll::ScrollRegion;
+//!
+//! let stdout = stdout();
+//! let mut stdout = stdout.lock();
+//! write!(stdout, "{}Header{}Status", Goto(1, 1), Goto(1, 24)).unwrap();
+//!
+//! let mut region = ScrollRegion::from(&mut stdout, 2, 23);
+//! write!(region, "{}", Goto(1, 23)).unwrap();
+//! for i in 0..100 {
+//!     write!(region, "\r\nLine {}", i).unwrap();
+//! }
+//! ```
 
 use std::fmt;
+use std::io::Write;
+
+use guard::ModeGuard;
 
 /// Scroll up.
 #[derive(Copy, Clone, PartialEq, Eq)]
@@ -21,3 +44,60 @@ impl fmt::Display for Down {
         write!(f, csi!("{}T"), self.0)
     }
 }
+
+/// Set the scrolling region to the rows from `top` to `bottom` (one-based and inclusive, DECSTBM).
+///
+/// Line feeds at the bottom of the region, and the scrolling sequences, then scroll only the region
+/// while the rows outside of it stay put. This moves the cursor to the top left of the screen (or
+/// of the region, in origin mode, see `cursor::EnableOriginMode`).
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub struct Region(pub u16, pub u16);
+
+impl fmt::Display for Region {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        debug_assert!(self.0 < self.1, "The region needs at least two rows.");
+
+        write!(f, csi!("{};{}r"), self.0, self.1)
+    }
+}
+
+/// Reset the scrolling region to the whole screen.
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub struct ResetRegion;
+
+impl fmt::Display for ResetRegion {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, csi!("r"))
+    }
+}
+
+/// A wrapper setting the scrolling region of the terminal on creation and resetting it on drop,
+/// see `Region`.
+pub struct ScrollRegion<W: Write> {
+    guard: ModeGuard<W>,
+}
+
+impl<W: Write> ScrollRegion<W> {
+    /// Set the scrolling region of the provided output to the rows from `top` to `bottom`
+    /// (one-based and inclusive). This moves the cursor to the top left of the screen.
+    pub fn from(output: W, top: u16, bottom: u16) -> Self {
+        ScrollRegion { guard: ModeGuard::new(output, Region(top, bottom), ResetRegion) }
+    }
+}
+
+derive_mode_guard!(ScrollRegion);
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_scroll_region() {
+        let mut out = Vec::new();
+        {
+            let mut region = ScrollRegion::from(&mut out, 2, 23);
+            write!(region, "{}{}", Up(1), Down(2)).unwrap();
+        }
+        assert_eq!(out, b"\x1B[2;23r\x1B[1S\x1B[2T\x1B[r");
+    }
+}
@@ -0,0 +1,239 @@
+use std::io;
+use std::sync::{Mutex, Weak};
+
+/// A signal which can be reported as an input event.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Signal {
+    /// The terminal was resized (`SIGWINCH`), reported as `Event::Resize`.
+    Resize,
+    /// The user asked to suspend the process (`SIGTSTP`, usually by typing Ctrl-Z), reported as
+    /// `Event::Suspend`.
+    ///
+    /// Once reported, the signal no longer stops the process, so call `suspend` after restoring the
+    /// terminal's state. Note that raw mode disables typing Ctrl-Z for this.
+    Suspend,
+    /// The process was continued after it was stopped (`SIGCONT`, e.g. by `fg`), reported as
+    /// `Event::Resume`.
+    Resume,
+    /// The user asked to interrupt the process (`SIGINT`, usually by typing Ctrl-C), reported as
+    /// `Event::Interrupt`.
+    ///
+    /// Once reported, the signal no longer terminates the process. Note that raw mode disables
+    /// typing Ctrl-C for this.
+    Interrupt,
+}
+
+/// Something notified of the signals reported.
+pub trait Listener: Send + Sync {
+    /// Handle `signal`, which was just received.
+    fn signal(&self, signal: Signal);
+}
+
+/// The listeners notified of the signals reported.
+static LISTENERS: Mutex<Vec<Weak<dyn Listener>>> = Mutex::new(Vec::new());
+
+/// Notify `listener` of the signals reported, for as long as it is alive.
+pub fn listen(listener: Weak<dyn Listener>) {
+    let mut listeners = LISTENERS.lock().unwrap();
+    listeners.retain(|listener| listener.upgrade().is_some());
+    listeners.push(listener);
+}
+
+/// Notify the listeners of `signal`.
+#[cfg(not(any(windows, target_os = "redox")))]
+fn notify(signal: Signal) {
+    // The listeners are notified after unlocking, so they may call `listen` themselves.
+    let listeners: Vec<_> = {
+        let mut listeners = LISTENERS.lock().unwrap();
+        listeners.retain(|listener| listener.upgrade().is_some());
+        listeners.iter().filter_map(Weak::upgrade).collect()
+    };
+    for listener in listeners {
+        listener.signal(signal);
+    }
+}
+
+/// Report `signal` as an input event from now on.
+///
+/// The iterators returned by `TermRead::events` report the signal before their next read, or when
+/// it interrupts their read. The latter only works for blocking reads on the thread which receives
+/// the signal, though, so multi-threaded programs should rather read from an `AsyncReader`.
+/// Resizes are also inserted into the input of the readers returned by `async_stdin` and
+/// `async_tty`, as the sequence parsed as `Event::Resize`.
+///
+/// The signal is handled without `SA_RESTART`, so it interrupts blocking system calls with
+/// `io::ErrorKind::Interrupted` (which `std` retries in most places, e.g. in `write_all`). This
+/// replaces any handler previously installed for the signal.
+///
+/// This is not supported on Windows and Redox, where it returns an error.
+#[cfg(not(any(windows, target_os = "redox")))]
+pub fn report_signal(signal: Signal) -> io::Result<()> {
+    imp::install(signal)
+}
+
+#[cfg(any(windows, target_os = "redox"))]
+pub fn report_signal(_signal: Signal) -> io::Result<()> {
+    Err(io::Error::new(io::ErrorKind::Other, "Signals can't be reported on this platform."))
+}
+
+/// Stop the process, as `SIGTSTP` does unless it is reported.
+///
+/// This returns once the process is continued, which is reported as `Event::Resume` if
+/// `Signal::Resume` is reported.
+#[cfg(not(any(windows, target_os = "redox")))]
+pub fn suspend() -> io::Result<()> {
+    imp::suspend()
+}
+
+#[cfg(any(windows, target_os = "redox"))]
+pub fn suspend() -> io::Result<()> {
+    Err(io::Error::new(io::ErrorKind::Other, "Processes can't be suspended on this platform."))
+}
+
+/// How often `signal` has been received since it is reported.
+#[cfg(not(any(windows, target_os = "redox")))]
+pub fn received(signal: Signal) -> usize {
+    imp::received(signal)
+}
+
+#[cfg(any(windows, target_os = "redox"))]
+pub fn received(_signal: Signal) -> usize {
+    0
+}
+
+#[cfg(not(any(windows, target_os = "redox")))]
+mod imp {
+    use std::fs;
+    use std::io::{self, Read};
+    use std::mem;
+    use std::os::unix::io::IntoRawFd;
+    use std::ptr;
+    use std::sync::Mutex;
+    use std::sync::atomic::{AtomicI32, AtomicUsize, Ordering};
+    use std::thread;
+
+    use libc;
+
+    use async::pipe;
+    use super::{Signal, notify};
+
+    /// How often each signal has been received, by number.
+    static RECEIVED: [AtomicUsize; 32] = [const { AtomicUsize::new(0) }; 32];
+
+    /// The write end of the pipe waking up the thread notifying the listeners, once started.
+    static WAKE: AtomicI32 = AtomicI32::new(-1);
+
+    /// Whether the thread notifying the listeners has been started.
+    static STARTED: Mutex<bool> = Mutex::new(false);
+
+    const SIGNALS: [Signal; 4] = [
+        Signal::Resize,
+        Signal::Suspend,
+        Signal::Resume,
+        Signal::Interrupt,
+    ];
+
+    fn number(signal: Signal) -> libc::c_int {
+        match signal {
+            Signal::Resize => libc::SIGWINCH,
+            Signal::Suspend => libc::SIGTSTP,
+            Signal::Resume => libc::SIGCONT,
+            Signal::Interrupt => libc::SIGINT,
+        }
+    }
+
+    pub fn suspend() -> io::Result<()> {
+        if unsafe { libc::raise(libc::SIGSTOP) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+
+    pub fn received(signal: Signal) -> usize {
+        RECEIVED[number(signal) as usize].load(Ordering::SeqCst)
+    }
+
+    /// The signal handler, which only does what is async-signal-safe: count the signal and wake up
+    /// the notifying thread.
+    extern "C" fn handle(num: libc::c_int) {
+        // The write may change `errno`, which the code interrupted by the signal may be about to
+        // read.
+        let errno = unsafe { *errno_location() };
+        RECEIVED[num as usize].fetch_add(1, Ordering::SeqCst);
+
+        let byte = num as u8;
+        unsafe {
+            libc::write(WAKE.load(Ordering::SeqCst), &byte as *const u8 as *const libc::c_void, 1);
+            *errno_location() = errno;
+        }
+    }
+
+    /// The location of the calling thread's `errno`.
+    #[cfg(any(target_os = "linux", target_os = "emscripten", target_os = "fuchsia",
+              target_os = "l4re", target_os = "hurd", target_os = "dragonfly"))]
+    unsafe fn errno_location() -> *mut libc::c_int {
+        libc::__errno_location()
+    }
+
+    #[cfg(any(target_os = "macos", target_os = "ios", target_os = "freebsd"))]
+    unsafe fn errno_location() -> *mut libc::c_int {
+        libc::__error()
+    }
+
+    #[cfg(any(target_os = "android", target_os = "openbsd", target_os = "netbsd"))]
+    unsafe fn errno_location() -> *mut libc::c_int {
+        libc::__errno()
+    }
+
+    #[cfg(any(target_os = "solaris", target_os = "illumos"))]
+    unsafe fn errno_location() -> *mut libc::c_int {
+        libc::___errno()
+    }
+
+    /// Notify the listeners of the signals read from `wake`.
+    fn dispatch(mut wake: fs::File) {
+        // Leave the signals to the other threads, so they interrupt their reads.
+        unsafe {
+            let mut set = mem::zeroed();
+            libc::sigfillset(&mut set);
+            libc::pthread_sigmask(libc::SIG_BLOCK, &set, ptr::null_mut());
+        }
+
+        let mut byte = [0];
+        while let Ok(1) = wake.read(&mut byte) {
+            let num = libc::c_int::from(byte[0]);
+            if let Some(&signal) = SIGNALS.iter().find(|&&signal| number(signal) == num) {
+                notify(signal);
+            }
+        }
+    }
+
+    pub fn install(signal: Signal) -> io::Result<()> {
+        {
+            let mut started = STARTED.lock().unwrap();
+            if !*started {
+                let (write, read) = pipe()?;
+                let fd = write.into_raw_fd();
+                // The handler must never block, even if the pipe is full.
+                unsafe {
+                    let flags = libc::fcntl(fd, libc::F_GETFL);
+                    libc::fcntl(fd, libc::F_SETFL, flags | libc::O_NONBLOCK);
+                }
+                WAKE.store(fd, Ordering::SeqCst);
+                thread::spawn(move || dispatch(read));
+                *started = true;
+            }
+        }
+
+        unsafe {
+            let mut action: libc::sigaction = mem::zeroed();
+            action.sa_sigaction = handle as extern "C" fn(libc::c_int) as libc::sighandler_t;
+            libc::sigemptyset(&mut action.sa_mask);
+            if libc::sigaction(number(signal), &action, ptr::null_mut()) == -1 {
+                return Err(io::Error::last_os_error());
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -1,20 +1,757 @@
 //! Text styling management.
 
+use std::env;
 use std::fmt;
+use std::io::{self, Write};
+use std::ops;
+
+use color::{AnyColor, Bg, Fg, UnderlineColor};
 
 derive_csi_sequence!("Reset SGR parameters.", Reset, "m");
+derive_csi_sequence!("Reset the foreground, background and underline colors, but not the other \
+                      attributes.",
+                     ResetColors,
+                     "39;49;59m");
+derive_csi_sequence!("Reset the attributes, such as bold and underlined text, but not the colors.",
+                     ResetAttributes,
+                     "22;23;24;25;27;28;29;55m");
 derive_csi_sequence!("Bold text.", Bold, "1m");
 derive_csi_sequence!("Fainted text (not widely supported).", Faint, "2m");
 derive_csi_sequence!("Italic text.", Italic, "3m");
 derive_csi_sequence!("Underlined text.", Underline, "4m");
 derive_csi_sequence!("Blinking text (not widely supported).", Blink, "5m");
+derive_csi_sequence!("Rapidly blinking text (not widely supported).", RapidBlink, "6m");
 derive_csi_sequence!("Inverted colors (negative mode).", Invert, "7m");
-derive_csi_sequence!("Crossed out text (not widely supported).", CrossedOut, "9m");
-derive_csi_sequence!("Undo bold text.", NoBold, "21m");
-derive_csi_sequence!("Undo fainted text (not widely supported).", NoFaint, "22m");
+derive_csi_sequence!("Concealed (invisible) text, e.g. for passwords (not widely supported).",
+                     Conceal,
+                     "8m");
+derive_csi_sequence!("Crossed out text (strikethrough).", CrossedOut, "9m");
+derive_csi_sequence!("Doubly underlined text (not widely supported).", DoubleUnderline, "21m");
+derive_csi_sequence!("Undo bold text. This undoes fainted text as well, since terminals undo both \
+                      with the same sequence.",
+                     NoBold,
+                     "22m");
+derive_csi_sequence!("Undo fainted text (not widely supported). This undoes bold text as well, \
+                      like `NoBold`.",
+                     NoFaint,
+                     "22m");
+derive_csi_sequence!("Undo both bold and fainted text (normal intensity).", NoBoldFaint, "22m");
 derive_csi_sequence!("Undo italic text.", NoItalic, "23m");
-derive_csi_sequence!("Undo underlined text.", NoUnderline, "24m");
-derive_csi_sequence!("Undo blinking text (not widely supported).", NoBlink, "25m");
+derive_csi_sequence!("Undo underlined text, including doubly underlined text.", NoUnderline, "24m");
+derive_csi_sequence!("Undo blinking text, including rapidly blinking text (not widely supported).",
+                     NoBlink,
+                     "25m");
 derive_csi_sequence!("Undo inverted colors (negative mode).", NoInvert, "27m");
-derive_csi_sequence!("Undo crossed out text (not widely supported).", NoCrossedOut, "29m");
+derive_csi_sequence!("Reveal concealed text (not widely supported).", Reveal, "28m");
+derive_csi_sequence!("Undo crossed out text.", NoCrossedOut, "29m");
 derive_csi_sequence!("Framed text (not widely supported).", Framed, "51m");
+derive_csi_sequence!("Undo framed text (not widely supported).", NoFramed, "54m");
+derive_csi_sequence!("Curly underlined text (undercurl, not widely supported).", CurlyUnderline,
+                     "4:3m");
+derive_csi_sequence!("Dotted underlined text (not widely supported).", DottedUnderline, "4:4m");
+derive_csi_sequence!("Dashed underlined text (not widely supported).", DashedUnderline, "4:5m");
+derive_csi_sequence!("Overlined text.", Overline, "53m");
+derive_csi_sequence!("Undo overlined text.", NoOverline, "55m");
+
+/// A style of underlined text.
+///
+/// The styles besides `Single` are an extension of kitty, VTE and others (`ESC [ 4 : n m`), used
+/// e.g. by editors for marking diagnostics. Other terminals may show something else entirely for
+/// them, such as italic text, so they should only be written when supported, see `sequence`.
+///
+/// # Example
+///
+/// ```rust
+/// use termion::style::{self, UnderlineStyle};
+///
+/// let curly = UnderlineStyle::Curly.sequence(style::styled_underlines_supported());
+/// println!("{}Misspeled{}", curly, style::NoUnderline);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum UnderlineStyle {
+    /// A single straight line, like `Underline`.
+    Single,
+    /// Two straight lines.
+    Double,
+    /// A wavy line.
+    Curly,
+    /// A dotted line.
+    Dotted,
+    /// A dashed line.
+    Dashed,
+}
+
+impl UnderlineStyle {
+    /// The sequence turning on this style, or plain underlining if the styles aren't `supported`.
+    pub fn sequence(self, supported: bool) -> &'static str {
+        match (self, supported) {
+            (UnderlineStyle::Single, _) | (_, false) => csi!("4m"),
+            (UnderlineStyle::Double, true) => csi!("4:2m"),
+            (UnderlineStyle::Curly, true) => csi!("4:3m"),
+            (UnderlineStyle::Dotted, true) => csi!("4:4m"),
+            (UnderlineStyle::Dashed, true) => csi!("4:5m"),
+        }
+    }
+}
+
+impl fmt::Display for UnderlineStyle {
+    /// Write the sequence of this style, assuming it is supported.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.sequence(true))
+    }
+}
+
+/// Detect whether the terminal supports the underline styles of `UnderlineStyle`, from the
+/// environment.
+///
+/// This recognizes kitty, WezTerm, foot, Ghostty, Contour and VTE based terminals (such as GNOME
+/// Terminal). Others may support the styles as well.
+pub fn styled_underlines_supported() -> bool {
+    let var = |name| env::var(name).ok();
+    styled_underlines_from_env(var("TERM").as_ref().map(|s| &s[..]),
+                               var("TERM_PROGRAM").as_ref().map(|s| &s[..]),
+                               var("VTE_VERSION").as_ref().map(|s| &s[..]))
+}
+
+/// Whether the underline styles are supported according to `TERM`, `TERM_PROGRAM` and
+/// `VTE_VERSION`.
+fn styled_underlines_from_env(term: Option<&str>,
+                              program: Option<&str>,
+                              vte_version: Option<&str>)
+                              -> bool {
+    let terminals = ["kitty", "wezterm", "foot", "ghostty", "contour"];
+    term.is_some_and(|term| terminals.iter().any(|t| term.contains(t))) ||
+    program == Some("WezTerm") ||
+    // VTE supports them since 0.51.2.
+    vte_version.and_then(|v| v.parse::<u32>().ok()).is_some_and(|v| v >= 5102)
+}
+
+/// A set of text attributes, like bold or italic text.
+///
+/// The attributes are combined with `|`, e.g. `Attributes::BOLD | Attributes::ITALIC`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Attributes(u16);
+
+impl Attributes {
+    /// No attributes.
+    pub const NONE: Attributes = Attributes(0);
+    /// Bold text, see `Bold`.
+    pub const BOLD: Attributes = Attributes(1);
+    /// Fainted text, see `Faint`.
+    pub const FAINT: Attributes = Attributes(1 << 1);
+    /// Italic text, see `Italic`.
+    pub const ITALIC: Attributes = Attributes(1 << 2);
+    /// Underlined text, see `Underline`.
+    pub const UNDERLINE: Attributes = Attributes(1 << 3);
+    /// Blinking text, see `Blink`.
+    pub const BLINK: Attributes = Attributes(1 << 4);
+    /// Inverted colors, see `Invert`.
+    pub const INVERT: Attributes = Attributes(1 << 5);
+    /// Crossed out text, see `CrossedOut`.
+    pub const CROSSED_OUT: Attributes = Attributes(1 << 6);
+    /// Overlined text, see `Overline`.
+    pub const OVERLINE: Attributes = Attributes(1 << 7);
+    /// Curly underlined text, see `CurlyUnderline`.
+    pub const CURLY_UNDERLINE: Attributes = Attributes(1 << 8);
+    /// Rapidly blinking text, see `RapidBlink`.
+    pub const RAPID_BLINK: Attributes = Attributes(1 << 9);
+    /// Concealed text, see `Conceal`.
+    pub const CONCEAL: Attributes = Attributes(1 << 10);
+
+    /// Whether all of `other` are set.
+    pub fn contains(self, other: Attributes) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    /// Whether no attributes are set.
+    pub fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    /// The attributes set in `self` but not in `other`.
+    pub fn difference(self, other: Attributes) -> Attributes {
+        Attributes(self.0 & !other.0)
+    }
+}
+
+impl ops::BitOr for Attributes {
+    type Output = Attributes;
+
+    fn bitor(self, other: Attributes) -> Attributes {
+        Attributes(self.0 | other.0)
+    }
+}
+
+impl ops::BitOrAssign for Attributes {
+    fn bitor_assign(&mut self, other: Attributes) {
+        self.0 |= other.0;
+    }
+}
+
+/// The attributes with the SGR parameters setting and undoing them.
+const SGR_ATTRIBUTES: [(Attributes, &str, &str); 11] = [
+    (Attributes::BOLD, "1", "22"),
+    (Attributes::FAINT, "2", "22"),
+    (Attributes::ITALIC, "3", "23"),
+    (Attributes::UNDERLINE, "4", "24"),
+    (Attributes::BLINK, "5", "25"),
+    (Attributes::INVERT, "7", "27"),
+    (Attributes::CROSSED_OUT, "9", "29"),
+    (Attributes::OVERLINE, "53", "55"),
+    (Attributes::CURLY_UNDERLINE, "4:3", "24"),
+    (Attributes::RAPID_BLINK, "6", "25"),
+    (Attributes::CONCEAL, "8", "28"),
+];
+
+/// The SGR parameters setting `attributes`.
+fn attribute_params(attributes: Attributes) -> Vec<&'static str> {
+    SGR_ATTRIBUTES.iter()
+        .filter(|&&(attribute, _, _)| attributes.contains(attribute))
+        .map(|&(_, param, _)| param)
+        .collect()
+}
+
+/// A value written with a style, followed by a reset of all attributes.
+///
+/// This is built by chaining the attributes to set, which are written before the value. Note
+/// that the reset undoes the attributes set outside as well.
+///
+/// # Example
+///
+/// ```rust
+/// use termion::color;
+/// use termion::style::Styled;
+///
+/// let warning = Styled::new("warning").fg(color::Yellow).bold();
+/// assert_eq!(warning.to_string(), "\x1B[1m\x1B[33mwarning\x1B[m");
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct Styled<T: fmt::Display> {
+    value: T,
+    fg: Option<AnyColor>,
+    bg: Option<AnyColor>,
+    underline_color: Option<AnyColor>,
+    attributes: Attributes,
+}
+
+macro_rules! styled_attribute {
+    ($doc:expr, $name:ident, $attribute:ident) => {
+        #[doc = $doc]
+        pub fn $name(mut self) -> Styled<T> {
+            self.attributes |= Attributes::$attribute;
+            self
+        }
+    };
+}
+
+impl<T: fmt::Display> Styled<T> {
+    /// Write `value` without any style, until the attributes are set.
+    pub fn new(value: T) -> Styled<T> {
+        Styled {
+            value,
+            fg: None,
+            bg: None,
+            underline_color: None,
+            attributes: Attributes::NONE,
+        }
+    }
+
+    /// Set the foreground color.
+    pub fn fg<C: Into<AnyColor>>(mut self, color: C) -> Styled<T> {
+        self.fg = Some(color.into());
+        self
+    }
+
+    /// Set the background color.
+    pub fn bg<C: Into<AnyColor>>(mut self, color: C) -> Styled<T> {
+        self.bg = Some(color.into());
+        self
+    }
+
+    /// Set the underline color, see `color::UnderlineColor`.
+    pub fn underline_color<C: Into<AnyColor>>(mut self, color: C) -> Styled<T> {
+        self.underline_color = Some(color.into());
+        self
+    }
+
+    /// Set the given attributes, in addition to those already set.
+    pub fn attributes(mut self, attributes: Attributes) -> Styled<T> {
+        self.attributes |= attributes;
+        self
+    }
+
+    styled_attribute!("Make the value bold, see `Bold`.", bold, BOLD);
+    styled_attribute!("Make the value faint, see `Faint`.", faint, FAINT);
+    styled_attribute!("Make the value italic, see `Italic`.", italic, ITALIC);
+    styled_attribute!("Underline the value, see `Underline`.", underline, UNDERLINE);
+    styled_attribute!("Make the value blink, see `Blink`.", blink, BLINK);
+    styled_attribute!("Invert the colors of the value, see `Invert`.", invert, INVERT);
+    styled_attribute!("Cross out the value, see `CrossedOut`.", crossed_out, CROSSED_OUT);
+    styled_attribute!("Overline the value, see `Overline`.", overline, OVERLINE);
+    styled_attribute!("Underline the value with a wavy line, see `CurlyUnderline`.",
+                      curly_underline,
+                      CURLY_UNDERLINE);
+    styled_attribute!("Make the value blink rapidly, see `RapidBlink`.", rapid_blink, RAPID_BLINK);
+    styled_attribute!("Conceal the value, see `Conceal`.", conceal, CONCEAL);
+
+    /// Get a reference to the value.
+    pub fn get_ref(&self) -> &T {
+        &self.value
+    }
+
+    /// Get back the value.
+    pub fn into_inner(self) -> T {
+        self.value
+    }
+}
+
+impl<T: fmt::Display> fmt::Display for Styled<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let styled = !self.attributes.is_empty() || self.fg.is_some() || self.bg.is_some() ||
+                     self.underline_color.is_some();
+        if !styled {
+            return self.value.fmt(f);
+        }
+
+        if !self.attributes.is_empty() {
+            write!(f, csi!("{}m"), attribute_params(self.attributes).join(";"))?;
+        }
+        if let Some(color) = self.fg {
+            write!(f, "{}", Fg(color))?;
+        }
+        if let Some(color) = self.bg {
+            write!(f, "{}", Bg(color))?;
+        }
+        if let Some(color) = self.underline_color {
+            write!(f, "{}", UnderlineColor(color))?;
+        }
+        self.value.fmt(f)?;
+        write!(f, "{}", Reset)
+    }
+}
+
+/// Text linked to a URL (OSC 8), which can be clicked in supporting terminals.
+///
+/// Other terminals show just the text. Cells with the same `id` (and URL) are highlighted
+/// together when hovered, e.g. for a link split over several lines.
+///
+/// # Example
+///
+/// ```rust
+/// use termion::style::Hyperlink;
+///
+/// let link = Hyperlink::new("https://example.com", "example");
+/// assert_eq!(link.to_string(),
+///            "\x1B]8;;https://example.com\x1B\\example\x1B]8;;\x1B\\");
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct Hyperlink<'a, T: fmt::Display> {
+    url: &'a str,
+    id: Option<&'a str>,
+    text: T,
+}
+
+impl<'a, T: fmt::Display> Hyperlink<'a, T> {
+    /// Link `text` to `url`.
+    ///
+    /// Characters terminals don't allow in URLs, such as spaces and non-ASCII characters, are
+    /// percent-encoded.
+    pub fn new(url: &'a str, text: T) -> Hyperlink<'a, T> {
+        Hyperlink {
+            url,
+            id: None,
+            text,
+        }
+    }
+
+    /// Identify the link, so its parts written separately are highlighted together.
+    ///
+    /// Colons, semicolons and control characters are left out of the id.
+    pub fn id(mut self, id: &'a str) -> Hyperlink<'a, T> {
+        self.id = Some(id);
+        self
+    }
+}
+
+impl<'a, T: fmt::Display> fmt::Display for Hyperlink<'a, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("\x1B]8;")?;
+        if let Some(id) = self.id {
+            f.write_str("id=")?;
+            for c in id.chars().filter(|&c| c != ':' && c != ';' && !c.is_control()) {
+                write!(f, "{}", c)?;
+            }
+        }
+        f.write_str(";")?;
+        for &byte in self.url.as_bytes() {
+            if (33..=126).contains(&byte) {
+                write!(f, "{}", byte as char)?;
+            } else {
+                write!(f, "%{:02X}", byte)?;
+            }
+        }
+        write!(f, "\x1B\\{}\x1B]8;;\x1B\\", self.text)
+    }
+}
+
+/// The complete style of text: its colors and attributes.
+///
+/// The default style is that of reset terminals, without attributes and with the default colors.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Style {
+    /// The foreground color.
+    pub fg: AnyColor,
+    /// The background color.
+    pub bg: AnyColor,
+    /// The underline color, see `color::UnderlineColor`.
+    pub underline_color: AnyColor,
+    /// The attributes.
+    pub attributes: Attributes,
+}
+
+impl Default for Style {
+    fn default() -> Style {
+        Style {
+            fg: AnyColor::Reset,
+            bg: AnyColor::Reset,
+            underline_color: AnyColor::Reset,
+            attributes: Attributes::NONE,
+        }
+    }
+}
+
+impl Style {
+    /// Change the style like a terminal does for the SGR sequence with the given parameters,
+    /// e.g. `"1;31"` for `ESC [ 1 ; 31 m`.
+    ///
+    /// Unknown parameters are ignored. Doubly and otherwise styled underlines are taken as
+    /// underlined text.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use termion::color::AnyColor;
+    /// use termion::style::{Attributes, Style};
+    ///
+    /// let mut style = Style::default();
+    /// style.apply_sgr("1;38;5;208");
+    /// assert_eq!(style.attributes, Attributes::BOLD);
+    /// assert_eq!(style.fg, AnyColor::Indexed(208));
+    /// ```
+    pub fn apply_sgr(&mut self, params: &str) {
+        let mut params = params.split(';');
+        while let Some(param) = params.next() {
+            let mut subs = param.split(':');
+            let target = match subs.next().unwrap_or("") {
+                "" | "0" => {
+                    *self = Style::default();
+                    continue;
+                }
+                "38" => &mut self.fg,
+                "48" => &mut self.bg,
+                "58" => &mut self.underline_color,
+                "39" => {
+                    self.fg = AnyColor::Reset;
+                    continue;
+                }
+                "49" => {
+                    self.bg = AnyColor::Reset;
+                    continue;
+                }
+                "59" => {
+                    self.underline_color = AnyColor::Reset;
+                    continue;
+                }
+                _ => {
+                    self.apply_attribute(param);
+                    continue;
+                }
+            };
+
+            // Either `38;5;n` and `38;2;r;g;b`, or their colon-separated forms (`38:2::r:g:b`).
+            let nums: Vec<Option<u8>> = if param.contains(':') {
+                subs.filter(|sub| !sub.is_empty()).map(|sub| sub.parse().ok()).collect()
+            } else {
+                let len = match params.clone().next() {
+                    Some("5") => 2,
+                    Some("2") => 4,
+                    _ => 0,
+                };
+                params.by_ref().take(len).map(|sub| sub.parse().ok()).collect()
+            };
+            match nums[..] {
+                [Some(5), Some(n)] => *target = AnyColor::Indexed(n),
+                [Some(2), Some(r), Some(g), Some(b)] |
+                [Some(2), _, Some(r), Some(g), Some(b)] => *target = AnyColor::Rgb(r, g, b),
+                _ => {}
+            }
+        }
+    }
+
+    /// Change the attributes or basic colors for the SGR parameter `param`.
+    fn apply_attribute(&mut self, param: &str) {
+        match param.parse::<u8>() {
+            Ok(n @ 30..=37) => self.fg = AnyColor::Ansi(n - 30),
+            Ok(n @ 40..=47) => self.bg = AnyColor::Ansi(n - 40),
+            Ok(n @ 90..=97) => self.fg = AnyColor::Ansi(n - 90 + 8),
+            Ok(n @ 100..=107) => self.bg = AnyColor::Ansi(n - 100 + 8),
+            _ => {}
+        }
+
+        let underlines = Attributes::UNDERLINE | Attributes::CURLY_UNDERLINE;
+        match param {
+            "4" | "4:1" | "4:2" | "4:4" | "4:5" | "21" => {
+                self.attributes = self.attributes.difference(underlines) | Attributes::UNDERLINE;
+            }
+            "4:3" => {
+                self.attributes = self.attributes.difference(underlines) |
+                                  Attributes::CURLY_UNDERLINE;
+            }
+            "4:0" => self.attributes = self.attributes.difference(underlines),
+            _ => {
+                for &(attribute, on, off) in &SGR_ATTRIBUTES {
+                    if param == on {
+                        self.attributes |= attribute;
+                    } else if param == off {
+                        self.attributes = self.attributes.difference(attribute);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Tracks the style of the terminal, so changing it only takes the parameters which differ.
+///
+/// Renderers changing the style from cell to cell can write a lot less this way, e.g. only a new
+/// foreground color instead of a reset followed by every attribute. The state has to see every
+/// change of the style, so styles should not be written besides `transition`.
+///
+/// # Example
+///
+/// ```rust
+/// use termion::color;
+/// use termion::style::{Attributes, Style, StyleState};
+///
+/// let mut out = Vec::new();
+/// let mut state = StyleState::new();
+/// let bold = Style { attributes: Attributes::BOLD, ..Style::default() };
+/// state.transition(&mut out, &bold).unwrap();
+/// state.transition(&mut out, &Style { fg: color::Red.into(), ..bold }).unwrap();
+/// state.transition(&mut out, &Style { fg: color::Red.into(), ..bold }).unwrap();
+/// assert_eq!(out, b"\x1B[0;1m\x1B[31m");
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct StyleState {
+    /// The style of the terminal, if known.
+    current: Option<Style>,
+}
+
+impl StyleState {
+    /// Track a terminal of an unknown style, so the first transition resets it.
+    pub fn new() -> StyleState {
+        StyleState { current: None }
+    }
+
+    /// Track a terminal of the given style.
+    pub fn with_style(style: Style) -> StyleState {
+        StyleState { current: Some(style) }
+    }
+
+    /// The style of the terminal, if known.
+    pub fn current(&self) -> Option<&Style> {
+        self.current.as_ref()
+    }
+
+    /// Forget the style of the terminal, e.g. after writing styles elsewhere, so the next
+    /// transition resets it.
+    pub fn invalidate(&mut self) {
+        self.current = None;
+    }
+
+    /// Write the shortest SGR sequence changing the style to `target`, if it differs.
+    pub fn transition<W: Write>(&mut self, w: &mut W, target: &Style) -> io::Result<()> {
+        let params = match self.current {
+            Some(ref current) if current == target => return Ok(()),
+            Some(ref current) => {
+                let delta = delta_params(current, target);
+                let full = full_params(target);
+                if delta.join(";").len() <= full.join(";").len() {
+                    delta
+                } else {
+                    full
+                }
+            }
+            None => full_params(target),
+        };
+        write!(w, csi!("{}m"), params.join(";"))?;
+        self.current = Some(*target);
+        Ok(())
+    }
+}
+
+/// The SGR parameters setting `color` as foreground (`target` 38), background (48) or underline
+/// (58) color.
+fn color_param(target: u8, color: AnyColor) -> String {
+    let sequence = match target {
+        38 => Fg(color).to_string(),
+        48 => Bg(color).to_string(),
+        _ => UnderlineColor(color).to_string(),
+    };
+    // The sequence without `ESC [` and the final `m`.
+    sequence[2..sequence.len() - 1].to_string()
+}
+
+/// The SGR parameters resetting the terminal and setting `style`.
+fn full_params(style: &Style) -> Vec<String> {
+    let mut params = vec!["0".to_string()];
+    params.extend(attribute_params(style.attributes).into_iter().map(String::from));
+    for &(target, color) in &[(38, style.fg), (48, style.bg), (58, style.underline_color)] {
+        if color != AnyColor::Reset {
+            params.push(color_param(target, color));
+        }
+    }
+    params
+}
+
+/// The SGR parameters changing the style from `current` to `target`.
+fn delta_params(current: &Style, target: &Style) -> Vec<String> {
+    let mut params: Vec<String> = Vec::new();
+
+    // Some attributes are undone together (e.g. bold and faint), so the others are set again.
+    let mut set = target.attributes.difference(current.attributes);
+    let removed = current.attributes.difference(target.attributes);
+    for &(attribute, _, undo) in &SGR_ATTRIBUTES {
+        if !removed.contains(attribute) || params.iter().any(|p| p == undo) {
+            continue;
+        }
+        params.push(undo.to_string());
+        for &(other, _, other_undo) in &SGR_ATTRIBUTES {
+            if other_undo == undo && target.attributes.contains(other) {
+                set |= other;
+            }
+        }
+    }
+    params.extend(attribute_params(set).into_iter().map(String::from));
+
+    let colors = [(38, current.fg, target.fg),
+                  (48, current.bg, target.bg),
+                  (58, current.underline_color, target.underline_color)];
+    for &(target, from, to) in &colors {
+        if from != to {
+            params.push(color_param(target, to));
+        }
+    }
+    params
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_styled() {
+        use color::{Blue, Reset, Rgb};
+
+        assert_eq!(Styled::new(42).to_string(), "42");
+        assert_eq!(Styled::new("x").italic().bold().crossed_out().to_string(),
+                   "\x1B[1;3;9mx\x1B[m");
+        assert_eq!(Styled::new("x").bg(Blue).fg(Rgb(1, 2, 3)).underline_color(Reset).to_string(),
+                   "\x1B[38;2;1;2;3m\x1B[44m\x1B[59mx\x1B[m");
+        assert_eq!(Styled::new("x").curly_underline().into_inner(), "x");
+        assert_eq!(Styled::new("x").conceal().rapid_blink().to_string(), "\x1B[6;8mx\x1B[m");
+    }
+
+    #[test]
+    fn test_style_state() {
+        use color::{AnsiValue, Red};
+
+        let transitions = |styles: &[Style]| {
+            let mut out = Vec::new();
+            let mut state = StyleState::new();
+            for style in styles {
+                state.transition(&mut out, style).unwrap();
+            }
+            String::from_utf8(out).unwrap()
+        };
+
+        let plain = Style::default();
+        let bold = Style { attributes: Attributes::BOLD | Attributes::FAINT, ..plain };
+        let red = Style { fg: Red.into(), bg: AnsiValue(200).into(), ..bold };
+        assert_eq!(transitions(&[plain, plain]), "\x1B[0m");
+        assert_eq!(transitions(&[bold, red, plain]),
+                   "\x1B[0;1;2m\x1B[31;48;5;200m\x1B[0m");
+
+        // Undoing bold undoes faint as well, which is set again.
+        let faint = Style { attributes: Attributes::FAINT | Attributes::ITALIC, ..red };
+        assert_eq!(transitions(&[red, faint]), "\x1B[0;1;2;31;48;5;200m\x1B[22;2;3m");
+
+        let curly = Style { attributes: Attributes::CURLY_UNDERLINE, ..plain };
+        let underline = Style { attributes: Attributes::UNDERLINE, ..plain };
+        // Resetting everything is shorter here.
+        assert_eq!(transitions(&[curly, underline, plain]), "\x1B[0;4:3m\x1B[0;4m\x1B[0m");
+
+        let blink = Style { attributes: Attributes::BLINK | Attributes::RAPID_BLINK, ..red };
+        let rapid = Style { attributes: Attributes::RAPID_BLINK | Attributes::CONCEAL, ..red };
+        assert_eq!(transitions(&[blink, rapid]), "\x1B[0;5;6;31;48;5;200m\x1B[25;6;8m");
+
+        // Applying the written parameters gives the styles back.
+        let mut style = Style::default();
+        for params in ["0;5;6;31;48;5;200", "25;6;8"] {
+            style.apply_sgr(params);
+        }
+        assert_eq!(style, rapid);
+
+        let mut state = StyleState::with_style(plain);
+        let mut out = Vec::new();
+        state.transition(&mut out, &Style { underline_color: Red.into(), ..plain }).unwrap();
+        assert_eq!(out, b"\x1B[58;5;1m");
+        state.invalidate();
+        assert_eq!(state.current(), None);
+    }
+
+    #[test]
+    fn test_apply_sgr() {
+        let mut style = Style::default();
+        style.apply_sgr("1;2;4:3;38:2::1:2:3;48:5:17;58;2;4;5;6;91");
+        assert_eq!(style,
+                   Style {
+                       fg: AnyColor::Ansi(9),
+                       bg: AnyColor::Indexed(17),
+                       underline_color: AnyColor::Rgb(4, 5, 6),
+                       attributes: Attributes::BOLD | Attributes::FAINT |
+                                   Attributes::CURLY_UNDERLINE,
+                   });
+
+        style.apply_sgr("22;21;39;49;59;53;x;38;5");
+        let attributes = Attributes::UNDERLINE | Attributes::OVERLINE;
+        assert_eq!(style, Style { attributes, ..Style::default() });
+        style.apply_sgr("4:0;9");
+        assert_eq!(style.attributes, Attributes::OVERLINE | Attributes::CROSSED_OUT);
+        style.apply_sgr("");
+        assert_eq!(style, Style::default());
+    }
+
+    #[test]
+    fn test_hyperlink() {
+        let link = Hyperlink::new("file:///tmp/a b/ü", Styled::new("a").bold()).id("x;1:\x07y");
+        assert_eq!(link.to_string(),
+                   "\x1B]8;id=x1y;file:///tmp/a%20b/%C3%BC\x1B\\\x1B[1ma\x1B[m\x1B]8;;\x1B\\");
+    }
+
+    #[test]
+    fn test_underline_style() {
+        assert_eq!(UnderlineStyle::Curly.to_string(), "\x1B[4:3m");
+        assert_eq!(UnderlineStyle::Curly.sequence(false), "\x1B[4m");
+        assert_eq!(UnderlineStyle::Dashed.sequence(true), DashedUnderline.to_string());
+        assert_eq!(UnderlineStyle::Single.sequence(true), Underline.to_string());
+        // `ESC [ 21 m` is a double underline in most terminals.
+        assert_eq!(NoBold.to_string(), "\x1B[22m");
+
+        assert!(styled_underlines_from_env(Some("xterm-kitty"), None, None));
+        assert!(styled_underlines_from_env(Some("xterm-256color"), Some("WezTerm"), None));
+        assert!(styled_underlines_from_env(Some("xterm-256color"), None, Some("6800")));
+        assert!(!styled_underlines_from_env(Some("xterm-256color"), None, Some("5000")));
+        assert!(!styled_underlines_from_env(Some("xterm"), Some("Apple_Terminal"), None));
+    }
+}
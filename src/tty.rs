@@ -1,5 +1,24 @@
 use std::{fs, io};
+use std::io::Read;
+#[cfg(any(feature = "mio", feature = "tokio"))]
+use std::io::Write;
 use std::os::unix::io::AsRawFd;
+#[cfg(feature = "tokio")]
+use std::pin::Pin;
+#[cfg(feature = "tokio")]
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+#[cfg(feature = "mio")]
+use mio::{Interest, Registry, Token};
+#[cfg(feature = "mio")]
+use mio::event::Source;
+#[cfg(feature = "mio")]
+use mio::unix::SourceFd;
+#[cfg(feature = "tokio")]
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+#[cfg(feature = "tokio")]
+use tokio::io::unix::AsyncFd;
 
 /// Is this stream an TTY?
 #[cfg(not(target_os = "redox"))]
@@ -32,3 +51,285 @@ pub fn get_tty() -> io::Result<fs::File> {
 pub fn get_tty() -> io::Result<fs::File> {
     fs::OpenOptions::new().read(true).write(true).open("/dev/tty")
 }
+
+/// Get the TTY device, opened in non-blocking mode.
+///
+/// Reads and writes which would block fail with `io::ErrorKind::WouldBlock` instead, so the device
+/// can be driven by an event loop rather than a dedicated thread, see `AsyncTty` for tokio and
+/// `TtySource` for mio.
+///
+/// The non-blocking mode belongs to this file description only, so other handles of the TTY (like
+/// the standard streams) are unaffected.
+#[cfg(not(target_os = "redox"))]
+pub fn get_tty_nonblocking() -> io::Result<fs::File> {
+    use libc;
+    use std::os::unix::fs::OpenOptionsExt;
+
+    fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .custom_flags(libc::O_NONBLOCK)
+        .open("/dev/tty")
+}
+
+/// The TTY device as a mio event source, so its input is polled along with the other sources.
+///
+/// The device is in non-blocking mode (see `get_tty_nonblocking`), so reading and writing fail
+/// with `io::ErrorKind::WouldBlock` until it is reported ready again.
+///
+/// This needs the `mio` feature.
+#[cfg(all(feature = "mio", not(target_os = "redox")))]
+pub struct TtySource {
+    file: fs::File,
+}
+
+#[cfg(all(feature = "mio", not(target_os = "redox")))]
+impl TtySource {
+    /// Open the TTY device.
+    pub fn new() -> io::Result<TtySource> {
+        Ok(TtySource { file: get_tty_nonblocking()? })
+    }
+}
+
+#[cfg(all(feature = "mio", not(target_os = "redox")))]
+impl Source for TtySource {
+    fn register(&mut self, registry: &Registry, token: Token, interests: Interest)
+                -> io::Result<()> {
+        SourceFd(&self.file.as_raw_fd()).register(registry, token, interests)
+    }
+
+    fn reregister(&mut self, registry: &Registry, token: Token, interests: Interest)
+                  -> io::Result<()> {
+        SourceFd(&self.file.as_raw_fd()).reregister(registry, token, interests)
+    }
+
+    fn deregister(&mut self, registry: &Registry) -> io::Result<()> {
+        SourceFd(&self.file.as_raw_fd()).deregister(registry)
+    }
+}
+
+#[cfg(all(feature = "mio", not(target_os = "redox")))]
+impl Read for TtySource {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.file.read(buf)
+    }
+}
+
+#[cfg(all(feature = "mio", not(target_os = "redox")))]
+impl Write for TtySource {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.file.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+#[cfg(all(feature = "mio", not(target_os = "redox")))]
+impl AsRawFd for TtySource {
+    fn as_raw_fd(&self) -> ::std::os::unix::io::RawFd {
+        self.file.as_raw_fd()
+    }
+}
+
+/// The TTY device registered with the tokio reactor, so it is read and written asynchronously,
+/// without blocking a thread.
+///
+/// This needs the `tokio` feature.
+#[cfg(all(feature = "tokio", not(target_os = "redox")))]
+pub struct AsyncTty {
+    inner: AsyncFd<fs::File>,
+}
+
+#[cfg(all(feature = "tokio", not(target_os = "redox")))]
+impl AsyncTty {
+    /// Open the TTY device (see `get_tty_nonblocking`) and register it with the reactor.
+    ///
+    /// # Panics
+    ///
+    /// This panics if called outside of a tokio runtime with IO enabled.
+    pub fn new() -> io::Result<AsyncTty> {
+        AsyncTty::register(get_tty_nonblocking()?)
+    }
+
+    /// Register `file`, which has to be in non-blocking mode.
+    fn register(file: fs::File) -> io::Result<AsyncTty> {
+        Ok(AsyncTty { inner: AsyncFd::new(file)? })
+    }
+}
+
+#[cfg(all(feature = "tokio", not(target_os = "redox")))]
+impl AsyncRead for AsyncTty {
+    fn poll_read(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut ReadBuf<'_>)
+                 -> Poll<io::Result<()>> {
+        loop {
+            let mut guard = match self.inner.poll_read_ready(cx) {
+                Poll::Ready(guard) => guard?,
+                Poll::Pending => return Poll::Pending,
+            };
+            match guard.try_io(|inner| inner.get_ref().read(buf.initialize_unfilled())) {
+                Ok(Ok(n)) => {
+                    buf.advance(n);
+                    return Poll::Ready(Ok(()));
+                }
+                Ok(Err(err)) => return Poll::Ready(Err(err)),
+                // The readiness was stale, and has been cleared.
+                Err(_) => {}
+            }
+        }
+    }
+}
+
+#[cfg(all(feature = "tokio", not(target_os = "redox")))]
+impl AsyncWrite for AsyncTty {
+    fn poll_write(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8])
+                  -> Poll<io::Result<usize>> {
+        loop {
+            let mut guard = match self.inner.poll_write_ready(cx) {
+                Poll::Ready(guard) => guard?,
+                Poll::Pending => return Poll::Pending,
+            };
+            if let Ok(result) = guard.try_io(|inner| inner.get_ref().write(buf)) {
+                return Poll::Ready(result);
+            }
+        }
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        // Writes go straight to the device.
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+}
+
+/// Read a byte from the TTY device `tty`, waiting up to `timeout` for it to arrive.
+///
+/// Returns `None` if the timeout expires first.
+#[cfg(not(target_os = "redox"))]
+pub fn read_byte_timeout(mut tty: &fs::File, timeout: Duration) -> io::Result<Option<u8>> {
+    use libc;
+
+    let millis = timeout.as_secs()
+        .saturating_mul(1000)
+        .saturating_add(u64::from(timeout.subsec_millis()));
+    let mut fd = libc::pollfd {
+        fd: tty.as_raw_fd(),
+        events: libc::POLLIN,
+        revents: 0,
+    };
+
+    loop {
+        match unsafe { libc::poll(&mut fd, 1, millis.min(libc::c_int::MAX as u64) as _) } {
+            0 => return Ok(None),
+            -1 => {
+                let err = io::Error::last_os_error();
+                if err.kind() != io::ErrorKind::Interrupted {
+                    return Err(err);
+                }
+            }
+            _ => break,
+        }
+    }
+
+    let mut byte = [0];
+    match tty.read(&mut byte)? {
+        0 => Err(io::Error::new(io::ErrorKind::UnexpectedEof, "The TTY device was closed")),
+        _ => Ok(Some(byte[0])),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_get_tty_nonblocking() {
+        // There may not be a controlling terminal to open.
+        if let Ok(mut tty) = get_tty_nonblocking() {
+            match tty.read(&mut [0]) {
+                Ok(n) => assert!(n <= 1),
+                Err(err) => assert_eq!(err.kind(), io::ErrorKind::WouldBlock),
+            }
+        }
+    }
+
+    /// A connected pair of sockets, standing in for the device since they are both readable and
+    /// writable. The first is in non-blocking mode.
+    #[cfg(any(feature = "mio", feature = "tokio"))]
+    fn socket_pair() -> (fs::File, fs::File) {
+        use libc;
+        use std::os::unix::io::FromRawFd;
+
+        let mut fds = [0; 2];
+        unsafe {
+            assert_eq!(libc::socketpair(libc::AF_UNIX, libc::SOCK_STREAM, 0, fds.as_mut_ptr()), 0);
+            libc::fcntl(fds[0], libc::F_SETFL, libc::O_NONBLOCK);
+            (fs::File::from_raw_fd(fds[0]), fs::File::from_raw_fd(fds[1]))
+        }
+    }
+
+    #[cfg(feature = "mio")]
+    #[test]
+    fn test_tty_source() {
+        use mio::{Events, Poll};
+
+        let (file, mut other) = socket_pair();
+        let mut tty = TtySource { file };
+        let mut poll = Poll::new().unwrap();
+        poll.registry().register(&mut tty, Token(1), Interest::READABLE).unwrap();
+
+        let mut buf = [0; 4];
+        assert_eq!(tty.read(&mut buf).unwrap_err().kind(), io::ErrorKind::WouldBlock);
+        other.write_all(b"ab").unwrap();
+        let mut events = Events::with_capacity(4);
+        poll.poll(&mut events, Some(Duration::from_secs(10))).unwrap();
+        let event = events.iter().next().unwrap();
+        assert_eq!(event.token(), Token(1));
+        assert!(event.is_readable());
+        assert_eq!(tty.read(&mut buf).unwrap(), 2);
+        assert_eq!(&buf[..2], b"ab");
+
+        tty.write_all(b"cd").unwrap();
+        other.read_exact(&mut buf[..2]).unwrap();
+        assert_eq!(&buf[..2], b"cd");
+        poll.registry().deregister(&mut tty).unwrap();
+    }
+
+    #[cfg(feature = "tokio")]
+    #[test]
+    fn test_async_tty() {
+        use std::future;
+        use tokio::runtime::Builder;
+
+        let (file, mut other) = socket_pair();
+        let runtime = Builder::new_current_thread().enable_io().build().unwrap();
+        let mut tty = {
+            let _entered = runtime.enter();
+            AsyncTty::register(file).unwrap()
+        };
+
+        other.write_all(b"ab").unwrap();
+        let mut bytes = [0; 4];
+        let read = runtime.block_on(future::poll_fn(|cx| {
+            let mut buf = ReadBuf::new(&mut bytes);
+            match Pin::new(&mut tty).poll_read(cx, &mut buf) {
+                Poll::Ready(result) => Poll::Ready(result.map(|()| buf.filled().len())),
+                Poll::Pending => Poll::Pending,
+            }
+        }));
+        assert_eq!(read.unwrap(), 2);
+        assert_eq!(&bytes[..2], b"ab");
+
+        let written = runtime.block_on(future::poll_fn(|cx| {
+            Pin::new(&mut tty).poll_write(cx, b"cd")
+        }));
+        assert_eq!(written.unwrap(), 2);
+        let mut buf = [0; 2];
+        other.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf, b"cd");
+    }
+}
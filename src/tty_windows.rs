@@ -1,4 +1,10 @@
 use std::io;
+use std::mem;
+use std::ptr;
+use std::time::{Duration, Instant};
+
+use kernel32;
+use winapi;
 
 /// This will panic.
 pub fn is_tty(_stream: ()) -> bool {
@@ -9,3 +15,67 @@ pub fn is_tty(_stream: ()) -> bool {
 pub fn get_tty() -> io::Result<()> {
     unimplemented!()
 }
+
+/// Opening the TTY device is not supported on Windows, so this returns an error.
+pub fn get_tty_nonblocking() -> io::Result<()> {
+    Err(io::Error::new(io::ErrorKind::Other, "The TTY device can't be opened on this platform."))
+}
+
+/// Read a byte from the console input, waiting up to `timeout` for it to arrive.
+///
+/// Returns `None` if the timeout expires first. The console has to be in raw mode, so keys are
+/// reported as VT sequences.
+pub fn read_byte_timeout(timeout: Duration) -> io::Result<Option<u8>> {
+    let deadline = Instant::now() + timeout;
+
+    unsafe {
+        let handle = kernel32::GetStdHandle(winapi::STD_INPUT_HANDLE);
+        if handle == winapi::INVALID_HANDLE_VALUE {
+            return Err(io::Error::last_os_error());
+        }
+
+        // The handle is signaled by any input record, while reading the input blocks until a
+        // character is typed. So the records without one (key releases, focus and mouse events,
+        // etc.) are discarded while waiting.
+        loop {
+            let timeout = deadline.saturating_duration_since(Instant::now());
+            let millis = timeout.as_secs()
+                .saturating_mul(1000)
+                .saturating_add(u64::from(timeout.subsec_millis()));
+            let millis = millis.min(winapi::INFINITE as u64 - 1) as winapi::DWORD;
+            match kernel32::WaitForSingleObject(handle, millis) {
+                winapi::WAIT_OBJECT_0 => (),
+                winapi::WAIT_TIMEOUT => return Ok(None),
+                _ => return Err(io::Error::last_os_error()),
+            }
+
+            let mut record: winapi::INPUT_RECORD = mem::zeroed();
+            let mut count = 0;
+            if kernel32::PeekConsoleInputW(handle, &mut record, 1, &mut count) == 0 {
+                return Err(io::Error::last_os_error());
+            }
+            if count == 0 {
+                continue;
+            }
+            if record.EventType == winapi::KEY_EVENT {
+                let key = record.KeyEvent();
+                // Characters typed as their code on the keypad come with the release of Alt.
+                let alt = key.wVirtualKeyCode as winapi::c_int == winapi::VK_MENU;
+                if key.UnicodeChar != 0 && (key.bKeyDown != 0 || alt) {
+                    break;
+                }
+            }
+            if kernel32::ReadConsoleInputW(handle, &mut record, 1, &mut count) == 0 {
+                return Err(io::Error::last_os_error());
+            }
+        }
+
+        let mut byte = 0u8;
+        let mut read = 0;
+        let buf = &mut byte as *mut u8 as winapi::LPVOID;
+        if kernel32::ReadFile(handle, buf, 1, &mut read, ptr::null_mut()) == 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(if read == 1 { Some(byte) } else { None })
+    }
+}